@@ -31,6 +31,14 @@ container.push(Age(37u8));
 container.push(Name(format!("Name {}", "Robert")));
 container.push(Height(121u32));
 container.push(InfectionStatus::Recovered);
+container.push(Age(38u8));
+
+// Each type has its own `Vec`, so values come back last in, first out per type, regardless of
+// the order in which values of different types were pushed.
+assert_eq!(container.pop::<Age>(), Some(Age(38u8)));
+assert_eq!(container.pop::<Height>(), Some(Height(121u32)));
+assert_eq!(container.pop::<Height>(), None);
+assert_eq!(container.remove::<Age>(), Some(vec![Age(37u8)]));
 # }
 ```
 
@@ -55,6 +63,12 @@ pub struct AnyMap {
     map: HashMap<TypeId, Box<dyn Any>>,
 }
 
+impl Default for AnyMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AnyMap {
     pub fn new() -> AnyMap {
         AnyMap {
@@ -73,6 +87,36 @@ impl AnyMap {
         v.push(value);
     }
 
+    /// Removes the most recently pushed value of type `T`, or returns `None` if there is none.
+    pub fn pop<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .get_mut(&type_of::<T>())
+            // ToDo: Use `Any::downcast_mut_unchecked` (nightly feature). This is guaranteed safe,
+            //       because only a `Box<Vec<T>>` can be mapped to by `type_of::<T>()`.
+            .and_then(|v| unsafe { v.downcast_mut::<Vec<T>>().unwrap_unchecked() }.pop())
+    }
+
+    /// Removes the container for `T` and returns its values in the order they were pushed, or
+    /// returns `None` if no container for `T` exists.
+    pub fn remove<T: 'static>(&mut self) -> Option<Vec<T>> {
+        self.map
+            .remove(&type_of::<T>())
+            // ToDo: Use `Any::downcast_unchecked` (nightly feature). This is guaranteed safe,
+            //       because only a `Box<Vec<T>>` can be mapped to by `type_of::<T>()`.
+            .map(|v| *unsafe { v.downcast().unwrap_unchecked() })
+    }
+
+    /// Removes every container.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// The number of types that have a container.
+    #[must_use]
+    pub fn type_count(&self) -> usize {
+        self.map.len()
+    }
+
     pub fn get_container_mut<T: 'static>(&mut self) -> &mut Vec<T> {
         unsafe {
             self.map
@@ -85,6 +129,8 @@ impl AnyMap {
         }
     }
 
+    /// # Safety
+    /// The caller must guarantee that a container for `T` exists in the map.
     pub unsafe fn get_container_ref_unchecked<T: 'static>(&self) -> &Vec<T> { unsafe {
         self.map
             .get(&type_of::<T>())
@@ -110,8 +156,8 @@ impl AnyMap {
 /// # Parameters
 /// - `$name`: The name of the struct to define.
 /// - `$container<$generic $( : $traitfirst $(+ $traitrest)* )?>`: The type of container to use for
-///    each generic type with optional trait constraints (e.g., `Vec<T: Property + Send>`). The
-///    additional constraint that `$generic` is `'static` will be added automatically.
+///   each generic type with optional trait constraints (e.g., `Vec<T: Property + Send>`). The
+///   additional constraint that `$generic` is `'static` will be added automatically.
 /// - `$constructor`: An expression to construct a new instance of the container.
 /// - `$inserter`: A function or closure to insert a value into the container.
 ///
@@ -133,7 +179,6 @@ impl AnyMap {
 /// # Safety
 /// - The `get_container_ref_unchecked` method is unsafe because it uses `unwrap_unchecked`
 ///   to bypass runtime checks. Ensure the type exists in the map before calling this method.
-
 #[macro_export]
 macro_rules! define_any_map_container {
     (
@@ -198,9 +243,10 @@ pub use define_any_map_container;
 
 
 
+#[cfg(test)]
 mod tests {
+    use super::AnyMap;
     use std::hash::Hash;
-    use super::define_any_map_container;
 
     #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
     struct Age(u8);
@@ -233,4 +279,66 @@ mod tests {
             assert_eq!(vector[0], Name("Robert".to_string()));
         }
     }
+
+    #[test]
+    fn push_pop_lifo() {
+        let mut container = AnyMap::new();
+        container.push(Age(1));
+        container.push(Name("first".to_string()));
+        container.push(Age(2));
+        container.push(Name("second".to_string()));
+        container.push(Age(3));
+
+        assert_eq!(container.pop::<Age>(), Some(Age(3)));
+        assert_eq!(container.pop::<Name>(), Some(Name("second".to_string())));
+        assert_eq!(container.pop::<Age>(), Some(Age(2)));
+        assert_eq!(container.pop::<Age>(), Some(Age(1)));
+        assert_eq!(container.pop::<Age>(), None);
+        assert_eq!(container.pop::<Name>(), Some(Name("first".to_string())));
+        assert_eq!(container.pop::<u32>(), None);
+    }
+
+    #[test]
+    fn pop_each_type() {
+        let mut container = AnyMap::new();
+        container.push(Age(37));
+        container.push(Name("Robert".to_string()));
+        container.push(121_u32);
+
+        assert_eq!(container.pop::<u32>(), Some(121));
+        assert_eq!(container.pop::<Age>(), Some(Age(37)));
+        assert_eq!(container.pop::<Name>(), Some(Name("Robert".to_string())));
+        assert_eq!(container.pop::<u32>(), None);
+        assert_eq!(container.pop::<Age>(), None);
+        assert_eq!(container.pop::<Name>(), None);
+    }
+
+    #[test]
+    fn remove_container() {
+        let mut container = AnyMap::new();
+        container.push(Age(1));
+        container.push(Age(2));
+        container.push(Name("Robert".to_string()));
+
+        assert_eq!(container.remove::<Age>(), Some(vec![Age(1), Age(2)]));
+        assert_eq!(container.remove::<Age>(), None);
+        assert!(container.get_container_ref::<Age>().is_none());
+        assert_eq!(container.get_container_ref::<Name>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn clear() {
+        let mut container = AnyMap::new();
+        assert_eq!(container.type_count(), 0);
+        container.push(Age(1));
+        container.push(Age(2));
+        container.push(Name("Robert".to_string()));
+        assert_eq!(container.type_count(), 2);
+
+        container.clear();
+        assert_eq!(container.type_count(), 0);
+        assert!(container.get_container_ref::<Age>().is_none());
+        assert!(container.get_container_ref::<Name>().is_none());
+        assert_eq!(container.pop::<Age>(), None);
+    }
 }
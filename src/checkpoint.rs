@@ -0,0 +1,388 @@
+//! Checkpoints of a running simulation, so that calibration runs can branch from a saved state.
+//!
+//! A [`Checkpoint`] holds the current time, the state of every random number generator stream
+//! that can be exported with `ContextRandomExt::rng_state_blobs()`, the entities of
+//! `DefaultEntity` with the values of the properties that have opted in with
+//! `define_snapshot_property!()`, and the data of every data plugin that implements
+//! [`Checkpointable`] and has been registered with
+//! [`ContextCheckpointExt::register_checkpointable()`]. Other properties and data plugins are
+//! skipped with a warning.
+//!
+//! Plans are closures and can't be saved, so restoring a checkpoint discards every scheduled
+//! plan and the caller schedules the plans the branch needs. A checkpoint is restored into a
+//! context that has been set up the same way as the one it was taken from:
+//!
+//! ```ignore
+//! let checkpoint = context.checkpoint()?;
+//! context.add_plan(20.0, ContextPlanExt::shutdown);
+//! context.execute();
+//!
+//! // Branch again from the saved state.
+//! context.restore_checkpoint(&checkpoint)?;
+//! context.add_plan(20.0, ContextPlanExt::shutdown);
+//! context.execute();
+//! ```
+use crate::{
+    context::{Context, DataPlugin},
+    entity::EntityData,
+    plans::{ContextPlanExt, PlanData},
+    random::{ContextRandomExt, RngPlugin},
+    snapshot::EntitySnapshot,
+    type_of,
+    warn,
+    IxaError,
+    New,
+    TypeId,
+};
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+
+/// A data plugin whose data can be saved in a `Checkpoint` and restored from it.
+pub trait Checkpointable: New {
+    /// Serializes the plugin's data.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the data can't be serialized.
+    fn save_checkpoint(&self) -> Result<Vec<u8>, IxaError>;
+
+    /// Replaces the plugin's data with data serialized by `save_checkpoint()`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the data can't be deserialized.
+    fn load_checkpoint(&mut self, bytes: &[u8]) -> Result<(), IxaError>;
+}
+
+/// Saves the data of one `Checkpointable` plugin, or returns `None` if the plugin hasn't been
+/// created.
+type SaveFn = fn(&Context) -> Option<Result<Vec<u8>, IxaError>>;
+
+/// Restores the data of one `Checkpointable` plugin, creating the plugin if needed.
+type LoadFn = fn(&mut Context, &[u8]) -> Result<(), IxaError>;
+
+/// Returns one `Checkpointable` plugin to its fresh state.
+type ResetFn = fn(&mut Context);
+
+struct CheckpointFns {
+    name: &'static str,
+    type_id: TypeId,
+    save: SaveFn,
+    load: LoadFn,
+    reset: ResetFn,
+}
+
+fn save_plugin<T: Checkpointable>(context: &Context) -> Option<Result<Vec<u8>, IxaError>> {
+    context.get_data_container::<T>().map(Checkpointable::save_checkpoint)
+}
+
+fn load_plugin<T: Checkpointable>(context: &mut Context, bytes: &[u8]) -> Result<(), IxaError> {
+    context.get_data_container_mut::<T>().load_checkpoint(bytes)
+}
+
+/// Removes the plugin, so that it is created afresh the next time it is used.
+fn reset_plugin<T: Checkpointable>(context: &mut Context) {
+    context.remove_data_container::<T>();
+}
+
+struct CheckpointData {
+    /// One entry for each plugin registered with `register_checkpointable()`.
+    plugins: Vec<CheckpointFns>,
+}
+
+impl DataPlugin for CheckpointData {
+    #[allow(non_upper_case_globals)]
+    const new: &'static dyn Fn() -> Self = &|| CheckpointData { plugins: Vec::new() };
+}
+
+/// The saved state of a simulation. It can be serialized with serde to store it outside the
+/// process.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    current_time: f64,
+    plans_executed: usize,
+    base_seed: Option<u64>,
+    rng_states: Vec<(String, Vec<u8>)>,
+    entities: EntitySnapshot,
+    /// The type name of each registered plugin with its data, or `None` if the plugin hadn't
+    /// been created.
+    plugins: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl Checkpoint {
+    /// The simulation time the checkpoint was taken at.
+    #[must_use]
+    pub fn current_time(&self) -> f64 {
+        self.current_time
+    }
+}
+
+pub trait ContextCheckpointExt {
+    /// Includes the data plugin `T` in checkpoints. Register each plugin during setup, both in
+    /// the context that takes a checkpoint and in the context it is restored into.
+    fn register_checkpointable<T: Checkpointable>(&mut self);
+
+    /// Saves the current time, the random number generator state, the entities and the values
+    /// of their snapshot properties, and the data of every registered `Checkpointable` plugin.
+    /// Scheduled plans, other properties, and other data plugins aren't saved, and a warning is
+    /// logged for each property and plugin that is skipped.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if a plugin's data can't be serialized.
+    fn checkpoint(&self) -> Result<Checkpoint, IxaError>;
+
+    /// Restores the state saved in `checkpoint`. Every scheduled plan is discarded, and
+    /// generator streams that couldn't be saved restart from their seeds. Properties that weren't
+    /// saved are left without values, and registered plugins that hadn't been created when the
+    /// checkpoint was taken are returned to their fresh state. Data plugins that aren't
+    /// registered are left as they are.
+    ///
+    /// # Errors
+    /// Returns `IxaError::PluginNotRegistered` if the checkpoint holds a plugin that isn't
+    /// registered with this context, `IxaError::PropertyNotRegistered` if it holds a property
+    /// that hasn't opted into snapshots, and an `IxaError` if saved data can't be deserialized.
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), IxaError>;
+}
+
+impl ContextCheckpointExt for Context {
+    fn register_checkpointable<T: Checkpointable>(&mut self) {
+        let checkpoint_data = self.get_data_container_mut::<CheckpointData>();
+        if checkpoint_data.plugins.iter().any(|fns| fns.type_id == type_of::<T>()) {
+            return;
+        }
+        checkpoint_data.plugins.push(CheckpointFns {
+            name: type_name::<T>(),
+            type_id: type_of::<T>(),
+            save: save_plugin::<T>,
+            load: load_plugin::<T>,
+            reset: reset_plugin::<T>,
+        });
+    }
+
+    fn checkpoint(&self) -> Result<Checkpoint, IxaError> {
+        let pending_plans = self
+            .get_data_container::<PlanData>()
+            .map_or(0, PlanData::pending_plan_count);
+        if pending_plans > 0 {
+            warn!("{pending_plans} scheduled plans are not saved in the checkpoint");
+        }
+
+        let registered: &[CheckpointFns] = self
+            .get_data_container::<CheckpointData>()
+            .map_or(&[], |checkpoint_data| &checkpoint_data.plugins);
+        let handled = [
+            type_of::<PlanData>(),
+            type_of::<RngPlugin>(),
+            type_of::<CheckpointData>(),
+            type_of::<EntityData>(),
+        ];
+        for (type_id, name) in self.data_container_names() {
+            if !handled.contains(type_id) && !registered.iter().any(|fns| fns.type_id == *type_id)
+            {
+                warn!("Skipping data plugin {name} in checkpoint, as it isn't Checkpointable");
+            }
+        }
+
+        let entities = EntitySnapshot::save(self, |name| {
+            warn!("Skipping property {name} in checkpoint, as it isn't a snapshot property");
+        })?;
+
+        let mut plugins = Vec::with_capacity(registered.len());
+        for fns in registered {
+            plugins.push((fns.name.to_string(), (fns.save)(self).transpose()?));
+        }
+
+        Ok(Checkpoint {
+            current_time: self.get_current_time(),
+            plans_executed: self.plans_executed(),
+            base_seed: self.base_seed(),
+            rng_states: self.rng_state_blobs(),
+            entities,
+            plugins,
+        })
+    }
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), IxaError> {
+        let mut plugin_fns = Vec::with_capacity(checkpoint.plugins.len());
+        for (name, bytes) in &checkpoint.plugins {
+            let (load, reset) = self
+                .get_data_container::<CheckpointData>()
+                .and_then(|checkpoint_data| {
+                    checkpoint_data.plugins.iter().find(|fns| fns.name == name)
+                })
+                .map(|fns| (fns.load, fns.reset))
+                .ok_or_else(|| IxaError::PluginNotRegistered(name.clone()))?;
+            plugin_fns.push((load, reset, bytes));
+        }
+        checkpoint.entities.load(self)?;
+        for (load, reset, bytes) in plugin_fns {
+            match bytes {
+                Some(bytes) => load(self, bytes)?,
+                None => reset(self),
+            }
+        }
+
+        if let Some(base_seed) = checkpoint.base_seed {
+            self.init_random(base_seed);
+        }
+        self.restore_rng_state_blobs(checkpoint.rng_states.clone())?;
+
+        self.get_data_container_mut::<PlanData>()
+            .reset(checkpoint.current_time, checkpoint.plans_executed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        define_rng,
+        define_snapshot_property,
+        entity::ContextEntityExt,
+        property::Property,
+        EntityId,
+    };
+    use rand::RngCore;
+
+    define_rng!(CheckpointRng);
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    struct Stage(u8);
+    impl Property for Stage {}
+    define_snapshot_property!(Stage);
+
+    /// A property that hasn't opted into snapshots.
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct Marked(bool);
+    impl Property for Marked {}
+
+    /// The random draws made at each tick.
+    #[derive(Default, Serialize, Deserialize)]
+    struct Draws {
+        values: Vec<(f64, u64)>,
+    }
+
+    impl DataPlugin for Draws {
+        #[allow(non_upper_case_globals)]
+        const new: &'static dyn Fn() -> Self = &Draws::default;
+    }
+
+    impl Checkpointable for Draws {
+        fn save_checkpoint(&self) -> Result<Vec<u8>, IxaError> {
+            Ok(serde_json::to_vec(self)?)
+        }
+
+        fn load_checkpoint(&mut self, bytes: &[u8]) -> Result<(), IxaError> {
+            *self = serde_json::from_slice(bytes)?;
+            Ok(())
+        }
+    }
+
+    /// A plugin that isn't `Checkpointable`.
+    #[derive(Default)]
+    struct Scratch(u32);
+
+    impl DataPlugin for Scratch {
+        #[allow(non_upper_case_globals)]
+        const new: &'static dyn Fn() -> Self = &Scratch::default;
+    }
+
+    /// Draws a random number once per unit of time until `end_time`.
+    fn run_until(context: &mut Context, end_time: f64) {
+        let plan_id = context.add_periodic_plan(1.0, |context| {
+            let value = context.sample::<CheckpointRng, _>(RngCore::next_u64);
+            let time = context.get_current_time();
+            context.get_data_container_mut::<Draws>().values.push((time, value));
+        });
+        context.add_plan(end_time, move |context| context.cancel_plan(plan_id));
+        context.execute();
+    }
+
+    #[test]
+    fn checkpoint_and_restore() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.register_checkpointable::<Draws>();
+        run_until(&mut context, 10.0);
+        context.get_data_container_mut::<Scratch>().0 = 1;
+
+        let checkpoint = context.checkpoint().unwrap();
+        assert_eq!(checkpoint.current_time(), 10.0);
+        run_until(&mut context, 20.0);
+        let expected = context.get_data_container::<Draws>().unwrap().values.clone();
+        assert_eq!(expected.len(), 20);
+        context.get_data_container_mut::<Scratch>().0 = 2;
+
+        context.restore_checkpoint(&checkpoint).unwrap();
+        assert_eq!(context.get_current_time(), 10.0);
+        assert_eq!(context.get_data_container::<Draws>().unwrap().values.len(), 10);
+        // Plugins that aren't checkpointable are left as they are.
+        assert_eq!(context.get_data_container::<Scratch>().unwrap().0, 2);
+
+        run_until(&mut context, 20.0);
+        assert_eq!(context.get_current_time(), 20.0);
+        assert_eq!(context.get_data_container::<Draws>().unwrap().values, expected);
+    }
+
+    #[test]
+    fn restore_discards_scheduled_plans() {
+        let mut context = Context::new();
+        context.register_checkpointable::<Draws>();
+        context.add_plan(5.0, |context| {
+            context.get_data_container_mut::<Draws>().values.push((5.0, 0));
+        });
+        let checkpoint = context.checkpoint().unwrap();
+
+        context.restore_checkpoint(&checkpoint).unwrap();
+        context.execute();
+        assert_eq!(context.get_current_time(), 0.0);
+        assert!(context.get_data_container::<Draws>().is_none());
+    }
+
+    #[test]
+    fn restore_unregistered_plugin() {
+        let mut context = Context::new();
+        context.register_checkpointable::<Draws>();
+        context.get_data_container_mut::<Draws>();
+        let checkpoint = context.checkpoint().unwrap();
+
+        let mut other = Context::new();
+        let error = other.restore_checkpoint(&checkpoint).unwrap_err();
+        assert!(matches!(error, IxaError::PluginNotRegistered(name) if name.contains("Draws")));
+    }
+
+    #[test]
+    fn restore_entities() {
+        let mut context = Context::new();
+        context.index_property::<Stage>();
+        for stage in [1, 2, 1] {
+            context.add_entity((Stage(stage), Marked(true))).unwrap();
+        }
+        context.remove_entity(EntityId(1)).unwrap();
+        let checkpoint = context.checkpoint().unwrap();
+
+        context.set_property(EntityId(0), Stage(3)).unwrap();
+        context.add_entity(Stage(1)).unwrap();
+        context.add_entity(Stage(1)).unwrap();
+        assert_eq!(context.query_entities(Stage(1)).len(), 3);
+
+        context.restore_checkpoint(&checkpoint).unwrap();
+        assert_eq!(context.get_entity_count(), 2);
+        assert!(!context.entity_exists(EntityId(1)));
+        assert_eq!(context.get_property::<Stage>(EntityId(0)), Some(Stage(1)));
+        assert_eq!(context.query_entities(Stage(1)), vec![EntityId(0), EntityId(2)]);
+        assert!(context.query_entities(Stage(3)).is_empty());
+        // Properties that aren't snapshot properties aren't saved.
+        assert_eq!(context.get_property::<Marked>(EntityId(0)), None);
+    }
+
+    #[test]
+    fn restore_resets_plugins_created_after_checkpoint() {
+        let mut context = Context::new();
+        context.register_checkpointable::<Draws>();
+        let checkpoint = context.checkpoint().unwrap();
+
+        context.get_data_container_mut::<Draws>().values.push((1.0, 1));
+        context.restore_checkpoint(&checkpoint).unwrap();
+        assert!(context.get_data_container::<Draws>().is_none());
+    }
+}
@@ -1,43 +1,93 @@
 use crate::new_trait::New;
 use crate::type_of;
-use std::any::{Any, TypeId};
-use std::collections::HashMap;
-use crate::entity::EntityData;
+use std::any::{type_name, Any, TypeId};
+use std::collections::{hash_map::Entry, HashMap};
 
 pub trait DataPlugin: 'static {
     /// A constant reference to a constructor
     #[allow(non_upper_case_globals)]
     const new: &'static dyn Fn() -> Self;
+
+    /// Called when the `Context` that owns this data plugin is dropped, before any data plugin is
+    /// dropped. Data plugins holding resources such as open report files use this to flush them
+    /// even if the simulation never called `shutdown()`. Does nothing by default.
+    fn on_context_drop(&mut self) {}
 }
 impl<T: DataPlugin> New for T {
     const new: &'static dyn Fn() -> Self = DataPlugin::new;
+
+    fn on_context_drop(&mut self) {
+        DataPlugin::on_context_drop(self);
+    }
 }
 
+/// Calls `New::on_context_drop` on a type-erased data container of type `T`.
+type DropHook = fn(&mut dyn Any);
+
+fn drop_hook<T: New>(container: &mut dyn Any) {
+    // Will never panic as hooks are only ever called on the container they were created for.
+    container.downcast_mut::<T>().unwrap().on_context_drop();
+}
 
 pub struct Context {
     // This is actually a `HashMap<TypeId, Box<dyn New>>` but must be declared this way to avoid 
     // having to implement an `as_any()` method on everything, at least as far as I know.
     data_plugins: HashMap<TypeId, Box<dyn Any>>,
+    /// The `on_context_drop` hook of each data container, in the order the containers were created.
+    drop_hooks: Vec<(TypeId, DropHook)>,
+    /// The type name of each data container, in the order the containers were created.
+    data_plugin_names: Vec<(TypeId, &'static str)>,
 }
 
 impl Context {
     pub fn new() -> Self {
         Context {
             data_plugins: HashMap::new(),
+            drop_hooks: Vec::new(),
+            data_plugin_names: Vec::new(),
         }
     }
 
     /// Returns a mutable reference for the data container for `T`, creating it if it doesn't exist yet.
     pub fn get_data_container_mut<T: New>(&mut self) -> &mut T {
-        let container = self.data_plugins
-                            .entry(type_of::<T>())
-                            .or_insert_with(|| Box::new(<T as New>::new()))
-                            .downcast_mut::<T>();
+        let container = match self.data_plugins.entry(type_of::<T>()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                self.drop_hooks.push((type_of::<T>(), drop_hook::<T>));
+                self.data_plugin_names.push((type_of::<T>(), type_name::<T>()));
+                entry.insert(Box::new(<T as New>::new()))
+            }
+        }
+        .downcast_mut::<T>();
         // Will never panic as data container has the matching type
         unsafe{ container.unwrap_unchecked() }
         
     }
 
+    /// Reports whether the data container for `T` exists. Unlike
+    /// `Context::get_data_container_mut()`, this never creates the container.
+    #[must_use]
+    pub fn contains_data_container<T: New>(&self) -> bool {
+        self.data_plugins.contains_key(&type_of::<T>())
+    }
+
+    /// Removes the data container for `T` and returns it, or returns `None` if it doesn't exist.
+    /// The container's `on_context_drop` hook isn't called, and a later call to
+    /// `Context::get_data_container_mut()` creates a new container.
+    pub fn remove_data_container<T: New>(&mut self) -> Option<Box<T>> {
+        let container = self.data_plugins.remove(&type_of::<T>())?;
+        self.drop_hooks.retain(|(type_id, _)| *type_id != type_of::<T>());
+        self.data_plugin_names.retain(|(type_id, _)| *type_id != type_of::<T>());
+        // Will never fail as the data container has the matching type
+        container.downcast::<T>().ok()
+    }
+
+    /// The number of data containers that have been created.
+    #[must_use]
+    pub fn data_container_count(&self) -> usize {
+        self.data_plugins.len()
+    }
+
     /// Returns a reference to the data container for `T` if it exists.
     /// If you need a mutable reference or lazy instantiation, use `Context::get_data_container_mut()`.
     pub fn get_data_container<T: New>(&self) -> Option<&T> {
@@ -49,9 +99,33 @@ impl Context {
     }
 }
 
+impl Context {
+    /// The `TypeId` and type name of each data container, in the order they were created.
+    pub(crate) fn data_container_names(&self) -> &[(TypeId, &'static str)] {
+        &self.data_plugin_names
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        for (type_id, hook) in &self.drop_hooks {
+            if let Some(container) = self.data_plugins.get_mut(type_id) {
+                hook(container.as_mut());
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{fs, io::Write};
 
     #[test]
     fn test_context_creation() {
@@ -78,4 +152,83 @@ mod tests {
         assert!(result.is_some());
         println!("{:?}", result.unwrap());
     }
+
+    #[test]
+    fn contains_data_container() {
+        let mut context = Context::new();
+        assert_eq!(context.data_container_count(), 0);
+        assert!(!context.contains_data_container::<Vec<u8>>());
+        // Checking doesn't create the container.
+        assert!(!context.contains_data_container::<Vec<u8>>());
+        assert_eq!(context.data_container_count(), 0);
+
+        context.get_data_container_mut::<Vec<u8>>();
+        assert!(context.contains_data_container::<Vec<u8>>());
+        assert!(!context.contains_data_container::<Vec<u16>>());
+        assert_eq!(context.data_container_count(), 1);
+    }
+
+    #[test]
+    fn default_is_empty() {
+        for context in [Context::new(), Context::default()] {
+            assert_eq!(context.data_container_count(), 0);
+            assert!(context.data_container_names().is_empty());
+        }
+    }
+
+    #[test]
+    fn remove_data_container() {
+        let mut context = Context::new();
+        assert!(context.remove_data_container::<Vec<u8>>().is_none());
+
+        context.get_data_container_mut::<Vec<u8>>().extend([1, 2, 3]);
+        context.get_data_container_mut::<Vec<u16>>().push(4);
+        let removed = context.remove_data_container::<Vec<u8>>().unwrap();
+        assert_eq!(*removed, vec![1, 2, 3]);
+        assert!(!context.contains_data_container::<Vec<u8>>());
+        assert!(context.contains_data_container::<Vec<u16>>());
+        assert_eq!(context.data_container_count(), 1);
+        assert_eq!(context.data_container_names().len(), 1);
+
+        // A new container is created on the next mutable access.
+        assert!(context.get_data_container_mut::<Vec<u8>>().is_empty());
+    }
+
+    /// Buffers lines in memory and only writes them to its file when flushed.
+    struct BufferedReport {
+        file: Option<fs::File>,
+        lines: Vec<String>,
+    }
+
+    impl DataPlugin for BufferedReport {
+        #[allow(non_upper_case_globals)]
+        const new: &'static dyn Fn() -> Self = &|| BufferedReport {
+            file: None,
+            lines: Vec::new(),
+        };
+
+        fn on_context_drop(&mut self) {
+            if let Some(file) = &mut self.file {
+                for line in self.lines.drain(..) {
+                    writeln!(file, "{line}").unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn report_flushes_on_context_drop() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("report.csv");
+        {
+            let mut context = Context::new();
+            let report = context.get_data_container_mut::<BufferedReport>();
+            report.file = Some(fs::File::create(&path).unwrap());
+            report.lines.push("time,count".to_string());
+            report.lines.push("0,1".to_string());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "time,count\n0,1\n");
+    }
 }
@@ -0,0 +1,183 @@
+use crate::{
+    context::Context,
+    entity::{
+        init_list::index_new_entity,
+        DefaultEntity,
+        EntityCreatedEvent,
+        EntityData,
+        EntityKind,
+    },
+    event::ContextEventExt,
+    property::Property,
+    type_of,
+    EntityId,
+    IxaError,
+    TypeId,
+};
+
+/// A property value held by an `EntityBuilder`, with its type erased.
+trait BuilderValue<K: EntityKind> {
+    fn type_id(&self) -> TypeId;
+    fn name(&self) -> &'static str;
+    fn check_valid(&self) -> Result<(), IxaError>;
+    fn register(&self, context: &mut Context);
+    fn set(&self, entity_data: &mut EntityData<K>, entity_id: EntityId);
+    fn index_new_entity(&self, context: &Context, entity_id: EntityId);
+}
+
+impl<K: EntityKind, T: Property> BuilderValue<K> for T {
+    fn type_id(&self) -> TypeId {
+        type_of::<T>()
+    }
+
+    fn name(&self) -> &'static str {
+        T::name()
+    }
+
+    fn check_valid(&self) -> Result<(), IxaError> {
+        Property::check_valid(self)
+    }
+
+    fn register(&self, context: &mut Context) {
+        T::register_for::<K>(context);
+    }
+
+    fn set(&self, entity_data: &mut EntityData<K>, entity_id: EntityId) {
+        entity_data.set_property(entity_id, self.clone());
+    }
+
+    fn index_new_entity(&self, context: &Context, entity_id: EntityId) {
+        index_new_entity::<K, T>(context, entity_id);
+    }
+}
+
+/// Adds an entity with any number of initial property values, for entities with more
+/// properties than an initialization tuple can hold. Created with
+/// `ContextEntityExt::entity_builder()`:
+///
+/// ```ignore
+/// let entity_id = context
+///     .entity_builder()
+///     .with(Age(30))
+///     .with(RiskCategory::High)
+///     .build()?;
+/// ```
+pub struct EntityBuilder<'a, K: EntityKind = DefaultEntity> {
+    context: &'a mut Context,
+    properties: Vec<Box<dyn BuilderValue<K>>>,
+}
+
+impl<'a, K: EntityKind> EntityBuilder<'a, K> {
+    pub(super) fn new(context: &'a mut Context) -> Self {
+        EntityBuilder {
+            context,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Gives the new entity the value `value` for the property `T`.
+    #[must_use]
+    pub fn with<T: Property>(mut self, value: T) -> Self {
+        self.properties.push(Box::new(value));
+        self
+    }
+
+    /// Adds the entity, as `add_entity()` would with the same properties in a tuple.
+    ///
+    /// # Errors
+    /// Returns an error, without adding an entity, if a property is given twice, a required
+    /// property is missing, or a value fails `Property::validate`.
+    pub fn build(self) -> Result<EntityId, IxaError> {
+        let EntityBuilder { context, properties } = self;
+        for property in &properties {
+            property.register(context);
+        }
+        let names: Vec<(TypeId, &'static str)> = properties
+            .iter()
+            .map(|property| (property.type_id(), property.name()))
+            .collect();
+        let entity_data = context.get_data_container_mut::<EntityData<K>>();
+        entity_data.check_properties(&names)?;
+        for property in &properties {
+            property.check_valid()?;
+        }
+
+        let reuses_id = !entity_data.removed_entities.is_empty();
+        let entity_id = entity_data.add_entity();
+
+        // Initialize the properties without emitting change events, as `add_entity()` does.
+        entity_data.is_initializing = true;
+        for property in &properties {
+            property.set(entity_data, entity_id);
+        }
+        entity_data.is_initializing = false;
+
+        {
+            let context: &Context = context;
+            if reuses_id {
+                // Indexes won't revisit an id they have passed, so every index needs the entity
+                // now. Will not panic, as adding an entity creates the `EntityData`.
+                let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+                entity_data
+                    .property_indexes
+                    .borrow_mut()
+                    .add_reused_entity(context, entity_id);
+            } else {
+                for property in &properties {
+                    property.index_new_entity(context, entity_id);
+                }
+            }
+        }
+        context.emit_event(EntityCreatedEvent::<K>::new(entity_id));
+        Ok(entity_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{context::Context, entity::ContextEntityExt, property::Property, IxaError};
+    use seq_macro::seq;
+
+    seq!(N in 0..25 {
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+        struct P~N(u32);
+        impl Property for P~N {}
+    });
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct RiskGroup(u8);
+    crate::define_required_property!(RiskGroup);
+
+    #[test]
+    fn build_entity_with_25_properties() {
+        let mut context = Context::new();
+        context.index_property::<P3>();
+        context.add_entity(P3(3)).unwrap();
+
+        let builder = context.entity_builder();
+        seq!(N in 0..25 {
+            let builder = builder.with(P~N(N));
+        });
+        let entity_id = builder.build().unwrap();
+
+        assert_eq!(context.get_property::<P0>(entity_id), Some(P0(0)));
+        assert_eq!(context.get_property::<P24>(entity_id), Some(P24(24)));
+        assert_eq!(context.query_entities((P7(7), P20(20))), vec![entity_id]);
+        assert_eq!(context.query_entity_count(P3(3)), 2);
+    }
+
+    #[test]
+    fn build_checks_properties() {
+        let mut context = Context::new();
+        let error = context.entity_builder().with(P0(1)).with(P0(2)).build().unwrap_err();
+        assert!(matches!(error, IxaError::IxaError(message) if message.contains("Duplicate")));
+
+        RiskGroup::register(&mut context);
+        let error = context.entity_builder().with(P0(1)).build().unwrap_err();
+        assert!(matches!(error, IxaError::MissingRequiredProperty(_)));
+        assert_eq!(context.get_entity_count(), 0);
+
+        let entity_id = context.entity_builder().with(RiskGroup(1)).build().unwrap();
+        assert_eq!(context.get_property::<RiskGroup>(entity_id), Some(RiskGroup(1)));
+    }
+}
@@ -1,29 +1,260 @@
 use crate::{context::Context, error::IxaError, entity::{
+    DefaultEntity,
+    EntityBuilder,
+    EntityCreatedEvent,
+    EntityKind,
     Index,
     IndexValue,
     InitializationList,
     EntityData,
+    PropertyChangeEvent,
+    data::{clear_value, IndexUpdateFns, ReportValueFn, SerializeValueFn},
     Query
-}, EntityId, property::{
+}, EntityId, event::ContextEventExt, plans::ContextPlanExt, report::ReportData, property::{
     Property
 }, type_of, HashMap};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{io::Read, marker::PhantomData, path::Path};
 
 pub trait ContextEntityExt {
     fn get_entity_count(&self) -> usize;
+
+    /// Reserves space in the property stores for at least `additional` more entities, so that
+    /// adding a population of known size doesn't repeatedly reallocate. Call it once before
+    /// adding the entities.
+    fn reserve_entities(&mut self, additional: usize);
+
+    /// Like `reserve_entities()`, but reserves space for entities of kind `K`.
+    fn reserve_entities_of<K: EntityKind>(&mut self, additional: usize);
+
+    /// Adds an entity with the given initial property values and emits an `EntityCreatedEvent`
+    /// once they are set.
+    ///
+    /// # Errors
+    /// Returns an error if a property required by `DefaultEntity` is missing from `properties`.
     fn add_entity<T: InitializationList>(&mut self, properties: T) -> Result<EntityId, IxaError>;
 
+    /// The number of entities of kind `K`, not counting removed entities.
+    fn get_entity_count_of<K: EntityKind>(&self) -> usize;
+
+    /// Like `add_entity()`, but adds an entity of kind `K`. Each kind has its own ids, so the
+    /// returned id only identifies the entity together with `K`.
+    ///
+    /// # Errors
+    /// Returns an error if a property required by `K` is missing from `properties`.
+    fn add_entity_of<K: EntityKind, T: InitializationList>(
+        &mut self,
+        properties: T,
+    ) -> Result<EntityId, IxaError>;
+
+    /// Returns an `EntityBuilder` that adds an entity with the properties given to its `with()`
+    /// method. Unlike `add_entity()`, there is no limit on the number of properties.
+    fn entity_builder(&mut self) -> EntityBuilder<'_, DefaultEntity>;
+
+    /// Like `entity_builder()`, but builds an entity of kind `K`.
+    fn entity_builder_of<K: EntityKind>(&mut self) -> EntityBuilder<'_, K>;
+
+    /// Adds `n` entities with no initial property values, reserving space for them in the
+    /// property stores first. Like `add_entity()`, the ids of removed entities are reused,
+    /// lowest first, before new ids are handed out, so the returned ids, though in ascending
+    /// order, may not be contiguous.
+    ///
+    /// # Errors
+    /// Returns an error if a property is required, since the entities have no initial values.
+    fn add_entities(&mut self, n: usize) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `add_entities()`, but adds entities of kind `K`.
+    ///
+    /// # Errors
+    /// Returns an error if a property required by `K` is missing.
+    fn add_entities_of<K: EntityKind>(&mut self, n: usize) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `add_entities()`, but gives every new entity a copy of `properties`.
+    ///
+    /// # Errors
+    /// Returns an error if a required property is missing from `properties`.
+    fn add_entities_with<T: InitializationList + Clone>(
+        &mut self,
+        n: usize,
+        properties: T,
+    ) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `add_entities_with()`, but adds entities of kind `K`.
+    ///
+    /// # Errors
+    /// Returns an error if a property required by `K` is missing from `properties`.
+    fn add_entities_with_of<K: EntityKind, T: InitializationList + Clone>(
+        &mut self,
+        n: usize,
+        properties: T,
+    ) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Removes the entity: its property values are cleared, it is taken out of every index, and
+    /// it no longer matches queries or counts toward `get_entity_count()`. Its id is reused by a
+    /// later `add_entity()`, which takes the lowest removed id before handing out a new one.
+    ///
+    /// # Errors
+    /// Returns an error if the entity doesn't exist or has already been removed.
+    fn remove_entity(&mut self, entity_id: EntityId) -> Result<(), IxaError>;
+
+    /// Like `remove_entity()`, for the entity of kind `K` with the given id.
+    ///
+    /// # Errors
+    /// Returns an error if the entity doesn't exist or has already been removed.
+    fn remove_entity_of<K: EntityKind>(&mut self, entity_id: EntityId) -> Result<(), IxaError>;
+
+    /// Whether the entity has been added and not removed.
+    fn entity_exists(&self, entity_id: EntityId) -> bool;
+
+    /// Like `entity_exists()`, for the entity of kind `K` with the given id.
+    fn entity_exists_of<K: EntityKind>(&self, entity_id: EntityId) -> bool;
+
+    /// Returns the id of the entity at `index`, the inverse of `EntityId::index()`.
+    ///
+    /// # Errors
+    /// Returns an error if no entity with that index exists, either because the index is past
+    /// the ids handed out so far or because the entity has been removed.
+    fn entity_id_from_index(&self, index: usize) -> Result<EntityId, IxaError>;
+
+    /// Like `entity_id_from_index()`, for the entities of kind `K`.
+    ///
+    /// # Errors
+    /// Returns an error if no entity of kind `K` with that index exists.
+    fn entity_id_from_index_of<K: EntityKind>(&self, index: usize) -> Result<EntityId, IxaError>;
+
+    /// Lists the name and `Debug` representation of the value of every registered property the
+    /// entity has a value for, in the order the properties were registered. Derived properties
+    /// are computed. Returns an empty list if the entity doesn't exist.
+    fn describe_entity(&mut self, entity_id: EntityId) -> Vec<(String, String)>;
+
+    /// Like `describe_entity()`, for the entity of kind `K` with the given id.
+    fn describe_entity_of<K: EntityKind>(&mut self, entity_id: EntityId) -> Vec<(String, String)>;
+
     fn get_property<T: Property>(&mut self, entity_id: EntityId) -> Option<T>;
+
+    /// Like `get_property()`, for the entity of kind `K` with the given id.
+    fn get_property_of<K: EntityKind, T: Property>(&mut self, entity_id: EntityId) -> Option<T>;
+
+    /// Borrows the stored value of the property for the given entity without cloning it or
+    /// borrowing the context mutably, so that several properties can be read while other
+    /// immutable borrows are held. Unlike `get_property()`, this can't register `T`. For an
+    /// entity with no stored value, it borrows the `Property::default_value` of `T`, which is
+    /// stored once per property, or returns `None` if `T` has no default.
+    ///
+    /// # Panics
+    /// Panics if `T` is a derived property, which has no stored value; use `Property::compute`
+    /// to compute it from an immutable context. Also panics if `T` has not been registered,
+    /// which happens the first time it is read with `get_property()`, set, given to
+    /// `add_entity()`, queried, or indexed.
+    fn get_property_ref<T: Property>(&self, entity_id: EntityId) -> Option<&T>;
+
+    /// Like `get_property_ref()`, for the entity of kind `K` with the given id.
+    ///
+    /// # Panics
+    /// Panics if `T` is a derived property or has not been registered for `K`.
+    fn get_property_ref_of<K: EntityKind, T: Property>(&self, entity_id: EntityId) -> Option<&T>;
+
     fn get_property_mut<T: Property>(&mut self, entity_id: EntityId) -> &mut Option<T>;
+
+    /// Like `get_property_mut()`, for the entity of kind `K` with the given id.
+    ///
+    /// # Panics
+    /// Panics if `T` is a derived property.
+    fn get_property_mut_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+    ) -> &mut Option<T>;
+
+    /// Iterates over every entity in order of `EntityId`, yielding each entity together with its
+    /// value of `T`, or `None` if it has no value. Derived properties are computed as the
+    /// iterator advances.
+    fn iter_property<T: Property>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, Option<T>)> + '_;
+
+    /// Like `iter_property()`, but iterates over the entities of kind `K`.
+    fn iter_property_of<K: EntityKind, T: Property>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, Option<T>)> + '_;
+
     fn get_property_or_default<T: Property>(
         &mut self,
         entity_id: EntityId,
         default: T,
     ) -> &mut T;
 
-    fn set_property<T: Property>(&mut self, entity_id: EntityId, value: T);
+    /// Like `get_property_or_default()`, for the entity of kind `K` with the given id. Setting
+    /// the default emits a `PropertyChangeEvent<T, K>`.
+    ///
+    /// # Panics
+    /// Panics if the default fails `Property::validate`.
+    fn get_property_or_default_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        default: T,
+    ) -> &mut T;
+
+    /// Sets the value of the property for the given entity and emits a `PropertyChangeEvent<T>`.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the stored value unchanged, if `value` fails
+    /// `Property::validate`.
+    fn set_property<T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        value: T,
+    ) -> Result<(), IxaError>;
+
+    /// Sets every property in `properties` on an existing entity, as if by calling
+    /// `set_property()` for each in turn: each emits a `PropertyChangeEvent` and updates the
+    /// indexes.
+    ///
+    /// # Errors
+    /// Returns an error if the entity doesn't exist or a value fails `Property::validate`, in
+    /// which case no property is set.
+    fn update_entity<T: InitializationList>(
+        &mut self,
+        entity_id: EntityId,
+        properties: T,
+    ) -> Result<(), IxaError>;
+
+    /// Like `update_entity()`, for the entity of kind `K` with the given id. Each property set
+    /// emits a `PropertyChangeEvent<T, K>`.
+    ///
+    /// # Errors
+    /// Returns an error if the entity doesn't exist or a value fails `Property::validate`, in
+    /// which case no property is set.
+    fn update_entity_of<K: EntityKind, T: InitializationList>(
+        &mut self,
+        entity_id: EntityId,
+        properties: T,
+    ) -> Result<(), IxaError>;
+
+    /// Like `set_property()`, for the entity of kind `K` with the given id. Emits a
+    /// `PropertyChangeEvent<T, K>`.
+    ///
+    /// # Errors
+    /// Returns an error if `value` fails `Property::validate`.
+    fn set_property_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        value: T,
+    ) -> Result<(), IxaError>;
 
+    /// Returns the entities matching the query `q`, sorted by id, so that code consuming the
+    /// result in order, such as drawing random numbers for each entity, is reproducible.
     fn query_entities<T: Query>(&mut self, q: T) -> Vec<EntityId>;
 
+    /// Like `query_entities()`, but matches the entities of kind `K`.
+    fn query_entities_of<K: EntityKind, T: Query>(&mut self, q: T) -> Vec<EntityId>;
+
+    /// Like `query_entities()`, but skips sorting the result. The order depends on how the
+    /// query is executed, for example on which properties are indexed.
+    fn query_entities_unordered<T: Query>(&mut self, q: T) -> Vec<EntityId>;
+
+    /// Like `query_entities_unordered()`, but matches the entities of kind `K`.
+    fn query_entities_unordered_of<K: EntityKind, T: Query>(&mut self, q: T) -> Vec<EntityId>;
+
     /// Get the count of all entities matching a given set of criteria.
     ///
     /// [`Context::query_entity_count()`] takes any type that implements [Query],
@@ -34,29 +265,271 @@ pub trait ContextEntityExt {
     ///
     /// This is intended to be slightly faster than [`Context::query_entities()`]
     /// because it does not need to allocate a list. We haven't actually
-    /// measured it, so the difference may be modest if any.
+    /// measured it, so the difference may be modest if any. A query for a single indexed
+    /// property is counted from the size of its index bucket without visiting any entity.
     fn query_entity_count<T: Query>(&mut self, q: T) -> usize;
 
+    /// Like `query_entity_count()`, but counts the entities of kind `K`.
+    fn query_entity_count_of<K: EntityKind, T: Query>(&mut self, q: T) -> usize;
+
     /// Determine whether an entity matches a given expression.
     ///
     /// The syntax here is the same as with [`Context::query_entities()`].
     fn match_entity<T: Query>(&mut self, person_id: EntityId, q: T) -> bool;
 
+    /// Like `match_entity()`, for the entity of kind `K` with the given id.
+    fn match_entity_of<K: EntityKind, T: Query>(&mut self, entity_id: EntityId, q: T) -> bool;
+
+    /// Create the index for the given property. Note that this does not populate the index. That
+    /// happens lazily. Returns `true` if the index was newly created and `false` if the property
+    /// was already indexed.
+    fn index_property<T: Property>(&mut self) -> bool;
+
+    /// Like `index_property()`, but indexes `T` for the entities of kind `K`. Each kind has its
+    /// own indexes.
+    fn index_property_of<K: EntityKind, T: Property>(&mut self) -> bool;
+
+    /// Counts the entities with each value of `T`. Entities with no value are not counted. Uses
+    /// the index for `T` if there is one and otherwise looks up the value of every entity.
+    fn count_by<T: Property + Eq>(&mut self) -> HashMap<T, usize>;
+
+    /// Like `count_by()`, but counts the entities of kind `K`.
+    fn count_by_of<K: EntityKind, T: Property + Eq>(&mut self) -> HashMap<T, usize>;
+
+    /// Returns, for every indexed property, the number of entities with each value, keyed by
+    /// property name and then by the `IndexValue` of the value. The counts are read from the
+    /// sizes of the index buckets, so no entity's property is looked up except to index newly
+    /// added entities.
+    fn all_index_counts(&self) -> HashMap<String, HashMap<IndexValue, usize>>;
+
+    /// Like `all_index_counts()`, but reads the indexes of the entities of kind `K`.
+    fn all_index_counts_of<K: EntityKind>(&self) -> HashMap<String, HashMap<IndexValue, usize>>;
+
+    /// Starts recording the simulation time at which each entity's value of `T` is set, so that
+    /// it can be read back with `time_since_last_change()`. Only changes made after tracking
+    /// starts are recorded. Calling this more than once for the same property has no effect.
+    fn track_property_changes<T: Property>(&mut self);
+
+    /// Like `track_property_changes()`, but records changes to `T` on the entities of kind `K`.
+    fn track_property_changes_of<K: EntityKind, T: Property>(&mut self);
+
+    /// Returns how much simulation time has passed since the entity's value of `T` was last set,
+    /// or `None` if changes to `T` are not tracked or the value hasn't been set since tracking
+    /// started.
+    fn time_since_last_change<T: Property>(&self, entity_id: EntityId) -> Option<f64>;
+
+    /// Like `time_since_last_change()`, for the entity of kind `K` with the given id.
+    fn time_since_last_change_of<K: EntityKind, T: Property>(
+        &self,
+        entity_id: EntityId,
+    ) -> Option<f64>;
+
+    /// Writes one CSV row for each entity matching the query `q` to the report file named
+    /// `report_name`, without collecting the matches first. The file is named and placed
+    /// according to `report_options()`, like any other report. Each row holds the current time,
+    /// the entity id, and the value of each property named in `columns`, formatted with
+    /// `Property::to_report_value`. A property with no value for an entity gets an empty cell.
+    ///
+    /// # Errors
+    /// Returns `IxaError::PropertyNotRegistered` if a column does not name a registered property,
+    /// and an `IxaError` if the file already exists and `overwrite` is not set or if the file
+    /// can't be written.
+    fn export_query<T: Query>(
+        &mut self,
+        q: T,
+        report_name: &str,
+        columns: &[&str],
+    ) -> Result<(), IxaError>;
+
+    /// Like `export_query()`, but exports the entities of kind `K` matching the query `q`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if a column does not name a property registered for `K`, if the
+    /// file already exists and `overwrite` is not set, or if the file can't be written.
+    fn export_query_of<K: EntityKind, T: Query>(
+        &mut self,
+        q: T,
+        report_name: &str,
+        columns: &[&str],
+    ) -> Result<(), IxaError>;
+
+    /// Adds a column for `T` to the files written by `export_population_csv()`. Columns are
+    /// written in the order they were added. Adding the same property again has no effect.
+    fn add_population_column<T: Property + Serialize>(&mut self);
+
+    /// Like `add_population_column()`, but adds the column to the files written by
+    /// `export_population_csv_of::<K>()`.
+    fn add_population_column_of<K: EntityKind, T: Property + Serialize>(&mut self);
+
+    /// Writes the population to a CSV file at `path`, one row per entity in order of
+    /// `EntityId`, with a column for each property added with `add_population_column()`. Values
+    /// are serialized with serde: strings and unit enum variants are written bare and other
+    /// values as JSON, so a newtype like `Age(30)` becomes `30`. A property with no value for an
+    /// entity gets an empty cell. The file can be read back with `import_population_csv()`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if no columns have been added, a value can't be serialized, or the
+    /// file can't be written.
+    fn export_population_csv(&mut self, path: &Path) -> Result<(), IxaError>;
+
+    /// Like `export_population_csv()`, but writes the entities of kind `K` with the columns
+    /// added with `add_population_column_of::<K, _>()`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if no columns have been added for `K`, a value can't be serialized,
+    /// or the file can't be written.
+    fn export_population_csv_of<K: EntityKind>(&mut self, path: &Path) -> Result<(), IxaError>;
+
+    /// Adds an entity for each row of the CSV file at `path`, deserializing the row into the
+    /// initialization list `T`, such as a tuple of properties, one column per property. The
+    /// first row of the file is a header and is skipped. Returns the ids of the new entities in
+    /// the order of the rows.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the file can't be read, a row can't be deserialized into `T`,
+    /// or an entity can't be added. Errors in a row name the line of the file it is on. Entities
+    /// added from the rows before the error are kept.
+    fn import_population_csv<T: InitializationList + DeserializeOwned>(
+        &mut self,
+        path: &Path,
+    ) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `import_population_csv()`, but adds entities of kind `K`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the file can't be read, a row can't be deserialized into `T`,
+    /// or an entity of kind `K` can't be added.
+    fn import_population_csv_of<K: EntityKind, T: InitializationList + DeserializeOwned>(
+        &mut self,
+        path: &Path,
+    ) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `import_population_csv()`, but reads the CSV data from `reader`, such as a byte
+    /// slice holding a CSV string, instead of a file. Errors in a row name its line.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the data can't be read, a row can't be deserialized into `T`,
+    /// or an entity can't be added.
+    fn load_entities_from_csv_reader<T: InitializationList + DeserializeOwned, R: Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<Vec<EntityId>, IxaError>;
+
+    /// Like `load_entities_from_csv_reader()`, but adds entities of kind `K`.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the data can't be read, a row can't be deserialized into `T`,
+    /// or an entity of kind `K` can't be added.
+    fn load_entities_from_csv_reader_of<
+        K: EntityKind,
+        T: InitializationList + DeserializeOwned,
+        R: Read,
+    >(
+        &mut self,
+        reader: R,
+    ) -> Result<Vec<EntityId>, IxaError>;
+}
+
+/// Serializes the value of `T` for the entity of kind `K` for a CSV cell, as described in
+/// `ContextEntityExt::export_population_csv`.
+fn serialize_value<K: EntityKind, T: Property + Serialize>(
+    context: &Context,
+    entity_id: EntityId,
+) -> Result<Option<String>, IxaError> {
+    let Some(value) = T::compute_for::<K>(context, entity_id) else {
+        return Ok(None);
+    };
+    Ok(Some(match serde_json::to_value(&value)? {
+        serde_json::Value::String(string) => string,
+        value => value.to_string(),
+    }))
 }
 
 impl ContextEntityExt for Context {
     fn get_entity_count(&self) -> usize {
-        match self.get_data_container::<EntityData>() {
+        self.get_entity_count_of::<DefaultEntity>()
+    }
+
+    fn get_entity_count_of<K: EntityKind>(&self) -> usize {
+        match self.get_data_container::<EntityData<K>>() {
             None => 0,
-            Some(entity_data) => entity_data.entity_count,
+            Some(entity_data) => entity_data.live_entity_count(),
+        }
+    }
+
+    fn reserve_entities(&mut self, additional: usize) {
+        self.reserve_entities_of::<DefaultEntity>(additional);
+    }
+
+    fn reserve_entities_of<K: EntityKind>(&mut self, additional: usize) {
+        self.get_data_container_mut::<EntityData<K>>().reserve(additional);
+    }
+
+    fn count_by<T: Property + Eq>(&mut self) -> HashMap<T, usize> {
+        self.count_by_of::<DefaultEntity, T>()
+    }
+
+    fn count_by_of<K: EntityKind, T: Property + Eq>(&mut self) -> HashMap<T, usize> {
+        T::register_for::<K>(self);
+        let context: &Context = self;
+        // Will not panic, as registering `T` creates the `EntityData`.
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+        let mut index_map = entity_data.property_indexes.borrow_mut();
+        let index = index_map.get_container_mut::<T>();
+        let mut counts: HashMap<T, usize> = HashMap::default();
+
+        if index.lookup.is_some() {
+            index.index_unindexed_entities(context);
+            let lookup = index.lookup.as_ref().unwrap();
+            counts.reserve(lookup.len());
+            for entities in lookup.values() {
+                // Every entity in a bucket has the same value, so we look it up for any one of them.
+                if let Some(entity_id) = entities.iter().next()
+                    && let Some(value) = T::compute_for::<K>(context, *entity_id)
+                {
+                    counts.insert(value, entities.len());
+                }
+            }
+        } else {
+            for entity_id in entity_data.entity_iterator() {
+                if let Some(value) = T::compute_for::<K>(context, entity_id) {
+                    *counts.entry(value).or_default() += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    fn all_index_counts(&self) -> HashMap<String, HashMap<IndexValue, usize>> {
+        self.all_index_counts_of::<DefaultEntity>()
+    }
+
+    fn all_index_counts_of<K: EntityKind>(&self) -> HashMap<String, HashMap<IndexValue, usize>> {
+        match self.get_data_container::<EntityData<K>>() {
+            None => HashMap::default(),
+            Some(entity_data) => entity_data
+                .property_indexes
+                .borrow_mut()
+                .all_value_counts(self),
         }
     }
 
     /// Adds a new entity with the given list of properties.
     fn add_entity<T: InitializationList>(&mut self, properties: T) -> Result<EntityId, IxaError> {
-        let entity_data = self.get_data_container_mut::<EntityData>();
-        entity_data.check_initialization_list(&properties)?;
+        self.add_entity_of::<DefaultEntity, T>(properties)
+    }
+
+    fn add_entity_of<K: EntityKind, T: InitializationList>(
+        &mut self,
+        properties: T,
+    ) -> Result<EntityId, IxaError> {
+        T::register_properties::<K>(self);
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        entity_data.check_initialization_list::<T>()?;
+        properties.validate()?;
 
+        let reuses_id = !entity_data.removed_entities.is_empty();
         let entity_id = entity_data.add_entity();
 
         // Initialize the properties. We set |is_initializing| to prevent
@@ -65,57 +538,348 @@ impl ContextEntityExt for Context {
         properties.set_properties(entity_data, entity_id);
         entity_data.is_initializing = false;
 
+        if reuses_id {
+            // Indexes won't revisit an id they have passed, so every index needs the entity now.
+            let context: &Context = self;
+            // Will not panic, as adding an entity creates the `EntityData`.
+            let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+            entity_data
+                .property_indexes
+                .borrow_mut()
+                .add_reused_entity(context, entity_id);
+        } else {
+            T::index_new_entity::<K>(self, entity_id);
+        }
+
+        self.emit_event(EntityCreatedEvent::<K>::new(entity_id));
+        Ok(entity_id)
+    }
+
+    fn entity_builder(&mut self) -> EntityBuilder<'_, DefaultEntity> {
+        self.entity_builder_of::<DefaultEntity>()
+    }
+
+    fn entity_builder_of<K: EntityKind>(&mut self) -> EntityBuilder<'_, K> {
+        EntityBuilder::new(self)
+    }
+
+    fn add_entities(&mut self, n: usize) -> Result<Vec<EntityId>, IxaError> {
+        self.add_entities_of::<DefaultEntity>(n)
+    }
+
+    fn add_entities_of<K: EntityKind>(&mut self, n: usize) -> Result<Vec<EntityId>, IxaError> {
+        self.add_entities_with_of::<K, ()>(n, ())
+    }
+
+    fn add_entities_with<T: InitializationList + Clone>(
+        &mut self,
+        n: usize,
+        properties: T,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        self.add_entities_with_of::<DefaultEntity, T>(n, properties)
+    }
+
+    fn add_entities_with_of<K: EntityKind, T: InitializationList + Clone>(
+        &mut self,
+        n: usize,
+        properties: T,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        T::register_properties::<K>(self);
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        entity_data.check_initialization_list::<T>()?;
+        properties.validate()?;
+        entity_data.reserve(n.saturating_sub(entity_data.removed_entities.len()));
+        let first_new_id = entity_data.entity_count;
+        let entity_ids = entity_data.add_entities(n);
+
+        entity_data.is_initializing = true;
+        for &entity_id in &entity_ids {
+            properties.clone().set_properties(entity_data, entity_id);
+        }
+        entity_data.is_initializing = false;
+
+        for &entity_id in &entity_ids {
+            if entity_id.0 < first_new_id {
+                // Indexes won't revisit an id they have passed, so every index needs it now.
+                let context: &Context = self;
+                // Will not panic, as adding an entity creates the `EntityData`.
+                let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+                entity_data
+                    .property_indexes
+                    .borrow_mut()
+                    .add_reused_entity(context, entity_id);
+            } else {
+                T::index_new_entity::<K>(self, entity_id);
+            }
+        }
+        for &entity_id in &entity_ids {
+            self.emit_event(EntityCreatedEvent::<K>::new(entity_id));
+        }
+
+        Ok(entity_ids)
+    }
+
+    fn remove_entity(&mut self, entity_id: EntityId) -> Result<(), IxaError> {
+        self.remove_entity_of::<DefaultEntity>(entity_id)
+    }
+
+    fn remove_entity_of<K: EntityKind>(&mut self, entity_id: EntityId) -> Result<(), IxaError> {
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        if !entity_data.entity_exists(entity_id) {
+            return Err(IxaError::IxaError(format!("{entity_id:?} does not exist")));
+        }
+
+        // Indexes are updated first, because removing an entity from an index looks up its value.
+        let index_update_fns: Vec<IndexUpdateFns> =
+            entity_data.index_update_fns.values().copied().collect();
+        for index_update_fns in index_update_fns {
+            (index_update_fns.remove)(self, entity_id);
+        }
+        self.get_data_container_mut::<EntityData<K>>().remove_entity(entity_id)
+    }
+
+    fn entity_exists(&self, entity_id: EntityId) -> bool {
+        self.entity_exists_of::<DefaultEntity>(entity_id)
+    }
+
+    fn entity_exists_of<K: EntityKind>(&self, entity_id: EntityId) -> bool {
+        self.get_data_container::<EntityData<K>>()
+            .is_some_and(|entity_data| entity_data.entity_exists(entity_id))
+    }
+
+    fn entity_id_from_index(&self, index: usize) -> Result<EntityId, IxaError> {
+        self.entity_id_from_index_of::<DefaultEntity>(index)
+    }
+
+    fn entity_id_from_index_of<K: EntityKind>(&self, index: usize) -> Result<EntityId, IxaError> {
+        let entity_id = EntityId(index);
+        if !self.entity_exists_of::<K>(entity_id) {
+            return Err(IxaError::IxaError(format!("No entity with index {index}")));
+        }
         Ok(entity_id)
     }
 
+    fn describe_entity(&mut self, entity_id: EntityId) -> Vec<(String, String)> {
+        self.describe_entity_of::<DefaultEntity>(entity_id)
+    }
+
+    fn describe_entity_of<K: EntityKind>(&mut self, entity_id: EntityId) -> Vec<(String, String)> {
+        if !self.entity_exists_of::<K>(entity_id) {
+            return Vec::new();
+        }
+        let context: &Context = self;
+        // Will not panic, as the entity exists.
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+        entity_data
+            .property_metadata
+            .iter()
+            .filter_map(|property_info| {
+                let debug_value = entity_data.debug_value_fns[&property_info.type_id()];
+                let value = debug_value(context, entity_id)?;
+                Some((property_info.name().to_string(), value))
+            })
+            .collect()
+    }
+
     /// Gets a copy of the value of the property for the given entity.
     fn get_property<T: Property>(&mut self, entity_id: EntityId) -> Option<T> {
-        T::register(self);
-        T::compute(self, entity_id)
+        self.get_property_of::<DefaultEntity, T>(entity_id)
+    }
+
+    fn get_property_of<K: EntityKind, T: Property>(&mut self, entity_id: EntityId) -> Option<T> {
+        T::register_for::<K>(self);
+        T::compute_for::<K>(self, entity_id)
+    }
+
+    fn get_property_ref<T: Property>(&self, entity_id: EntityId) -> Option<&T> {
+        self.get_property_ref_of::<DefaultEntity, T>(entity_id)
+    }
+
+    fn get_property_ref_of<K: EntityKind, T: Property>(&self, entity_id: EntityId) -> Option<&T> {
+        assert!(!T::is_derived(), "Cannot borrow derived property {}", T::name());
+        let entity_data = self
+            .get_data_container::<EntityData<K>>()
+            .filter(|entity_data| {
+                entity_data
+                    .registered_derived_properties
+                    .contains(&type_of::<T>())
+            });
+        let Some(entity_data) = entity_data else {
+            panic!("Property {} is not registered", T::name());
+        };
+        entity_data
+            .get_property_ref(entity_id)
+            .or_else(|| entity_data.get_default_ref())
     }
 
     /// Gets a mutable reference to the value of the property for the given entity.
     fn get_property_mut<T: Property>(&mut self, entity_id: EntityId) -> &mut Option<T> {
+        self.get_property_mut_of::<DefaultEntity, T>(entity_id)
+    }
+
+    fn get_property_mut_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+    ) -> &mut Option<T> {
         assert!(!T::is_derived());
-        T::register(self);
-        self.get_data_container_mut::<EntityData>()
+        T::register_for::<K>(self);
+        self.get_data_container_mut::<EntityData<K>>()
             .get_property_mut(entity_id)
     }
 
+    fn iter_property<T: Property>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, Option<T>)> + '_ {
+        self.iter_property_of::<DefaultEntity, T>()
+    }
+
+    fn iter_property_of<K: EntityKind, T: Property>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, Option<T>)> + '_ {
+        T::register_for::<K>(self);
+        let context: &Context = self;
+        // Will not panic, as registering `T` creates the `EntityData`.
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+        entity_data
+            .entity_iterator()
+            .map(move |entity_id| (entity_id, T::compute_for::<K>(context, entity_id)))
+    }
+
     /// Gets a mutable reference to the value of the property for the given entity if it
-    /// exists, or else sets the property to the default value and returns that.
-    // ToDo: Does not emit event (or respect `PeopleData::is_initializing`)
+    /// exists, or else sets the property to its `Property::default_value`, if it has one, or to
+    /// `default` otherwise, and returns that. Setting the
+    /// default behaves like `set_property()`: it emits a `PropertyChangeEvent<T>` and adds the
+    /// entity to the index for `T`.
+    ///
+    /// # Panics
+    /// Panics if the default fails `Property::validate`.
     fn get_property_or_default<T: Property>(
         &mut self,
         entity_id: EntityId,
         default: T,
     ) -> &mut T {
-        let property: &mut Option<T> = self
-            .get_data_container_mut::<EntityData>()
-            .get_property_mut(entity_id);
+        self.get_property_or_default_of::<DefaultEntity, T>(entity_id, default)
+    }
 
-        match property {
-            Some(value) => value,
+    fn get_property_or_default_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        default: T,
+    ) -> &mut T {
+        T::register_for::<K>(self);
+        let has_value = self
+            .get_data_container_mut::<EntityData<K>>()
+            .get_property_mut::<T>(entity_id)
+            .is_some();
 
-            None => {
-                *property = Some(default);
-                property.as_mut().unwrap()
+        if !has_value {
+            // A property's own default takes precedence, as reads already return it.
+            let default = T::default_value().unwrap_or(default);
+            if let Err(error) = self.set_property_of::<K, T>(entity_id, default) {
+                panic!("{error}");
             }
         }
+
+        self.get_data_container_mut::<EntityData<K>>()
+            .get_property_mut(entity_id)
+            .as_mut()
+            .unwrap()
+    }
+
+    fn set_property<T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        value: T,
+    ) -> Result<(), IxaError> {
+        self.set_property_of::<DefaultEntity, T>(entity_id, value)
+    }
+
+    fn update_entity<T: InitializationList>(
+        &mut self,
+        entity_id: EntityId,
+        properties: T,
+    ) -> Result<(), IxaError> {
+        self.update_entity_of::<DefaultEntity, T>(entity_id, properties)
+    }
+
+    fn update_entity_of<K: EntityKind, T: InitializationList>(
+        &mut self,
+        entity_id: EntityId,
+        properties: T,
+    ) -> Result<(), IxaError> {
+        if !self.entity_exists_of::<K>(entity_id) {
+            return Err(IxaError::IxaError(format!("{entity_id:?} does not exist")));
+        }
+        T::register_properties::<K>(self);
+        // Validate every value first, so that an invalid list leaves the entity unchanged.
+        properties.validate()?;
+        properties.update_properties::<K>(self, entity_id)
     }
 
-    fn set_property<T: Property>(&mut self, entity_id: EntityId, value: T) {
-        let property: &mut Option<T> = self
-            .get_data_container_mut::<EntityData>()
-            .get_property_mut(entity_id);
-        *property = Some(value);
+    fn set_property_of<K: EntityKind, T: Property>(
+        &mut self,
+        entity_id: EntityId,
+        value: T,
+    ) -> Result<(), IxaError> {
+        value.check_valid()?;
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        let is_indexed = entity_data
+            .get_index_ref::<T>()
+            .is_some_and(|index| index.lookup.is_some());
+        // The values of indexed derived properties that depend on `T` may change, too.
+        let dependent_index_update_fns = entity_data.dependent_index_update_fns(type_of::<T>());
+        if is_indexed {
+            self.remove_from_index_maybe::<K, T>(entity_id);
+        }
+        for index_update_fns in &dependent_index_update_fns {
+            (index_update_fns.remove)(self, entity_id);
+        }
+
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        let is_initializing = entity_data.is_initializing;
+        let property: &mut Option<T> = entity_data.get_property_mut(entity_id);
+        let previous = property.replace(value.clone()).or_else(T::default_value);
+
+        if is_indexed {
+            self.add_to_index_maybe::<K, T>(entity_id);
+        }
+        for index_update_fns in &dependent_index_update_fns {
+            (index_update_fns.add)(self, entity_id);
+        }
+
+        if !is_initializing {
+            self.emit_event(PropertyChangeEvent::<T, K> {
+                entity_id,
+                current: value,
+                previous,
+                kind: PhantomData,
+            });
+        }
+        Ok(())
     }
 
     fn query_entities<T: Query>(&mut self, query: T) -> Vec<EntityId> {
-        query.setup(self);
+        self.query_entities_of::<DefaultEntity, T>(query)
+    }
+
+    fn query_entities_of<K: EntityKind, T: Query>(&mut self, query: T) -> Vec<EntityId> {
+        let mut result = self.query_entities_unordered_of::<K, T>(query);
+        result.sort_unstable();
+        result
+    }
+
+    fn query_entities_unordered<T: Query>(&mut self, query: T) -> Vec<EntityId> {
+        self.query_entities_unordered_of::<DefaultEntity, T>(query)
+    }
+
+    fn query_entities_unordered_of<K: EntityKind, T: Query>(
+        &mut self,
+        query: T,
+    ) -> Vec<EntityId> {
+        query.setup::<K>(self);
 
-        let mut result = Vec::new();
-        query.execute_query(
+        let mut result = Vec::with_capacity(query.size_hint::<K>(self).unwrap_or(0));
+        query.execute_query::<K>(
             self,
             |entity| {
                 result.push(entity);
@@ -126,9 +890,16 @@ impl ContextEntityExt for Context {
     }
 
     fn query_entity_count<T: Query>(&mut self, q: T) -> usize {
-        T::setup(&q, self);
+        self.query_entity_count_of::<DefaultEntity, T>(q)
+    }
+
+    fn query_entity_count_of<K: EntityKind, T: Query>(&mut self, q: T) -> usize {
+        T::setup::<K>(&q, self);
+        if let Some(count) = q.exact_count::<K>(self) {
+            return count;
+        }
         let mut count: usize = 0;
-        q.execute_query(self,|_person| {
+        q.execute_query::<K>(self,|_person| {
             count += 1;
         } );
 
@@ -136,61 +907,281 @@ impl ContextEntityExt for Context {
     }
 
     fn match_entity<T: Query>(&mut self, entity_id: EntityId, q: T) -> bool {
-        q.match_entity(self, entity_id)
+        self.match_entity_of::<DefaultEntity, T>(entity_id, q)
     }
 
-}
+    fn match_entity_of<K: EntityKind, T: Query>(&mut self, entity_id: EntityId, q: T) -> bool {
+        q.match_entity::<K>(self, entity_id)
+    }
 
-pub(crate) trait ContextEntityExtInternal {
-    /// Create the index for the given property. Note that this does not populate the index. That happens lazily.
-    fn index_property<T: Property>(&mut self);
-    /// Reports whether the property has already been registered for this context.
-    fn is_registered<T: Property>(&mut self) -> bool;
-    fn register_indexer<T: Property>(&mut self);
-    fn add_to_index_maybe<T: Property>(&mut self, entity_id: EntityId);
-    fn remove_from_index_maybe<T: Property>(&mut self, entity_id: EntityId);
-    /// Registers the property with all of its dependencies and then registers an index for the type.
-    fn register_derived_property<T: Property>(&mut self);
-    fn register_nonderived_property<T: Property>(&mut self);
-    /// A version of `get_property` that doesn't need a mutable context. This can only be called from context in which
-    /// you know `Property::register` has already been called.
-    fn get_property_internal<T: Property>(&self, entity_id: EntityId) -> Option<T>;
-}
+    fn index_property<T: Property>(&mut self) -> bool {
+        self.index_property_of::<DefaultEntity, T>()
+    }
 
-impl ContextEntityExtInternal for Context {
-    /// Create the index for the given property. Note that this does not populate the index. That happens lazily.
-    fn index_property<T: Property>(&mut self) {
-        T::register(self);
+    fn index_property_of<K: EntityKind, T: Property>(&mut self) -> bool {
+        T::register_for::<K>(self);
 
-        let data_container = self.get_data_container_mut::<EntityData>();
+        let data_container = self.get_data_container_mut::<EntityData<K>>();
         let index = data_container.get_index_mut::<T>();
         if index.lookup.is_none() {
             index.lookup = Some(HashMap::default());
+            true
+        } else {
+            false
         }
     }
 
-    /// Reports whether the property has already been registered for this context.
-    fn is_registered<T: Property>(&mut self) -> bool {
-        let data_container = self.get_data_container_mut::<EntityData>();
-        data_container.registered_derived_properties.contains(&type_of::<T>())
+    fn track_property_changes<T: Property>(&mut self) {
+        self.track_property_changes_of::<DefaultEntity, T>();
     }
 
-    fn register_indexer<T: Property>(&mut self) {
+    fn track_property_changes_of<K: EntityKind, T: Property>(&mut self) {
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        if entity_data.property_change_times.contains_key(&type_of::<T>()) {
+            return;
+        }
+        entity_data.property_change_times.insert(type_of::<T>(), Vec::new());
+
+        self.subscribe_to_event(|context: &mut Context, event: PropertyChangeEvent<T, K>| {
+            let time = context.get_current_time();
+            let idx = event.entity_id.0;
+            let change_times = context
+                .get_data_container_mut::<EntityData<K>>()
+                .property_change_times
+                .get_mut(&type_of::<T>())
+                .unwrap();
+            if idx >= change_times.len() {
+                change_times.resize(idx + 1, None);
+            }
+            change_times[idx] = Some(time);
+        });
+    }
+
+    fn time_since_last_change<T: Property>(&self, entity_id: EntityId) -> Option<f64> {
+        self.time_since_last_change_of::<DefaultEntity, T>(entity_id)
+    }
+
+    fn time_since_last_change_of<K: EntityKind, T: Property>(
+        &self,
+        entity_id: EntityId,
+    ) -> Option<f64> {
+        let last_change = self
+            .get_data_container::<EntityData<K>>()?
+            .property_change_times
+            .get(&type_of::<T>())?
+            .get(entity_id.0)
+            .copied()
+            .flatten()?;
+        Some(self.get_current_time() - last_change)
+    }
+
+    fn export_query<T: Query>(
+        &mut self,
+        q: T,
+        report_name: &str,
+        columns: &[&str],
+    ) -> Result<(), IxaError> {
+        self.export_query_of::<DefaultEntity, T>(q, report_name, columns)
+    }
+
+    fn export_query_of<K: EntityKind, T: Query>(
+        &mut self,
+        q: T,
+        report_name: &str,
+        columns: &[&str],
+    ) -> Result<(), IxaError> {
+        q.setup::<K>(self);
+
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
+        let mut report_value_fns: Vec<ReportValueFn> = Vec::with_capacity(columns.len());
+        for column in columns {
+            match entity_data.report_value_fns.get(*column) {
+                Some(report_value_fn) => report_value_fns.push(*report_value_fn),
+                None => return Err(IxaError::PropertyNotRegistered((*column).to_string())),
+            }
+        }
+
+        let mut writer = self
+            .get_data_container_mut::<ReportData>()
+            .create_writer(report_name)?;
+        writer.write_record(["time", "entity_id"].iter().chain(columns))?;
+
+        let time = self.get_current_time().to_string();
+        let mut result = Ok(());
+        q.execute_query::<K>(self, |entity_id| {
+            if result.is_err() {
+                return;
+            }
+            let mut record = vec![time.clone(), entity_id.0.to_string()];
+            for report_value_fn in &report_value_fns {
+                record.push(report_value_fn(self, entity_id).unwrap_or_default());
+            }
+            result = writer.write_record(&record);
+        });
+        result?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn add_population_column<T: Property + Serialize>(&mut self) {
+        self.add_population_column_of::<DefaultEntity, T>();
+    }
+
+    fn add_population_column_of<K: EntityKind, T: Property + Serialize>(&mut self) {
+        T::register_for::<K>(self);
+        let population_columns =
+            &mut self.get_data_container_mut::<EntityData<K>>().population_columns;
+        if !population_columns.iter().any(|(name, _)| *name == T::name()) {
+            population_columns.push((T::name(), serialize_value::<K, T>));
+        }
+    }
+
+    fn export_population_csv(&mut self, path: &Path) -> Result<(), IxaError> {
+        self.export_population_csv_of::<DefaultEntity>(path)
+    }
+
+    fn export_population_csv_of<K: EntityKind>(&mut self, path: &Path) -> Result<(), IxaError> {
+        let context: &Context = self;
+        let Some(entity_data) = context
+            .get_data_container::<EntityData<K>>()
+            .filter(|entity_data| !entity_data.population_columns.is_empty())
+        else {
+            return Err(IxaError::from("No population columns have been added"));
+        };
+        let serialize_value_fns: Vec<SerializeValueFn> = entity_data
+            .population_columns
+            .iter()
+            .map(|(_, serialize_value_fn)| *serialize_value_fn)
+            .collect();
+
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(entity_data.population_columns.iter().map(|(name, _)| name))?;
+        for entity_id in entity_data.entity_iterator() {
+            let mut record = Vec::with_capacity(serialize_value_fns.len());
+            for serialize_value_fn in &serialize_value_fns {
+                record.push(serialize_value_fn(context, entity_id)?.unwrap_or_default());
+            }
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn import_population_csv<T: InitializationList + DeserializeOwned>(
+        &mut self,
+        path: &Path,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        self.import_population_csv_of::<DefaultEntity, T>(path)
+    }
+
+    fn import_population_csv_of<K: EntityKind, T: InitializationList + DeserializeOwned>(
+        &mut self,
+        path: &Path,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        let reader = csv::Reader::from_path(path)?;
+        add_entities_from_csv::<K, T, _>(self, reader, Some(path))
+    }
+
+    fn load_entities_from_csv_reader<T: InitializationList + DeserializeOwned, R: Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        self.load_entities_from_csv_reader_of::<DefaultEntity, T, R>(reader)
+    }
+
+    fn load_entities_from_csv_reader_of<
+        K: EntityKind,
+        T: InitializationList + DeserializeOwned,
+        R: Read,
+    >(
+        &mut self,
+        reader: R,
+    ) -> Result<Vec<EntityId>, IxaError> {
+        add_entities_from_csv::<K, T, _>(self, csv::Reader::from_reader(reader), None)
+    }
+}
+
+/// Adds an entity of kind `K` for each row read by `reader`, as described in
+/// `ContextEntityExt::import_population_csv`. Errors in a row are wrapped in an
+/// `IxaError::CsvRowError` holding `path` and the line of the row.
+fn add_entities_from_csv<K: EntityKind, T: InitializationList + DeserializeOwned, R: Read>(
+    context: &mut Context,
+    mut reader: csv::Reader<R>,
+    path: Option<&Path>,
+) -> Result<Vec<EntityId>, IxaError> {
+    let line_error = |line: u64, error: IxaError| IxaError::CsvRowError {
+        path: path.map(Path::to_path_buf),
+        line,
+        error: Box::new(error),
+    };
+    let headers = reader.headers()?.clone();
+    let mut record = csv::StringRecord::new();
+    let mut entity_ids = Vec::new();
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(error) => {
+                let line = error.position().map_or(0, csv::Position::line);
+                return Err(line_error(line, error.into()));
+            }
+        }
+        // Will not panic, as a record that has been read has a position.
+        let line = record.position().unwrap().line();
+        let properties: T = record
+            .deserialize(Some(&headers))
+            .map_err(|error| line_error(line, error.into()))?;
+        let entity_id = context
+            .add_entity_of::<K, T>(properties)
+            .map_err(|error| line_error(line, error))?;
+        entity_ids.push(entity_id);
+    }
+    Ok(entity_ids)
+}
+
+pub(crate) trait ContextEntityExtInternal {
+    /// Reports whether the property has already been registered for this context.
+    fn is_registered<K: EntityKind, T: Property>(&mut self) -> bool;
+    fn register_indexer<K: EntityKind, T: Property>(&mut self);
+    fn add_to_index_maybe<K: EntityKind, T: Property>(&mut self, entity_id: EntityId);
+    fn remove_from_index_maybe<K: EntityKind, T: Property>(&mut self, entity_id: EntityId);
+    /// Registers the property with all of its dependencies and then registers an index for the type.
+    fn register_derived_property<K: EntityKind, T: Property>(&mut self);
+    fn register_nonderived_property<K: EntityKind, T: Property>(&mut self);
+    /// A version of `get_property` that doesn't need a mutable context. This can only be called from context in which
+    /// you know `Property::register` has already been called.
+    fn get_property_internal<K: EntityKind, T: Property>(&self, entity_id: EntityId)
+        -> Option<T>;
+}
+
+impl ContextEntityExtInternal for Context {
+    /// Reports whether the property has already been registered for this context.
+    fn is_registered<K: EntityKind, T: Property>(&mut self) -> bool {
+        let data_container = self.get_data_container_mut::<EntityData<K>>();
+        data_container.registered_derived_properties.contains(&type_of::<T>())
+    }
+
+    fn register_indexer<K: EntityKind, T: Property>(&mut self) {
         let property_indexes = self
-            .get_data_container_mut::<EntityData>()
+            .get_data_container_mut::<EntityData<K>>()
             .property_indexes
             .get_mut();
         let type_id = type_of::<T>();
 
         // This method should only be called during initial Property registration.
         assert!(!property_indexes.contains_key(&type_id));
-        property_indexes.insert(Index::<T>::new());
+        property_indexes.insert(Index::<T, K>::new());
     }
 
-    fn add_to_index_maybe<T: Property>(&mut self, entity_id: EntityId) {
-        let value = self.get_property_internal::<T>(entity_id).clone();
+    fn add_to_index_maybe<K: EntityKind, T: Property>(&mut self, entity_id: EntityId) {
+        // Entities without a value are not indexed.
+        let Some(value) = self.get_property_internal::<K, T>(entity_id) else {
+            return;
+        };
         let index_value = IndexValue::new(&value);
-        let entity_data = self.get_data_container_mut::<EntityData>();
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
 
         let index = entity_data.get_index_mut::<T>();
         if index.lookup.is_some() {
@@ -198,26 +1189,28 @@ impl ContextEntityExtInternal for Context {
         }
     }
 
-    fn remove_from_index_maybe<T: Property>(&mut self, entity_id: EntityId) {
-        let value = self.get_property_internal::<T>(entity_id).clone();
+    fn remove_from_index_maybe<K: EntityKind, T: Property>(&mut self, entity_id: EntityId) {
+        let Some(value) = self.get_property_internal::<K, T>(entity_id) else {
+            return;
+        };
         let index_value = IndexValue::new(&value);
-        let entity_data = self.get_data_container_mut::<EntityData>();
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
 
         let index = entity_data.get_index_mut::<T>();
-        if let Some(lookup) = &mut index.lookup {
-            if let Some(index_set) = lookup.get_mut(&index_value) {
-                index_set.remove(&entity_id);
-                // Clean up the entry if there are no entities
-                if index_set.is_empty() {
-                    lookup.remove(&index_value);
-                }
+        if let Some(lookup) = &mut index.lookup
+            && let Some(index_set) = lookup.get_mut(&index_value)
+        {
+            index_set.remove(&entity_id);
+            // Clean up the entry if there are no entities
+            if index_set.is_empty() {
+                lookup.remove(&index_value);
             }
         }
     }
 
     /// Registers the type with all of its dependencies and then registers an index for the type.
-    fn register_derived_property<T: Property>(&mut self) {
-        let entity_data = self.get_data_container_mut::<EntityData>();
+    fn register_derived_property<K: EntityKind, T: Property>(&mut self) {
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
         let type_id = type_of::<T>();
 
         // This method should only be called during initial Property registration.
@@ -229,15 +1222,46 @@ impl ContextEntityExtInternal for Context {
             let derived_prop_list = entity_data.dependency_map.entry(dependency).or_default();
             derived_prop_list.push(type_id);
         }
+        if T::is_memoized() {
+            entity_data.cache_clear_fns.insert(type_id, clear_value::<T>);
+        }
 
         // Also do everything that needs to be done for nonderived properties
-        self.register_nonderived_property::<T>();
+        self.register_nonderived_property::<K, T>();
+        T::register_global_dependencies::<K>(self);
     }
 
-    fn register_nonderived_property<T: Property>(&mut self) {
-        let entity_data = self.get_data_container_mut::<EntityData>();
+    fn register_nonderived_property<K: EntityKind, T: Property>(&mut self) {
+        let entity_data = self.get_data_container_mut::<EntityData<K>>();
         let property_info =T::property_info();
 
+        entity_data.report_value_fns.insert(
+            T::name().to_string(),
+            |context, entity_id| {
+                T::compute_for::<K>(context, entity_id).map(|value| value.to_report_value())
+            },
+        );
+        entity_data.debug_value_fns.insert(type_of::<T>(), |context, entity_id| {
+            T::compute_for::<K>(context, entity_id).map(|value| format!("{value:?}"))
+        });
+        entity_data.index_update_fns.insert(
+            type_of::<T>(),
+            IndexUpdateFns {
+                add: Context::add_to_index_maybe::<K, T>,
+                remove: Context::remove_from_index_maybe::<K, T>,
+            },
+        );
+        if !T::is_derived() {
+            entity_data.reserve_fns.push(|properties_map, total| {
+                let property_store = properties_map.get_container_mut::<T>();
+                property_store.reserve(total.saturating_sub(property_store.len()));
+            });
+            // Size the store for the existing population up front so back-filling values on a
+            // large population doesn't reallocate repeatedly.
+            let population = entity_data.entity_count.max(entity_data.reserved_entities);
+            let property_store = entity_data.properties_map.get_container_mut::<T>();
+            property_store.reserve(population.saturating_sub(property_store.len()));
+        }
         entity_data
             .registered_derived_properties
             .push(property_info.type_id());
@@ -245,10 +1269,938 @@ impl ContextEntityExtInternal for Context {
             .property_metadata
             .push(property_info);
 
-        self.register_indexer::<T>();
+        self.register_indexer::<K, T>();
+        if T::is_indexed() {
+            self.get_data_container_mut::<EntityData<K>>()
+                .get_index_mut::<T>()
+                .lookup = Some(HashMap::default());
+        }
+    }
+
+    fn get_property_internal<K: EntityKind, T: Property>(&self, entity_id: EntityId)
+        -> Option<T>
+    {
+        T::compute_for::<K>(self, entity_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_derived_property, ContextReportExt};
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    enum InfectionStatus {
+        S,
+        I,
+        R,
+    }
+    impl Property for InfectionStatus {
+        fn name() -> &'static str {
+            "InfectionStatus"
+        }
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct Age(u8);
+    impl Property for Age {
+        fn name() -> &'static str {
+            "Age"
+        }
+
+        fn to_report_value(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    #[test]
+    fn add_entity_emits_created_event() {
+        let mut context = Context::new();
+        let created = Rc::new(RefCell::new(Vec::new()));
+
+        let created_clone = created.clone();
+        context.subscribe_to_event(move |context, event: EntityCreatedEvent| {
+            // The initial properties are already set when the event is emitted.
+            let status = context.get_property::<InfectionStatus>(event.entity_id);
+            created_clone.borrow_mut().push((event.entity_id, status));
+        });
+
+        let mut expected = Vec::new();
+        for status in [InfectionStatus::S, InfectionStatus::I] {
+            expected.push((context.add_entity(status).unwrap(), Some(status)));
+        }
+        for entity_id in context.add_entities_with(2, InfectionStatus::R).unwrap() {
+            expected.push((entity_id, Some(InfectionStatus::R)));
+        }
+        let built = context.entity_builder().with(Age(3)).build().unwrap();
+        expected.push((built, None));
+        // Entities of another kind emit `EntityCreatedEvent<Household>` instead.
+        context.add_entity_of::<Household, _>(Age(4)).unwrap();
+
+        assert_eq!(*created.borrow(), expected);
+    }
+
+    #[test]
+    fn set_property_emits_change_event() {
+        let mut context = Context::new();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, event: PropertyChangeEvent<InfectionStatus>| {
+            events_clone.borrow_mut().push((event.entity_id, event.previous, event.current));
+        });
+
+        // Initial values don't generate events.
+        let entity_id = context.add_entity(InfectionStatus::S).unwrap();
+        assert!(events.borrow().is_empty());
+
+        context.set_property(entity_id, InfectionStatus::I).unwrap();
+        assert_eq!(
+            *events.borrow(),
+            vec![(entity_id, Some(InfectionStatus::S), InfectionStatus::I)]
+        );
+    }
+
+    #[test]
+    fn add_entities_matches_loop() {
+        const POPULATION: usize = 1000;
+        let mut looped = Context::new();
+        let looped_ids: Vec<EntityId> =
+            (0..POPULATION).map(|_| looped.add_entity(()).unwrap()).collect();
+
+        let mut bulk = Context::new();
+        let bulk_ids = bulk.add_entities(POPULATION).unwrap();
+        assert_eq!(bulk_ids, looped_ids);
+        assert_eq!(bulk.get_entity_count(), POPULATION);
+        assert_eq!(bulk.get_property::<Age>(bulk_ids[500]), None);
+        assert_eq!(bulk.add_entity(()).unwrap(), EntityId(POPULATION));
+    }
+
+    #[test]
+    fn add_entities_with() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        let first = context.add_entity(InfectionStatus::I).unwrap();
+        let second = context.add_entity(InfectionStatus::I).unwrap();
+        context.add_entity(InfectionStatus::I).unwrap();
+        context.remove_entity(second).unwrap();
+        context.remove_entity(first).unwrap();
+        assert_eq!(context.query_entity_count(InfectionStatus::I), 1);
+
+        // The removed ids are reused first, so the ids aren't contiguous.
+        let entity_ids = context.add_entities_with(3, (InfectionStatus::S, Age(5))).unwrap();
+        assert_eq!(entity_ids, vec![first, second, EntityId(3)]);
+        assert_eq!(context.get_property::<Age>(second), Some(Age(5)));
+        // The indexes pick up the reused ids even though they have already passed them.
+        assert_eq!(context.query_entities(InfectionStatus::S), entity_ids);
+        assert_eq!(context.add_entity(InfectionStatus::R).unwrap(), EntityId(4));
+    }
+
+    #[test]
+    fn remove_entity() {
+        let mut context = Context::new();
+        let first = context.add_entity((InfectionStatus::S, Age(10))).unwrap();
+        let second = context.add_entity((InfectionStatus::S, Age(20))).unwrap();
+        let third = context.add_entity((InfectionStatus::I, Age(30))).unwrap();
+        context.index_property::<InfectionStatus>();
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![first, second]);
+
+        context.remove_entity(second).unwrap();
+        assert_eq!(context.get_entity_count(), 2);
+        assert_eq!(context.get_property::<Age>(second), None);
+        assert_eq!(context.get_property::<InfectionStatus>(second), None);
+        // Indexed and unindexed queries both skip the removed entity.
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![first]);
+        assert!(context.query_entities(Age(20)).is_empty());
+        let ages: Vec<_> = context.iter_property::<Age>().collect();
+        assert_eq!(ages, vec![(first, Some(Age(10))), (third, Some(Age(30)))]);
+
+        assert_eq!(context.get_entity_count(), 2);
+    }
+
+    #[test]
+    fn remove_entity_reuses_id() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        let entities: Vec<EntityId> = (0..4)
+            .map(|_| context.add_entity((InfectionStatus::S, Age(10))).unwrap())
+            .collect();
+        assert_eq!(context.query_entity_count(InfectionStatus::S), 4);
+
+        context.remove_entity(entities[2]).unwrap();
+        context.remove_entity(entities[1]).unwrap();
+
+        // The lowest removed id is reused first, and the index picks up the new entity even
+        // though it has already indexed past its id.
+        let reused = context.add_entity((InfectionStatus::I, Age(20))).unwrap();
+        assert_eq!(reused, entities[1]);
+        assert_eq!(context.get_entity_count(), 3);
+        assert_eq!(context.get_property::<Age>(reused), Some(Age(20)));
+        assert_eq!(context.query_entities(InfectionStatus::I), vec![reused]);
+        assert_eq!(context.query_entity_count(InfectionStatus::S), 2);
+
+        assert_eq!(context.add_entity(InfectionStatus::R).unwrap(), entities[2]);
+        assert_eq!(context.add_entity(InfectionStatus::R).unwrap(), EntityId(4));
+        assert_eq!(context.query_entity_count(InfectionStatus::R), 2);
+        // Reused entities don't inherit values of properties they weren't given.
+        assert_eq!(context.get_property::<Age>(entities[2]), None);
+    }
+
+    #[test]
+    fn reused_entity_without_indexed_value() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        let first = context.add_entity(InfectionStatus::S).unwrap();
+        let second = context.add_entity(InfectionStatus::S).unwrap();
+        assert_eq!(context.query_entity_count(InfectionStatus::S), 2);
+
+        // A reused id without a value for the indexed property is left out of the index.
+        context.remove_entity(first).unwrap();
+        assert_eq!(context.add_entity(Age(5)).unwrap(), first);
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![second]);
+
+        context.remove_entity(first).unwrap();
+        assert_eq!(context.entity_builder().with(Age(6)).build().unwrap(), first);
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![second]);
+
+        context.set_property(first, InfectionStatus::I).unwrap();
+        assert_eq!(context.query_entities(InfectionStatus::I), vec![first]);
+    }
+
+    #[test]
+    fn remove_entity_before_indexing() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        let first = context.add_entity(InfectionStatus::S).unwrap();
+        let second = context.add_entity(InfectionStatus::S).unwrap();
+
+        // The index hasn't seen either entity yet, and mustn't pick up the removed one.
+        context.remove_entity(first).unwrap();
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![second]);
+        assert_eq!(context.query_entity_count(InfectionStatus::S), 1);
+    }
+
+    #[test]
+    fn remove_missing_entity() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(InfectionStatus::S).unwrap();
+        assert!(context.remove_entity(EntityId(1)).is_err());
+
+        context.remove_entity(entity_id).unwrap();
+        let result = context.remove_entity(entity_id);
+        assert!(matches!(result, Err(IxaError::IxaError(message))
+            if message == "EntityId(0) does not exist"));
+    }
+
+    #[test]
+    fn is_initializing_suppresses_change_events() {
+        let mut context = Context::new();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, event: PropertyChangeEvent<InfectionStatus>| {
+            events_clone.borrow_mut().push(event.current);
+        });
+
+        let entity_id = context.add_entity(()).unwrap();
+        context.get_data_container_mut::<EntityData>().is_initializing = true;
+        context.set_property(entity_id, InfectionStatus::S).unwrap();
+        context.get_data_container_mut::<EntityData>().is_initializing = false;
+        assert!(events.borrow().is_empty());
+        assert_eq!(context.get_property(entity_id), Some(InfectionStatus::S));
+
+        context.set_property(entity_id, InfectionStatus::I).unwrap();
+        assert_eq!(*events.borrow(), vec![InfectionStatus::I]);
+
+        // Bulk creation sets the flag, too.
+        context.add_entities_with(2, InfectionStatus::R).unwrap();
+        assert_eq!(*events.borrow(), vec![InfectionStatus::I]);
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct Alive(bool);
+    impl Property for Alive {
+        fn default_value() -> Option<Self> {
+            Some(Alive(true))
+        }
+    }
+
+    #[test]
+    fn property_default_value() {
+        let mut context = Context::new();
+        let never_set = context.add_entity(()).unwrap();
+        let dead = context.add_entity(Alive(false)).unwrap();
+        assert_eq!(context.get_property::<Alive>(never_set), Some(Alive(true)));
+        assert_eq!(context.get_property_ref::<Alive>(never_set), Some(&Alive(true)));
+        assert_eq!(context.get_property_ref::<Alive>(dead), Some(&Alive(false)));
+        assert_eq!(context.query_entities(Alive(true)), vec![never_set]);
+
+        // Indexing doesn't panic on the entity without a stored value.
+        context.index_property::<Alive>();
+        assert_eq!(context.query_entities(Alive(true)), vec![never_set]);
+        assert_eq!(context.query_entities(Alive(false)), vec![dead]);
+
+        // The change event reports the default as the previous value.
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, event: PropertyChangeEvent<Alive>| {
+            events_clone.borrow_mut().push((event.previous, event.current));
+        });
+        context.set_property(never_set, Alive(false)).unwrap();
+        assert_eq!(*events.borrow(), vec![(Some(Alive(true)), Alive(false))]);
+        assert!(context.query_entities(Alive(true)).is_empty());
+
+        // `get_property_or_default` prefers the property's own default.
+        let newborn = context.add_entity(()).unwrap();
+        assert_eq!(*context.get_property_or_default(newborn, Alive(false)), Alive(true));
+    }
+
+    #[test]
+    fn get_property_or_default() {
+        let mut context = Context::new();
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, event: PropertyChangeEvent<InfectionStatus>| {
+            events_clone.borrow_mut().push((event.entity_id, event.previous, event.current));
+        });
+
+        let with_value = context.add_entity(InfectionStatus::I).unwrap();
+        let without_value = context.add_entity(()).unwrap();
+        context.index_property::<InfectionStatus>();
+
+        // An existing value is returned without being replaced.
+        assert_eq!(
+            *context.get_property_or_default(with_value, InfectionStatus::S),
+            InfectionStatus::I
+        );
+        assert!(events.borrow().is_empty());
+
+        assert_eq!(
+            *context.get_property_or_default(without_value, InfectionStatus::S),
+            InfectionStatus::S
+        );
+        assert_eq!(*events.borrow(), vec![(without_value, None, InfectionStatus::S)]);
+        assert_eq!(context.query_entities(InfectionStatus::S), vec![without_value]);
+    }
+
+    #[test]
+    fn get_property_or_default_respects_is_initializing() {
+        let mut context = Context::new();
+        let events = Rc::new(RefCell::new(0));
+
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, _: PropertyChangeEvent<InfectionStatus>| {
+            *events_clone.borrow_mut() += 1;
+        });
+
+        let entity_id = context.add_entity(()).unwrap();
+        context.get_data_container_mut::<EntityData>().is_initializing = true;
+        *context.get_property_or_default(entity_id, InfectionStatus::S) = InfectionStatus::R;
+        context.get_data_container_mut::<EntityData>().is_initializing = false;
+
+        assert_eq!(*events.borrow(), 0);
+        // The returned reference is to the stored value.
+        assert_eq!(context.get_property(entity_id), Some(InfectionStatus::R));
+    }
+
+    #[test]
+    fn time_since_last_change() {
+        let mut context = Context::new();
+        context.track_property_changes::<InfectionStatus>();
+        let entity_id = context.add_entity(InfectionStatus::S).unwrap();
+        assert_eq!(context.time_since_last_change::<InfectionStatus>(entity_id), None);
+
+        context.add_plan(2.0, move |context| {
+            context.set_property(entity_id, InfectionStatus::I).unwrap();
+        });
+        context.add_plan(5.0, move |context| {
+            assert_eq!(
+                context.time_since_last_change::<InfectionStatus>(entity_id),
+                Some(3.0)
+            );
+            context.set_property(entity_id, InfectionStatus::R).unwrap();
+        });
+        context.add_plan(5.5, move |context| {
+            assert_eq!(
+                context.time_since_last_change::<InfectionStatus>(entity_id),
+                Some(0.5)
+            );
+        });
+        context.execute();
+    }
+
+    #[test]
+    fn time_since_last_change_untracked() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(InfectionStatus::S).unwrap();
+        context.set_property(entity_id, InfectionStatus::I).unwrap();
+        assert_eq!(context.time_since_last_change::<InfectionStatus>(entity_id), None);
+    }
+
+    #[test]
+    fn get_property_ref_reads_without_mut() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity((Age(30), InfectionStatus::I)).unwrap();
+
+        let context = &context;
+        assert_eq!(
+            (
+                context.get_property_ref::<Age>(entity_id),
+                context.get_property_ref::<InfectionStatus>(entity_id)
+            ),
+            (Some(&Age(30)), Some(&InfectionStatus::I))
+        );
+    }
+
+    static LABEL_CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    struct Label(String);
+    impl Property for Label {}
+
+    impl Clone for Label {
+        fn clone(&self) -> Self {
+            LABEL_CLONES.fetch_add(1, Ordering::SeqCst);
+            Label(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn get_property_ref_does_not_clone() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(Label("a long label".to_string())).unwrap();
+        let unlabeled = context.add_entity(Age(30)).unwrap();
+
+        let clones = LABEL_CLONES.load(Ordering::SeqCst);
+        let label = context.get_property_ref::<Label>(entity_id).unwrap();
+        assert_eq!(label.0, "a long label");
+        assert_eq!(context.get_property_ref::<Label>(unlabeled), None);
+        assert_eq!(LABEL_CLONES.load(Ordering::SeqCst), clones);
+    }
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct Adult(bool);
+    define_derived_property!(Adult, [Age], |age| {
+        let Age(years) = age;
+        Some(Adult(years >= 18))
+    });
+
+    #[test]
+    #[should_panic(expected = "Cannot borrow derived property")]
+    fn get_property_ref_derived() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(Age(30)).unwrap();
+        context.get_property::<Adult>(entity_id);
+        context.get_property_ref::<Adult>(entity_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not registered")]
+    fn get_property_ref_unregistered() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(Age(30)).unwrap();
+        context.get_property_ref::<InfectionStatus>(entity_id);
+    }
+
+    fn add_sir_population(context: &mut Context) {
+        for (status, count) in [
+            (InfectionStatus::S, 5),
+            (InfectionStatus::I, 3),
+            (InfectionStatus::R, 2),
+        ] {
+            for _ in 0..count {
+                context.add_entity(status).unwrap();
+            }
+        }
+        // Entities without a status aren't counted.
+        context.add_entity(Age(30)).unwrap();
+    }
+
+    #[test]
+    fn count_by() {
+        let mut context = Context::new();
+        add_sir_population(&mut context);
+
+        let counts = context.count_by::<InfectionStatus>();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&InfectionStatus::S], 5);
+        assert_eq!(counts[&InfectionStatus::I], 3);
+        assert_eq!(counts[&InfectionStatus::R], 2);
+    }
+
+    #[test]
+    fn count_by_indexed() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        // Indexing requires every entity to have a value, so every entity gets a status.
+        for (status, count) in [
+            (InfectionStatus::S, 5),
+            (InfectionStatus::I, 3),
+            (InfectionStatus::R, 2),
+        ] {
+            for _ in 0..count {
+                context.add_entity(status).unwrap();
+            }
+        }
+
+        let counts = context.count_by::<InfectionStatus>();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&InfectionStatus::S], 5);
+        assert_eq!(counts[&InfectionStatus::I], 3);
+        assert_eq!(counts[&InfectionStatus::R], 2);
+    }
+
+    #[test]
+    fn all_index_counts() {
+        let mut context = Context::new();
+        context.index_property::<Age>();
+        context.index_property::<InfectionStatus>();
+        for (age, status) in [
+            (30, InfectionStatus::S),
+            (30, InfectionStatus::I),
+            (40, InfectionStatus::I),
+            (30, InfectionStatus::I),
+        ] {
+            context.add_entity((Age(age), status)).unwrap();
+        }
+
+        let counts = context.all_index_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["Age"].len(), 2);
+        assert_eq!(counts["Age"][&IndexValue::new(&Age(30))], 3);
+        assert_eq!(counts["Age"][&IndexValue::new(&Age(40))], 1);
+        assert_eq!(counts["InfectionStatus"].len(), 2);
+        assert_eq!(counts["InfectionStatus"][&IndexValue::new(&InfectionStatus::S)], 1);
+        assert_eq!(counts["InfectionStatus"][&IndexValue::new(&InfectionStatus::I)], 3);
+    }
+
+    #[test]
+    fn iter_property() {
+        let mut context = Context::new();
+        let first = context.add_entity((Age(30), InfectionStatus::I)).unwrap();
+        let second = context.add_entity(InfectionStatus::S).unwrap();
+        let third = context.add_entity(Age(50)).unwrap();
+
+        let ages: Vec<(EntityId, Option<Age>)> = context.iter_property::<Age>().collect();
+        assert_eq!(
+            ages,
+            vec![(first, Some(Age(30))), (second, None), (third, Some(Age(50)))]
+        );
+    }
+
+    #[test]
+    fn export_query() {
+        let mut context = Context::new();
+        let first = context.add_entity((Age(30), InfectionStatus::I)).unwrap();
+        context.add_entity((Age(40), InfectionStatus::S)).unwrap();
+        let third = context.add_entity(InfectionStatus::I).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf());
+        context
+            .export_query(InfectionStatus::I, "infected", &["Age", "InfectionStatus"])
+            .unwrap();
+
+        let mut reader = csv::Reader::from_path(temp_dir.path().join("infected.csv")).unwrap();
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["time", "entity_id", "Age", "InfectionStatus"]
+        );
+        let mut rows: Vec<Vec<String>> = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["0".to_string(), first.0.to_string(), "30".to_string(), "I".to_string()],
+                vec!["0".to_string(), third.0.to_string(), String::new(), "I".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn export_query_unknown_column() {
+        let mut context = Context::new();
+        context.add_entity(InfectionStatus::I).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf());
+        let result = context.export_query(InfectionStatus::I, "infected", &["Height"]);
+        assert!(matches!(result, Err(IxaError::PropertyNotRegistered(name)) if name == "Height"));
     }
 
-    fn get_property_internal<T: Property>(&self, entity_id: EntityId) -> Option<T> {
-        T::compute(self, entity_id)
+    struct Household;
+    impl EntityKind for Household {}
+
+    #[test]
+    fn entity_kind_api() {
+        let mut context = Context::new();
+        let person = context.add_entity(Age(30)).unwrap();
+        let households = context
+            .add_entities_with_of::<Household, _>(3, (Age(4), InfectionStatus::S))
+            .unwrap();
+        let (first, second, third) = (households[0], households[1], households[2]);
+        assert!(context.entity_exists_of::<Household>(third));
+        assert!(!context.entity_exists(third));
+
+        context.update_entity_of::<Household, _>(first, InfectionStatus::I).unwrap();
+        assert!(context.match_entity_of::<Household, _>(first, InfectionStatus::I));
+        assert!(!context.match_entity(person, InfectionStatus::I));
+        let counts = context.count_by_of::<Household, InfectionStatus>();
+        assert_eq!(counts[&InfectionStatus::S], 2);
+        assert_eq!(counts[&InfectionStatus::I], 1);
+
+        context.track_property_changes_of::<Household, Age>();
+        context.set_property_of::<Household, _>(second, Age(5)).unwrap();
+        assert_eq!(context.time_since_last_change_of::<Household, Age>(second), Some(0.0));
+        assert_eq!(context.time_since_last_change::<Age>(second), None);
+        assert_eq!(context.get_property_ref_of::<Household, Age>(second), Some(&Age(5)));
+        assert_eq!(*context.get_property_or_default_of::<Household, _>(third, Age(9)), Age(4));
+        assert_eq!(
+            context.iter_property_of::<Household, Age>().collect::<Vec<_>>(),
+            vec![(first, Some(Age(4))), (second, Some(Age(5))), (third, Some(Age(4)))]
+        );
+
+        context.index_property_of::<Household, InfectionStatus>();
+        let index_counts = context.all_index_counts_of::<Household>();
+        assert_eq!(index_counts["InfectionStatus"][&IndexValue::new(&InfectionStatus::S)], 2);
+        assert!(!context.all_index_counts().contains_key("InfectionStatus"));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf());
+        context
+            .export_query_of::<Household, _>(InfectionStatus::I, "households", &["Age"])
+            .unwrap();
+        let mut reader = csv::Reader::from_path(temp_dir.path().join("households.csv")).unwrap();
+        let rows: Vec<Vec<String>> = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        assert_eq!(rows, vec![vec!["0".to_string(), first.0.to_string(), "4".to_string()]]);
+
+        context.remove_entity_of::<Household>(first).unwrap();
+        assert!(!context.entity_exists_of::<Household>(first));
+        assert!(context.entity_exists(person));
+        assert_eq!(context.get_entity_count_of::<Household>(), 2);
+    }
+
+    #[test]
+    fn entity_kinds_have_separate_ids() {
+        let mut context = Context::new();
+        let person = context.add_entity(Age(30)).unwrap();
+        let household = context.add_entity_of::<Household, _>(Age(4)).unwrap();
+        context.add_entity_of::<Household, _>(Age(7)).unwrap();
+
+        // The ids overlap, but the entities and their values don't.
+        assert_eq!(person, household);
+        assert_eq!(context.get_entity_count(), 1);
+        assert_eq!(context.get_entity_count_of::<Household>(), 2);
+        assert_eq!(context.get_property::<Age>(person), Some(Age(30)));
+        assert_eq!(context.get_property_of::<Household, Age>(household), Some(Age(4)));
+
+        let person_events = Rc::new(RefCell::new(0));
+        let household_events = Rc::new(RefCell::new(Vec::new()));
+        let person_events_clone = person_events.clone();
+        context.subscribe_to_event(move |_, _: PropertyChangeEvent<Age>| {
+            *person_events_clone.borrow_mut() += 1;
+        });
+        let household_events_clone = household_events.clone();
+        context.subscribe_to_event(move |_, event: PropertyChangeEvent<Age, Household>| {
+            household_events_clone.borrow_mut().push((event.previous, event.current));
+        });
+
+        context.set_property_of::<Household, _>(household, Age(5)).unwrap();
+        assert_eq!(context.get_property::<Age>(person), Some(Age(30)));
+        assert_eq!(context.get_property_of::<Household, Age>(household), Some(Age(5)));
+        context.execute();
+        assert_eq!(*person_events.borrow(), 0);
+        assert_eq!(*household_events.borrow(), vec![(Some(Age(4)), Age(5))]);
+    }
+
+    #[test]
+    fn entity_id_from_index() {
+        let mut context = Context::new();
+        assert!(context.entity_id_from_index(0).is_err());
+        let first = context.add_entity(Age(30)).unwrap();
+        let second = context.add_entity(Age(40)).unwrap();
+        assert_eq!(second.index(), 1);
+        assert_eq!(context.entity_id_from_index(first.index()).unwrap(), first);
+        assert_eq!(context.entity_id_from_index(second.index()).unwrap(), second);
+        assert!(context.entity_id_from_index(2).is_err());
+        context.remove_entity(first).unwrap();
+        assert!(context.entity_id_from_index(0).is_err());
+    }
+
+    #[test]
+    fn entity_exists() {
+        let mut context = Context::new();
+        assert!(!context.entity_exists(EntityId(0)));
+        let entity_id = context.add_entity(Age(30)).unwrap();
+        assert!(context.entity_exists(entity_id));
+        assert!(!context.entity_exists(EntityId(1)));
+        context.remove_entity(entity_id).unwrap();
+        assert!(!context.entity_exists(entity_id));
+    }
+
+    #[derive(Clone, PartialEq, Debug, Hash)]
+    struct Name(String);
+    impl Property for Name {}
+
+    #[test]
+    fn describe_entity() {
+        let mut context = Context::new();
+        let entity_id = context
+            .add_entity((Age(30), InfectionStatus::I, Name("Ada".to_string())))
+            .unwrap();
+        let other = context.add_entity(Age(4)).unwrap();
+
+        assert_eq!(
+            context.describe_entity(entity_id),
+            vec![
+                ("Age".to_string(), "Age(30)".to_string()),
+                ("InfectionStatus".to_string(), "I".to_string()),
+                (Name::name().to_string(), "Name(\"Ada\")".to_string()),
+            ]
+        );
+        // Properties the entity has no value for are left out.
+        assert_eq!(
+            context.describe_entity(other),
+            vec![("Age".to_string(), "Age(4)".to_string())]
+        );
+        assert!(context.describe_entity(EntityId(2)).is_empty());
+    }
+
+    #[test]
+    fn update_entity() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        let entity_id = context.add_entity((Age(30), InfectionStatus::S)).unwrap();
+        context.add_entity((Age(30), InfectionStatus::S)).unwrap();
+
+        let events = Rc::new(RefCell::new(0));
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, _: PropertyChangeEvent<Age>| {
+            *events_clone.borrow_mut() += 1;
+        });
+        let events_clone = events.clone();
+        context.subscribe_to_event(move |_, _: PropertyChangeEvent<InfectionStatus>| {
+            *events_clone.borrow_mut() += 1;
+        });
+        context
+            .update_entity(entity_id, (Age(31), InfectionStatus::I))
+            .unwrap();
+        context.execute();
+
+        assert_eq!(context.query_entities((Age(31), InfectionStatus::I)), vec![entity_id]);
+        assert_eq!(context.query_entity_count(InfectionStatus::S), 1);
+        assert_eq!(context.query_entity_count(Age(30)), 1);
+        assert_eq!(*events.borrow(), 2);
+        assert!(context.update_entity(EntityId(2), Age(1)).is_err());
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, serde::Deserialize)]
+    struct Height(u16);
+    impl Property for Height {
+        fn name() -> &'static str {
+            "Height"
+        }
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, serde::Deserialize)]
+    enum Sex {
+        Female,
+        Male,
+    }
+    impl Property for Sex {
+        fn name() -> &'static str {
+            "Sex"
+        }
+    }
+
+    #[test]
+    fn export_and_import_population_csv() {
+        let mut context = Context::new();
+        context.add_population_column::<Height>();
+        context.add_population_column::<Sex>();
+        context.add_entity((Height(150), Sex::Female)).unwrap();
+        context.add_entity((Height(180), Sex::Male)).unwrap();
+        let removed = context.add_entity((Height(90), Sex::Male)).unwrap();
+        context.remove_entity(removed).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("population.csv");
+        context.export_population_csv(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Height,Sex\n150,Female\n180,Male\n"
+        );
+
+        let mut imported = Context::new();
+        let entity_ids = imported.import_population_csv::<(Height, Sex)>(&path).unwrap();
+        let values: Vec<(Option<Height>, Option<Sex>)> = entity_ids
+            .into_iter()
+            .map(|entity_id| (imported.get_property(entity_id), imported.get_property(entity_id)))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (Some(Height(150)), Some(Sex::Female)),
+                (Some(Height(180)), Some(Sex::Male)),
+            ]
+        );
+    }
+
+    #[test]
+    fn export_population_csv_empty_cells() {
+        let mut context = Context::new();
+        context.add_population_column::<Height>();
+        context.add_population_column::<Sex>();
+        context.add_population_column::<Height>();
+        context.add_entity(Sex::Male).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("population.csv");
+        context.export_population_csv(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Height,Sex\n,Male\n");
+
+        let mut no_columns = Context::new();
+        assert!(no_columns.export_population_csv(&path).is_err());
+    }
+
+    #[test]
+    fn entity_kind_population_api() {
+        let mut context = Context::new();
+        context.add_entity(Height(180)).unwrap();
+        context.reserve_entities_of::<Household>(3);
+        let households = context.add_entities_of::<Household>(2).unwrap();
+        assert_eq!(context.entity_id_from_index_of::<Household>(1).unwrap(), households[1]);
+        assert!(context.entity_id_from_index_of::<Household>(2).is_err());
+
+        *context.get_property_mut_of::<Household, Sex>(households[0]) = Some(Sex::Female);
+        assert_eq!(context.get_property::<Sex>(EntityId(0)), None);
+        assert_eq!(
+            context.describe_entity_of::<Household>(households[0]),
+            vec![("Sex".to_string(), "Female".to_string())]
+        );
+
+        context.set_property_of::<Household, _>(households[1], Sex::Male).unwrap();
+        context.add_population_column_of::<Household, Sex>();
+        context.add_population_column::<Height>();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("households.csv");
+        context.export_population_csv_of::<Household>(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Sex\nFemale\nMale\n");
+
+        let imported = context.import_population_csv_of::<Household, (Sex,)>(&path).unwrap();
+        assert_eq!(context.get_property_of::<Household, Sex>(imported[1]), Some(Sex::Male));
+        let loaded = context
+            .load_entities_from_csv_reader_of::<Household, (Sex,), _>("Sex\nFemale\n".as_bytes())
+            .unwrap();
+        assert_eq!(context.get_property_of::<Household, Sex>(loaded[0]), Some(Sex::Female));
+        assert_eq!(context.get_entity_count_of::<Household>(), 5);
+        assert_eq!(context.get_entity_count(), 1);
+    }
+
+    #[test]
+    fn import_population_csv_malformed_row() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("census.csv");
+        std::fs::write(&path, "Height,Sex\n150,Female\n180,Male\ntall,Female\n160,Male\n")
+            .unwrap();
+
+        let mut context = Context::new();
+        let error = context
+            .import_population_csv::<(Height, Sex)>(&path)
+            .unwrap_err();
+        match &error {
+            IxaError::CsvRowError { path: Some(error_path), line: 4, error: inner } => {
+                assert_eq!(error_path, &path);
+                assert!(matches!(**inner, IxaError::CsvError(_)), "{inner:?}");
+            }
+            error => panic!("Unexpected error {error:?}"),
+        }
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(error.to_string().contains("line 4: CSV error: "), "{error}");
+        // The rows before the malformed one were imported.
+        assert_eq!(context.get_entity_count(), 2);
+        assert_eq!(context.query_entities(Sex::Male), vec![EntityId(1)]);
+
+        std::fs::write(&path, "Height,Sex\n150,Female\n180,Unknown\n").unwrap();
+        let error = Context::new()
+            .import_population_csv::<(Height, Sex)>(&path)
+            .unwrap_err();
+        assert!(matches!(&error, IxaError::CsvRowError { line: 3, .. }));
+
+        std::fs::write(&path, "Height,Sex\n150\n").unwrap();
+        let error = Context::new()
+            .import_population_csv::<(Height, Sex)>(&path)
+            .unwrap_err();
+        assert!(matches!(&error, IxaError::CsvRowError { line: 2, .. }));
+    }
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct BoundedAge(u8);
+    impl Property for BoundedAge {
+        fn validate(&self) -> Result<(), String> {
+            if self.0 > 150 {
+                return Err("must be at most 150".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn validate_property() {
+        let mut context = Context::new();
+        let error = context.add_entity((Alive(true), BoundedAge(200))).unwrap_err();
+        assert!(matches!(
+            &error,
+            IxaError::IxaError(message) if message.contains("BoundedAge(200)")
+                && message.ends_with("must be at most 150")
+        ));
+        assert_eq!(context.get_entity_count(), 0);
+        assert!(context.add_entities_with(3, BoundedAge(151)).is_err());
+
+        let entity_id = context.add_entity(BoundedAge(40)).unwrap();
+        assert!(context.set_property(entity_id, BoundedAge(151)).is_err());
+        assert_eq!(context.get_property::<BoundedAge>(entity_id), Some(BoundedAge(40)));
+        assert!(context.update_entity(entity_id, (Alive(false), BoundedAge(200))).is_err());
+        assert_eq!(context.get_property::<Alive>(entity_id), Some(Alive(true)));
+
+        context.set_property(entity_id, BoundedAge(150)).unwrap();
+        assert_eq!(context.get_property::<BoundedAge>(entity_id), Some(BoundedAge(150)));
+    }
+
+    #[test]
+    fn load_entities_from_csv_reader() {
+        let data = "Height,Sex\n150,Female\n180,Male\n165,Female\n";
+        let mut context = Context::new();
+        let entity_ids = context
+            .load_entities_from_csv_reader::<(Height, Sex), _>(data.as_bytes())
+            .unwrap();
+        assert_eq!(entity_ids, vec![EntityId(0), EntityId(1), EntityId(2)]);
+        assert_eq!(context.query_entity_count(Sex::Female), 2);
+        assert_eq!(context.get_property::<Height>(EntityId(1)), Some(Height(180)));
+
+        let data = "Height,Sex\n150,Female\nshort,Male\n";
+        let error = Context::new()
+            .load_entities_from_csv_reader::<(Height, Sex), _>(data.as_bytes())
+            .unwrap_err();
+        assert!(matches!(&error, IxaError::CsvRowError { path: None, line: 3, .. }));
+        assert!(error.to_string().starts_with("line 3: CSV error: "), "{error}");
     }
 }
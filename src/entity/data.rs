@@ -1,23 +1,109 @@
 use std::{
     any::TypeId,
     cell::RefCell,
-    collections::HashMap
+    collections::{BTreeSet, HashMap},
+    marker::PhantomData,
 };
 use crate::{
+    context::Context,
     New,
     EntityId,
+    type_of,
     error::IxaError,
-    entity::{Index, IndexMap, InitializationList},
+    entity::{DefaultEntity, EntityKind, Index, IndexMap, InitializationList},
     property::{Property, PropertyInfo},
     property_map::{PropertyMap, PropertyStore}
 };
 
-/// Stores all data associated to entities and their properties.
-pub struct EntityData {
+/// Looks up the value of a property for an entity and formats it with `Property::to_report_value`.
+pub(crate) type ReportValueFn = fn(&Context, EntityId) -> Option<String>;
+
+/// Looks up the value of a property for an entity and formats it with `Debug`.
+pub(crate) type DebugValueFn = fn(&Context, EntityId) -> Option<String>;
+
+/// Looks up the value of a property for an entity and serializes it for a CSV cell.
+pub(crate) type SerializeValueFn = fn(&Context, EntityId) -> Result<Option<String>, IxaError>;
+
+/// Grows the `PropertyStore` of one property type so that it can hold values for the given number
+/// of entities.
+pub(crate) type ReserveFn = fn(&mut PropertyMap, usize);
+
+/// Clears the value of one property type for an entity.
+pub(crate) type ClearFn = fn(&mut PropertyMap, EntityId);
+
+/// Adds an entity to, or removes it from, the index of one property type.
+pub(crate) type IndexUpdateFn = fn(&mut Context, EntityId);
+
+/// Type-erased `add_to_index_maybe` and `remove_from_index_maybe` for one property type.
+#[derive(Copy, Clone)]
+pub(crate) struct IndexUpdateFns {
+    pub(crate) add: IndexUpdateFn,
+    pub(crate) remove: IndexUpdateFn,
+}
+
+/// Clears the value of `T` for an entity, if it has one. A `ClearFn`.
+pub(crate) fn clear_value<T: Property>(properties_map: &mut PropertyMap, entity_id: EntityId) {
+    if let Some(value) = properties_map.get_container_mut::<T>().values.get_mut(entity_id.0) {
+        *value = None;
+    }
+}
+
+/// Returns the cached value of the memoized derived property `T` for the entity, or else
+/// computes it with `compute` and caches it. Values of `None` aren't cached. Called by the
+/// `Property::compute_for` of properties defined with `define_memoized_derived_property!`.
+pub fn memoize<K: EntityKind, T: Property>(
+    context: &Context,
+    entity_id: EntityId,
+    compute: impl FnOnce() -> Option<T>,
+) -> Option<T> {
+    let Some(entity_data) = context.get_data_container::<EntityData<K>>() else {
+        return compute();
+    };
+    let cached = entity_data
+        .derived_cache
+        .borrow()
+        .get_container_ref::<T>()
+        .and_then(|property_store| property_store.values.get(entity_id.0).cloned().flatten());
+    if cached.is_some() {
+        return cached;
+    }
+
+    // The cache isn't borrowed while computing, as the dependencies may be memoized, too.
+    let value = compute()?;
+    let mut derived_cache = entity_data.derived_cache.borrow_mut();
+    let property_store = derived_cache.get_container_mut::<T>();
+    if entity_id.0 >= property_store.len() {
+        property_store.values.resize_with(entity_id.0 + 1, || None);
+    }
+    property_store.values[entity_id.0] = Some(value.clone());
+    Some(value)
+}
+
+/// Called when a global property the derived property `T` depends on changes, which may change
+/// the value of `T` for every entity of kind `K`. Clears the cached values of `T`, if it is
+/// memoized, and empties its index, if it has one, so that it is rebuilt on the next query.
+pub fn global_dependency_changed<K: EntityKind, T: Property>(context: &mut Context) {
+    let entity_data = context.get_data_container_mut::<EntityData<K>>();
+    if T::is_memoized() {
+        entity_data
+            .derived_cache
+            .get_mut()
+            .get_container_mut::<T>()
+            .values
+            .clear();
+    }
+    entity_data.get_index_mut::<T>().reset();
+}
+
+/// Stores all data associated to the entities of kind `K` and their properties.
+pub struct EntityData<K: EntityKind = DefaultEntity> {
     /// FLag to prevent `set_property` event from being generated upon new entity creation.
     pub(super) is_initializing: bool,
-    /// How many entities exist.
+    /// How many entity ids have been handed out, including those of removed entities.
     pub(crate) entity_count: usize,
+    /// The ids of entities that have been removed with `remove_entity` and not yet reused.
+    /// `add_entity` takes the lowest of them before handing out a new id.
+    pub(crate) removed_entities: BTreeSet<EntityId>,
     /// Map from type `T: Property` to `PropertyStore`, a wrapper for `Vec<Option<T>>`
     pub(crate) properties_map: PropertyMap,
     /// Records which types have been registered with all of their dependencies in `dependency_map`
@@ -25,41 +111,171 @@ pub struct EntityData {
     /// Maps dependencies to types that depend on them
     pub(crate) dependency_map: HashMap<TypeId, Vec<TypeId>>,
     /// This is actually a `HashMap<TypeId, IndexCore<T: Property>`
-    pub(crate) property_indexes: RefCell<IndexMap>,
+    pub(crate) property_indexes: RefCell<IndexMap<K>>,
     /// A database of basic information about registered properties:
     ///     `PropertyInfo(Name, TypeId, IsRequired, IsDerived)`
     pub(crate) property_metadata: Vec<PropertyInfo>,
+    /// For each property whose changes are tracked, the simulation time each entity's value
+    /// was last set, indexed by entity id.
+    pub(crate) property_change_times: HashMap<TypeId, Vec<Option<f64>>>,
+    /// Maps the name of each registered property to a function that formats its value for a
+    /// report.
+    pub(crate) report_value_fns: HashMap<String, ReportValueFn>,
+    /// The `DebugValueFn` of each registered property.
+    pub(crate) debug_value_fns: HashMap<TypeId, DebugValueFn>,
+    /// The name and `SerializeValueFn` of each property added with `add_population_column`, in
+    /// the order the columns were added.
+    pub(crate) population_columns: Vec<(&'static str, SerializeValueFn)>,
+    /// The `ReserveFn` of each registered nonderived property.
+    pub(crate) reserve_fns: Vec<ReserveFn>,
+    /// The number of entities space was reserved for with `reserve`. Property stores that grow
+    /// later, including ones created after the call, grow straight to this size.
+    pub(crate) reserved_entities: usize,
+    /// The `ClearFn` of every property type that has a `PropertyStore`.
+    pub(crate) clear_fns: HashMap<TypeId, ClearFn>,
+    /// The cached values of memoized derived properties, which are cleared for an entity when
+    /// one of the property's dependencies is set.
+    pub(crate) derived_cache: RefCell<PropertyMap>,
+    /// The `ClearFn` of the cache of each registered memoized derived property.
+    pub(crate) cache_clear_fns: HashMap<TypeId, ClearFn>,
+    /// The `IndexUpdateFns` of each registered property.
+    pub(crate) index_update_fns: HashMap<TypeId, IndexUpdateFns>,
+    kind: PhantomData<K>,
 }
 
-impl Default for EntityData {
+impl<K: EntityKind> Default for EntityData<K> {
     fn default() -> Self {
         EntityData {
             is_initializing: false,
             entity_count: 0,
+            removed_entities: BTreeSet::new(),
             properties_map: PropertyMap::new(),
             registered_derived_properties: vec![],
             dependency_map: HashMap::new(),
             property_indexes: RefCell::new(IndexMap::default()),
             property_metadata: vec![],
+            property_change_times: HashMap::new(),
+            report_value_fns: HashMap::new(),
+            debug_value_fns: HashMap::new(),
+            population_columns: vec![],
+            reserve_fns: vec![],
+            reserved_entities: 0,
+            clear_fns: HashMap::new(),
+            derived_cache: RefCell::new(PropertyMap::new()),
+            cache_clear_fns: HashMap::new(),
+            index_update_fns: HashMap::new(),
+            kind: PhantomData,
         }
     }
 }
 
-impl New for EntityData {
+impl<K: EntityKind> New for EntityData<K> {
     const new: &'static dyn Fn() -> Self = &EntityData::default;
 }
 
-impl EntityData {
+impl<K: EntityKind> EntityData<K> {
     pub fn create_entities(&mut self, size: usize) {
         self.entity_count = size;
     }
 
     pub fn add_entity(&mut self) -> EntityId {
+        if let Some(entity_id) = self.removed_entities.pop_first() {
+            return entity_id;
+        }
         let entity_id = EntityId(self.entity_count);
         self.entity_count += 1;
         entity_id
     }
 
+    /// Adds `n` entities, first reusing the ids of removed entities, lowest first, like
+    /// `add_entity()`, and then handing out consecutive ids following the highest id handed out
+    /// so far. The ids are returned in ascending order but may not be contiguous.
+    pub fn add_entities(&mut self, n: usize) -> Vec<EntityId> {
+        let reused = n.min(self.removed_entities.len());
+        let mut entity_ids: Vec<EntityId> = Vec::with_capacity(n);
+        entity_ids.extend(std::iter::from_fn(|| self.removed_entities.pop_first()).take(reused));
+        let first = self.entity_count;
+        self.entity_count += n - reused;
+        entity_ids.extend((first..self.entity_count).map(EntityId));
+        entity_ids
+    }
+
+    /// The number of entities that exist, not counting removed entities.
+    pub fn live_entity_count(&self) -> usize {
+        self.entity_count - self.removed_entities.len()
+    }
+
+    /// Whether `entity_id` has been handed out and not removed.
+    pub fn entity_exists(&self, entity_id: EntityId) -> bool {
+        entity_id.0 < self.entity_count && !self.removed_entities.contains(&entity_id)
+    }
+
+    /// Returns the `IndexUpdateFns` of every derived property that depends on `type_id` and is
+    /// indexed.
+    pub(crate) fn dependent_index_update_fns(&self, type_id: TypeId) -> Vec<IndexUpdateFns> {
+        let Some(dependents) = self.dependency_map.get(&type_id) else {
+            return Vec::new();
+        };
+        let property_indexes = self.property_indexes.borrow();
+        dependents
+            .iter()
+            .filter(|dependent| property_indexes.is_indexed(dependent))
+            .filter_map(|dependent| self.index_update_fns.get(dependent).copied())
+            .collect()
+    }
+
+    /// Clears every property value of the entity and marks it as removed. The entity must
+    /// already have been removed from the indexes.
+    pub(super) fn remove_entity(&mut self, entity_id: EntityId) -> Result<(), IxaError> {
+        if !self.entity_exists(entity_id) {
+            return Err(IxaError::IxaError(format!("{entity_id:?} does not exist")));
+        }
+        for clear_fn in self.clear_fns.values() {
+            clear_fn(&mut self.properties_map, entity_id);
+        }
+        let derived_cache = self.derived_cache.get_mut();
+        for cache_clear_fn in self.cache_clear_fns.values() {
+            cache_clear_fn(derived_cache, entity_id);
+        }
+        // The id may be reused, so the new entity mustn't inherit the change times.
+        for change_times in self.property_change_times.values_mut() {
+            if let Some(change_time) = change_times.get_mut(entity_id.0) {
+                *change_time = None;
+            }
+        }
+        self.removed_entities.insert(entity_id);
+        Ok(())
+    }
+
+    /// Removes every entity along with its property values and change times, and empties the
+    /// indexes. Registered properties and indexes are kept.
+    pub(crate) fn clear_entities(&mut self) {
+        let derived_cache = self.derived_cache.get_mut();
+        for entity_id in (0..self.entity_count).map(EntityId) {
+            for clear_fn in self.clear_fns.values() {
+                clear_fn(&mut self.properties_map, entity_id);
+            }
+            for cache_clear_fn in self.cache_clear_fns.values() {
+                cache_clear_fn(derived_cache, entity_id);
+            }
+        }
+        for change_times in self.property_change_times.values_mut() {
+            change_times.clear();
+        }
+        self.property_indexes.get_mut().reset_all();
+        self.entity_count = 0;
+        self.removed_entities.clear();
+    }
+
+    /// Reserves space in every property store for at least `additional` more entities.
+    pub fn reserve(&mut self, additional: usize) {
+        let total = self.entity_count + additional;
+        self.reserved_entities = self.reserved_entities.max(total);
+        for reserve_fn in &self.reserve_fns {
+            reserve_fn(&mut self.properties_map, total);
+        }
+    }
+
     pub fn get_property_ref<T: Property>(&self, entity_id: EntityId) -> Option<&T> {
         
         let idx = entity_id.0;
@@ -74,12 +290,31 @@ impl EntityData {
         }
     }
 
+    /// Borrows the default value of `T`, or returns `None` if it has no default or no store.
+    pub(crate) fn get_default_ref<T: Property>(&self) -> Option<&T> {
+        self.properties_map.get_container_ref::<T>()?.default.as_ref()
+    }
+
     pub fn get_property_mut<T: Property>(&mut self, entity_id: EntityId) -> &mut Option<T> {
         assert!(!T::is_derived(), "Cannot set a derived property: {}", T::name());
+        // The value may be about to change, so cached values computed from it are stale.
+        if let Some(dependents) = self.dependency_map.get(&type_of::<T>()) {
+            let derived_cache = self.derived_cache.get_mut();
+            for dependent in dependents {
+                if let Some(cache_clear_fn) = self.cache_clear_fns.get(dependent) {
+                    cache_clear_fn(derived_cache, entity_id);
+                }
+            }
+        }
         let idx = entity_id.0;
+        let reserved_entities = self.reserved_entities;
         let property_values: &mut PropertyStore<T> = self.properties_map.get_container_mut();
 
         if idx >= property_values.len() {
+            if property_values.len() == 0 {
+                self.clear_fns.entry(type_of::<T>()).or_insert(clear_value::<T>);
+            }
+            property_values.reserve(reserved_entities.saturating_sub(property_values.len()));
             property_values.values.resize_with(idx + 1, || None);
         }
 
@@ -92,59 +327,57 @@ impl EntityData {
         *property = Some(value);
     }
 
-    pub(crate) fn get_index_mut<T: Property>(&mut self) -> &mut Index<T> {
+    pub(crate) fn get_index_mut<T: Property>(&mut self) -> &mut Index<T, K> {
         self.property_indexes
             .get_mut()
             .get_container_mut::<T>()
     }
 
-    pub(crate) fn get_index_ref<T: Property>(&mut self) -> Option<&Index<T>> {
+    pub(crate) fn get_index_ref<T: Property>(&mut self) -> Option<&Index<T, K>> {
         self.property_indexes
             .get_mut()
             .get_container_ref::<T>()
     }
 
-    pub(super) fn check_initialization_list<T: InitializationList>(&self, initialization: &T)
+    pub(super) fn check_initialization_list<T: InitializationList>(&self) -> Result<(), IxaError> {
+        let mut properties = Vec::new();
+        T::collect_properties(&mut properties);
+        self.check_properties(&properties)
+    }
+
+    /// Checks that `properties`, the `TypeId` and name of each property a new entity is given,
+    /// has no repeats and includes every required property.
+    pub(super) fn check_properties(&self, properties: &[(TypeId, &'static str)])
         -> Result<(), IxaError>
     {
+        for (idx, (type_id, name)) in properties.iter().enumerate() {
+            if properties[..idx].iter().any(|(earlier, _)| earlier == type_id) {
+                return Err(IxaError::IxaError(format!(
+                    "Duplicate property {name} in initialization list"
+                )));
+            }
+        }
+
         for property_info in self.property_metadata.iter() {
-            if property_info.is_required() && !initialization.has_property(property_info.type_id()) {
-                return Err(IxaError::IxaError(format!("Missing initial value {}", property_info.name())));
+            // Derived properties are computed, so they are never in an initialization list.
+            if property_info.is_required()
+                && !property_info.is_derived()
+                && !properties.iter().any(|(type_id, _)| *type_id == property_info.type_id())
+            {
+                return Err(IxaError::MissingRequiredProperty(property_info.name().to_string()));
             }
         }
 
         Ok(())
     }
 
-    /// Convenience function to iterate over the current set of entities.
-    /// Note that this doesn't hold a reference to EntityData, so if
-    /// you change the entity count while using it, it won't notice.
-    pub(super) fn entity_iterator(&self) -> Box<dyn Iterator<Item =EntityId>> {
-        pub(super) struct EntityIterator {
-            entity_count: usize,
-            entity_id: usize,
-        }
-
-        impl Iterator for EntityIterator {
-            type Item = EntityId;
-
-            fn next(&mut self) -> Option<Self::Item> {
-                let ret = if self.entity_id < self.entity_count {
-                    Some(EntityId(self.entity_id))
-                } else {
-                    None
-                };
-                self.entity_id += 1;
-
-                ret
-            }
-        }
-
+    /// Convenience function to iterate over the current set of entities, skipping removed
+    /// entities.
+    pub(super) fn entity_iterator(&self) -> Box<dyn Iterator<Item =EntityId> + '_> {
         Box::new(
-            EntityIterator {
-                entity_count: self.entity_count,
-                entity_id: 0,
-            }
+            (0..self.entity_count)
+                .map(EntityId)
+                .filter(|entity_id| !self.removed_entities.contains(entity_id))
         )
     }
 
@@ -156,6 +389,7 @@ mod tests {
     use crate::context::Context;
     use crate::entity::ContextEntityExt;
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[derive(Clone, Eq, PartialEq, Debug, Hash)]
     struct Age(u8);
@@ -181,4 +415,181 @@ mod tests {
         context.add_entity((Age(10), Name("John Smith".to_string()), InfectionStatus::I))
                .expect("Failed to add person");
     }
+
+    #[derive(Clone, Eq, PartialEq, Debug, Hash)]
+    struct RiskGroup(u8);
+    crate::define_required_property!(RiskGroup);
+
+    #[test]
+    fn missing_required_property() {
+        let mut context = Context::new();
+        RiskGroup::register(&mut context);
+
+        let result = context.add_entity(Age(10));
+        assert!(matches!(&result, Err(IxaError::MissingRequiredProperty(name))
+            if name == "RiskGroup"));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Missing initial value for required property RiskGroup"
+        );
+        assert_eq!(context.get_entity_count(), 0);
+
+        context.add_entity((Age(10), RiskGroup(2))).unwrap();
+        assert_eq!(context.get_entity_count(), 1);
+    }
+
+    #[test]
+    fn duplicate_property_in_initialization_list() {
+        let mut context = Context::new();
+        let result = context.add_entity((Age(1), RiskGroup(1), Age(2)));
+        assert!(matches!(result, Err(IxaError::IxaError(message))
+            if message.contains("Duplicate property") && message.contains("Age")));
+        assert_eq!(context.get_entity_count(), 0);
+
+        let entity_id = context.add_entity((Age(1), RiskGroup(1))).unwrap();
+        assert_eq!(context.get_property::<Age>(entity_id), Some(Age(1)));
+    }
+
+    #[test]
+    fn missing_required_property_empty_initialization() {
+        let mut context = Context::new();
+        RiskGroup::register(&mut context);
+        assert!(RiskGroup::is_required());
+
+        assert!(context.add_entity(()).is_err());
+        assert!(context.add_entities(3).is_err());
+        assert_eq!(context.get_entity_count(), 0);
+        let entity_ids = context.add_entities_with(2, RiskGroup(1)).unwrap();
+        assert_eq!(context.get_property::<RiskGroup>(entity_ids[1]), Some(RiskGroup(1)));
+    }
+
+    // `define_derived_property!` can't make a property required, so `Property` is implemented
+    // by hand.
+    #[derive(Clone, Eq, PartialEq, Debug, Hash)]
+    struct IsAdult(bool);
+    impl Property for IsAdult {
+        fn is_derived() -> bool {
+            true
+        }
+
+        fn is_required() -> bool {
+            true
+        }
+
+        fn collect_dependencies(dependencies: &mut Vec<TypeId>) {
+            Age::collect_dependencies(dependencies);
+        }
+
+        fn property_info() -> PropertyInfo {
+            PropertyInfo(Self::name().to_string(), type_of::<Self>(), true, true)
+        }
+
+        fn compute_for<K: EntityKind>(context: &Context, entity_id: EntityId) -> Option<Self> {
+            Age::compute_for::<K>(context, entity_id).map(|age| IsAdult(age.0 >= 18))
+        }
+    }
+
+    #[test]
+    fn required_derived_property() {
+        let mut context = Context::new();
+        IsAdult::register(&mut context);
+
+        let entity_id = context.add_entity(Age(30)).unwrap();
+        assert_eq!(context.get_property::<IsAdult>(entity_id), Some(IsAdult(true)));
+    }
+
+    static SENIOR_COMPUTATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct Senior(bool);
+    crate::define_memoized_derived_property!(Senior, [Age], |age| {
+        SENIOR_COMPUTATIONS.fetch_add(1, Ordering::Relaxed);
+        let Age(years) = age;
+        Some(Senior(years >= 65))
+    });
+
+    #[test]
+    fn memoized_derived_property() {
+        let computations = || SENIOR_COMPUTATIONS.load(Ordering::Relaxed);
+        let mut context = Context::new();
+        let entity_id = context.add_entity((Age(64), Name("Ada".to_string()))).unwrap();
+
+        assert_eq!(context.get_property::<Senior>(entity_id), Some(Senior(false)));
+        assert_eq!(context.get_property::<Senior>(entity_id), Some(Senior(false)));
+        assert_eq!(context.query_entities(Senior(false)), vec![entity_id]);
+        assert_eq!(computations(), 1);
+
+        // Setting a property the derived property doesn't depend on keeps the cached value.
+        context.set_property(entity_id, Name("Grace".to_string())).unwrap();
+        assert_eq!(context.get_property::<Senior>(entity_id), Some(Senior(false)));
+        assert_eq!(computations(), 1);
+
+        context.set_property(entity_id, Age(65)).unwrap();
+        assert_eq!(context.get_property::<Senior>(entity_id), Some(Senior(true)));
+        assert_eq!(context.get_property::<Senior>(entity_id), Some(Senior(true)));
+        assert_eq!(computations(), 2);
+
+        // A new entity that reuses the id doesn't get the removed entity's value.
+        context.remove_entity(entity_id).unwrap();
+        let reused = context.add_entity(Age(10)).unwrap();
+        assert_eq!(reused, entity_id);
+        assert_eq!(context.get_property::<Senior>(reused), Some(Senior(false)));
+        assert_eq!(computations(), 3);
+    }
+
+    #[test]
+    fn reserve() {
+        const POPULATION: usize = 1000;
+        let mut context = Context::new();
+        // `Age` is registered before reserving, `InfectionStatus` only gets a store on first use.
+        context.get_property::<Age>(EntityId(0));
+        context.reserve_entities(POPULATION);
+
+        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let ages = entity_data.properties_map.get_container_ref::<Age>().unwrap();
+        assert!(ages.values.capacity() >= POPULATION);
+
+        let mut entities = Vec::new();
+        for i in 0..POPULATION {
+            let status = if i % 2 == 0 { InfectionStatus::S } else { InfectionStatus::I };
+            entities.push(context.add_entity((Age((i % 100) as u8), status)).unwrap());
+        }
+
+        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let statuses = entity_data
+            .properties_map
+            .get_container_ref::<InfectionStatus>()
+            .unwrap();
+        assert_eq!(statuses.values.capacity(), POPULATION);
+        assert_eq!(context.get_property::<Age>(entities[321]), Some(Age(21)));
+        assert_eq!(
+            context.get_property::<InfectionStatus>(entities[321]),
+            Some(InfectionStatus::I)
+        );
+        assert_eq!(context.query_entity_count(InfectionStatus::S), POPULATION / 2);
+    }
+
+    #[test]
+    fn register_property_sizes_store_to_population() {
+        const POPULATION: usize = 10_000;
+        let mut context = Context::new();
+        let entities: Vec<EntityId> = (0..POPULATION)
+            .map(|i| context.add_entity(Age((i % 100) as u8)).unwrap())
+            .collect();
+
+        Name::register(&mut context);
+        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let names = entity_data.properties_map.get_container_ref::<Name>().unwrap();
+        let capacity = names.values.capacity();
+        assert!(capacity >= POPULATION);
+
+        // Back-filling the property doesn't grow the store any further.
+        for &entity_id in entities.iter().rev() {
+            context.set_property(entity_id, Name(format!("{entity_id:?}"))).unwrap();
+        }
+        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let names = entity_data.properties_map.get_container_ref::<Name>().unwrap();
+        assert_eq!(names.values.capacity(), capacity);
+        assert_eq!(names.len(), POPULATION);
+    }
 }
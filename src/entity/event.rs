@@ -0,0 +1,76 @@
+use crate::{
+    entity::{DefaultEntity, EntityKind},
+    property::Property,
+    EntityId,
+};
+use std::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+/// Emitted by [`ContextEntityExt::set_property()`](crate::ContextEntityExt::set_property) whenever
+/// the value of property `T` is set on an existing entity. Setting the initial properties of a new
+/// entity with `add_entity` does not emit this event; it emits an `EntityCreatedEvent` instead.
+/// Changes to entities of a kind other than `DefaultEntity` emit a `PropertyChangeEvent<T, K>`, so
+/// handlers only see the kind they subscribed to.
+pub struct PropertyChangeEvent<T: Property, K: EntityKind = DefaultEntity> {
+    /// The entity whose property was set
+    pub entity_id: EntityId,
+    /// The new value of the property
+    pub current: T,
+    /// The value of the property before it was set, if it had one
+    pub previous: Option<T>,
+    pub(crate) kind: PhantomData<K>,
+}
+
+// Implemented by hand, as deriving would require `K: Clone`.
+impl<T: Property, K: EntityKind> Clone for PropertyChangeEvent<T, K> {
+    fn clone(&self) -> Self {
+        PropertyChangeEvent {
+            entity_id: self.entity_id,
+            current: self.current.clone(),
+            previous: self.previous.clone(),
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<T: Property, K: EntityKind> Debug for PropertyChangeEvent<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PropertyChangeEvent")
+            .field("entity_id", &self.entity_id)
+            .field("current", &self.current)
+            .field("previous", &self.previous)
+            .finish()
+    }
+}
+
+/// Emitted by [`ContextEntityExt::add_entity()`](crate::ContextEntityExt::add_entity) once a new
+/// entity's initial properties have been set, so handlers can read them. Entities of a kind other
+/// than `DefaultEntity` emit an `EntityCreatedEvent<K>`.
+pub struct EntityCreatedEvent<K: EntityKind = DefaultEntity> {
+    /// The new entity
+    pub entity_id: EntityId,
+    pub(crate) kind: PhantomData<K>,
+}
+
+impl<K: EntityKind> EntityCreatedEvent<K> {
+    pub(crate) fn new(entity_id: EntityId) -> Self {
+        EntityCreatedEvent { entity_id, kind: PhantomData }
+    }
+}
+
+// Implemented by hand, as deriving would require `K: Clone`.
+impl<K: EntityKind> Clone for EntityCreatedEvent<K> {
+    fn clone(&self) -> Self {
+        EntityCreatedEvent::new(self.entity_id)
+    }
+}
+
+impl<K: EntityKind> Debug for EntityCreatedEvent<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EntityCreatedEvent")
+            .field("entity_id", &self.entity_id)
+            .finish()
+    }
+}
@@ -1,26 +1,31 @@
-// ToDo: Make this module generic over entity instead of specific to `PeopleId`
-
 use crate::{
     context::Context,
-    entity::ContextEntityExt,
+    entity::{DefaultEntity, EntityData, EntityKind},
+    hashing::hash_str,
     property::Property,
     type_of,
     EntityId,
     TypeId,
-    HashMap, 
+    HashMap,
+    HashMapExt,
     HashSet
 };
+use serde::{Deserialize, Serialize};
 use std::{
     any::Any,
+    cmp::Ordering,
     hash::{Hash, Hasher},
     marker::PhantomData,
 };
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 // The lookup key for entries in the index. This is a serialized
 // version of the value. If that serialization fits in 128 bits, we
 // store it in Fixed to avoid the allocation of the Vec. Otherwise, it
-// goes in Variable.
+// goes in Variable. The serialization starts with a hash of the
+// property's name, so that values of different properties with the
+// same serialization are distinct, and the key is the same in every
+// build.
 #[doc(hidden)]
 pub enum IndexValue {
     Fixed(u128),
@@ -28,8 +33,8 @@ pub enum IndexValue {
 }
 
 impl IndexValue {
-    pub fn new<T: Hash>(val: &T) -> IndexValue {
-        let mut hasher = IndexValueHasher::new();
+    pub fn new<T: Property>(val: &T) -> IndexValue {
+        let mut hasher = IndexValueHasher::new(hash_str(T::name()));
         val.hash(&mut hasher);
         if hasher.buf.len() <= 16 {
             let mut tmp: [u8; 16] = [0; 16];
@@ -40,6 +45,74 @@ impl IndexValue {
     }
 }
 
+/// An `f64` that implements `Eq`, `Ord`, and `Hash`, so that a property wrapping a float can be
+/// indexed and queried. `f64` itself doesn't implement `Hash`, and `NaN != NaN` would leave an
+/// entity with a `NaN` value unreachable in an index. Values are canonicalized instead: every
+/// `NaN` is equal to every other `NaN`, and `-0.0` is equal to `0.0`. Values are ordered by
+/// `f64::total_cmp`, with `NaN` greater than every other value.
+///
+/// ```
+/// # use ixa_core::OrderedF64;
+/// #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// struct Weight(OrderedF64);
+///
+/// assert_eq!(Weight(OrderedF64::new(-0.0)), Weight(OrderedF64::new(0.0)));
+/// assert_eq!(OrderedF64::new(f64::NAN), OrderedF64::new(-f64::NAN));
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct OrderedF64(f64);
+
+impl OrderedF64 {
+    #[must_use]
+    pub fn new(value: f64) -> Self {
+        if value.is_nan() {
+            OrderedF64(f64::NAN)
+        } else if value == 0.0 {
+            // Replaces `-0.0` with `0.0`.
+            OrderedF64(0.0)
+        } else {
+            OrderedF64(value)
+        }
+    }
+
+    #[must_use]
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for OrderedF64 {
+    fn from(value: f64) -> Self {
+        OrderedF64::new(value)
+    }
+}
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl Hash for OrderedF64 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.to_bits());
+    }
+}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
 // Implementation of the Hasher interface for IndexValue, used
 // for serialization. We're actually abusing this interface
 // because you can't call finish().
@@ -48,11 +121,23 @@ struct IndexValueHasher {
 }
 
 impl IndexValueHasher {
-    fn new() -> Self {
-        IndexValueHasher { buf: Vec::new() }
+    /// Creates a hasher whose output starts with `seed`.
+    fn new(seed: u64) -> Self {
+        IndexValueHasher { buf: seed.to_le_bytes().to_vec() }
     }
 }
 
+// Implements `Hasher` methods that write an integer as little-endian bytes.
+macro_rules! write_le_bytes {
+    ($($method:ident: $integer:ty),*) => {
+        $(
+            fn $method(&mut self, i: $integer) {
+                self.write(&i.to_le_bytes());
+            }
+        )*
+    };
+}
+
 impl Hasher for IndexValueHasher {
     fn finish(&self) -> u64 {
         panic!("Unimplemented")
@@ -61,10 +146,26 @@ impl Hasher for IndexValueHasher {
     fn write(&mut self, bytes: &[u8]) {
         self.buf.extend_from_slice(bytes);
     }
+
+    // The default methods write integers in the host's byte order. These write them in
+    // little-endian order, and `usize` and `isize` as 64-bit integers, so that the encoding is the
+    // same on every host.
+    write_le_bytes!(
+        write_u16: u16, write_u32: u32, write_u64: u64, write_u128: u128,
+        write_i16: i16, write_i32: i32, write_i64: i64, write_i128: i128
+    );
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
 }
 
-// An index for a single property.
-pub(crate) struct Index<T: Property> {
+// An index for a single property of the entities of kind `K`.
+pub(crate) struct Index<T: Property, K: EntityKind = DefaultEntity> {
     // The hash of the property value maps to a list of EntityIds or None if we're not indexing.
     pub(super) lookup: Option<HashMap<IndexValue, HashSet<EntityId>>>,
 
@@ -72,52 +173,37 @@ pub(crate) struct Index<T: Property> {
     // entity is added.
     pub(super) max_indexed: usize,
 
-    phantom: PhantomData<T>,
+    phantom: PhantomData<(T, K)>,
 }
 
-impl<T: Property> Index<T> {
+impl<T: Property, K: EntityKind> Index<T, K> {
     pub(super) fn new() -> Self {
         Self {
             lookup: None,
             max_indexed: 0,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         }
     }
 
     /// Looks up the value of the `T` property for `entity_id` and adds `entity_id` to the index
-    /// set for that `value`.
+    /// set for that `value`. Entities without a value are not indexed, as `set_property()` indexes
+    /// them once they get one.
     pub(crate) fn add_entity(&mut self, context: &Context, entity_id: EntityId) {
-        let value = T::compute(context, entity_id);
-        let value = value.unwrap_or_else(|| {
-            // ToDo: This is what Ixa does, but it seems like we'd want to be able to query for people who do not have
-            //       a value for a property. Have `None` hash to 0 or something.
-            panic!(
-                "{:?} has no {} value to index",
-                entity_id,
-                T::name()
-            );
-        });
+        let Some(value) = T::compute_for::<K>(context, entity_id) else {
+            return;
+        };
 
         let index_value = IndexValue::new(&value);
         self.insert((entity_id, index_value));
     }
 
-    /// Looks up the value of the `T` property for `entity_id` and removes `entity_id` from the
-    /// index set for that `value`.
-    fn remove_entity(&mut self, context: &mut Context, entity_id: EntityId) {
-        let value = context.get_property::<T>(entity_id);
-        // ToDo: If we index `None` values, we'd have to remove for None, too
-        if let Some(value) = value {
-            // ToDo: There is a lot of unwrapping here. What if values don't exist?
-            let index_value = IndexValue::new(&value);
-            let map: &mut HashMap<IndexValue, HashSet<EntityId>> = self.lookup.as_mut().unwrap();
-            let set: &mut HashSet<EntityId> = map.get_mut(&index_value).unwrap();
-
-            set.remove(&entity_id);
-            // Clean up the entry if there are no people
-            if set.is_empty() {
-                map.remove(&index_value);
-            }
+    /// Adds a newly created entity to the index if every earlier entity has already been indexed,
+    /// so that `max_indexed` stays in sync. Otherwise the entity is indexed with the others on the
+    /// next refresh.
+    pub(crate) fn add_new_entity(&mut self, context: &Context, entity_id: EntityId) {
+        if self.lookup.is_some() && self.max_indexed == entity_id.0 {
+            self.add_entity(context, entity_id);
+            self.max_indexed += 1;
         }
     }
 
@@ -125,14 +211,27 @@ impl<T: Property> Index<T> {
         if self.lookup.is_none() {
             return;
         }
-        let current_pop = context.get_entity_count();
+        // Will not panic, as an index can only exist in an `EntityData`.
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+        let current_pop = entity_data.entity_count;
         for id in self.max_indexed..current_pop {
             let entity_id = EntityId(id);
-            self.add_entity(context, entity_id);
+            if !entity_data.removed_entities.contains(&entity_id) {
+                self.add_entity(context, entity_id);
+            }
         }
         self.max_indexed = current_pop;
     }
 
+    /// Empties the index, if the property is indexed, so that every entity is indexed again on
+    /// the next refresh.
+    pub(crate) fn reset(&mut self) {
+        if let Some(lookup) = &mut self.lookup {
+            lookup.clear();
+            self.max_indexed = 0;
+        }
+    }
+
     /// Inserts the `entity_id` into the index set for the given index value.
     pub(crate) fn insert(&mut self, (entity_id, index_value): (EntityId, IndexValue)) {
         // ToDo: Can `self.lookup` ever be `None` here?
@@ -140,12 +239,76 @@ impl<T: Property> Index<T> {
             .as_mut()
             .unwrap()
             .entry(index_value)
-            .or_insert_with(HashSet::default)
+            .or_default()
             .insert(entity_id);
     }
 }
 
 
+/// Lets `IndexMap` work with an index without knowing the type of its property.
+pub(crate) trait AnyIndex: Any {
+    fn property_name(&self) -> &'static str;
+    /// Indexes any entities added since the index was last refreshed.
+    fn refresh(&mut self, context: &Context);
+    /// Returns the number of entities with each value, or `None` if the property isn't indexed.
+    fn value_counts(&self) -> Option<HashMap<IndexValue, usize>>;
+    /// Returns one entity from each nonempty bucket along with the size of the bucket, or `None`
+    /// if the property isn't indexed. Since every entity in a bucket has the same value, the
+    /// entity can be used to look the value up.
+    fn bucket_sizes(&self) -> Option<Vec<(EntityId, usize)>>;
+    /// Whether the property is indexed, as opposed to merely registered.
+    fn is_indexed(&self) -> bool;
+    /// Adds an entity whose id was reused to the index if the index has already passed the id,
+    /// as the next refresh only indexes ids from `max_indexed` on.
+    fn add_reused_entity(&mut self, context: &Context, entity_id: EntityId);
+    /// Empties the index, if the property is indexed, so that it is rebuilt on the next refresh.
+    fn reset(&mut self);
+}
+
+impl<T: Property, K: EntityKind> AnyIndex for Index<T, K> {
+    fn property_name(&self) -> &'static str {
+        T::name()
+    }
+
+    fn refresh(&mut self, context: &Context) {
+        self.index_unindexed_entities(context);
+    }
+
+    fn is_indexed(&self) -> bool {
+        self.lookup.is_some()
+    }
+
+    fn bucket_sizes(&self) -> Option<Vec<(EntityId, usize)>> {
+        let lookup = self.lookup.as_ref()?;
+        Some(
+            lookup
+                .values()
+                .filter_map(|entities| Some((*entities.iter().next()?, entities.len())))
+                .collect(),
+        )
+    }
+
+    fn add_reused_entity(&mut self, context: &Context, entity_id: EntityId) {
+        if self.lookup.is_some() && entity_id.0 < self.max_indexed {
+            self.add_entity(context, entity_id);
+        }
+    }
+
+    fn reset(&mut self) {
+        Index::reset(self);
+    }
+
+    fn value_counts(&self) -> Option<HashMap<IndexValue, usize>> {
+        let lookup = self.lookup.as_ref()?;
+        Some(
+            lookup
+                .iter()
+                .map(|(value, entities)| (value.clone(), entities.len()))
+                .collect(),
+        )
+    }
+}
+
 // We don't use the `define_any_map_container!` macro, because the insert method inserts a
 // `(EntityId, IndexValue)`, not a `T: Property`.
 // define_any_map_container!(
@@ -155,67 +318,126 @@ impl<T: Property> Index<T> {
 //     Index::<T>::insert
 // );
 
-pub struct IndexMap {
-    map: HashMap<TypeId, Box<dyn Any>>,
+/// The indexes of the properties of the entities of kind `K`.
+pub struct IndexMap<K: EntityKind = DefaultEntity> {
+    map: HashMap<TypeId, Box<dyn AnyIndex>>,
+    kind: PhantomData<K>,
 }
 
-impl Default for IndexMap{
+impl<K: EntityKind> Default for IndexMap<K> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl IndexMap {
+impl<K: EntityKind> IndexMap<K> {
     #[inline(always)]
-    pub fn new() -> IndexMap {
+    pub fn new() -> IndexMap<K> {
         IndexMap {
             map: HashMap::default(),
+            kind: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn insert<T: Property>(&mut self, index: Index<T>) {
+    pub fn insert<T: Property>(&mut self, index: Index<T, K>) {
         let result = self.map.insert(type_of::<T>(), Box::new(index));
         // We shouldn't insert an index for a type that already has an index.
         assert!(result.is_none());
     }
 
     #[inline(always)]
-    pub fn get_container_mut<T: Property + 'static>(&mut self) -> &mut Index<T> {
+    pub fn get_container_mut<T: Property + 'static>(&mut self) -> &mut Index<T, K> {
         unsafe {
-            self.map
+            (self.map
                 .entry(type_of::<T>())
-                .or_insert_with(|| Box::new(Index::<T>::new()))
+                .or_insert_with(|| Box::new(Index::<T, K>::new()))
+                .as_mut() as &mut dyn Any)
                 .downcast_mut()
                 .unwrap_unchecked()
         }
     }
 
     #[inline(always)]
-    pub fn get_container_ref<T: Property + 'static>(&self) -> Option<&Index<T>> {
+    pub fn get_container_ref<T: Property + 'static>(&self) -> Option<&Index<T, K>> {
         self.map
             .get(&type_of::<T>())
             .map(|v|
                 unsafe {
-                    v.downcast_ref()
+                    (v.as_ref() as &dyn Any)
+                        .downcast_ref()
                         .unwrap_unchecked()
                 }
             )
     }
 
     #[inline(always)]
-    pub unsafe fn get_container_ref_unchecked<T: Property + 'static>(&self) -> &Index<T> { unsafe {
-        self.map
-            .get(&type_of::<T>())
-            .unwrap_unchecked()
-            .downcast_ref()
-            .unwrap_unchecked()
-    }}
+    pub unsafe fn get_container_ref_unchecked<T: Property + 'static>(&self) -> &Index<T, K> {
+        unsafe {
+            (self.map
+                .get(&type_of::<T>())
+                .unwrap_unchecked()
+                .as_ref() as &dyn Any)
+                .downcast_ref()
+                .unwrap_unchecked()
+        }
+    }
 
     #[inline(always)]
     pub fn contains_key(&self, type_of: &TypeId) -> bool {
         self.map.contains_key(type_of)
     }
+
+    /// Whether the property with the given `TypeId` has an index that is in use.
+    pub(crate) fn is_indexed(&self, type_of: &TypeId) -> bool {
+        self.map.get(type_of).is_some_and(|index| index.is_indexed())
+    }
+
+    /// Adds an entity whose id was reused to every index that has already passed the id.
+    pub(crate) fn add_reused_entity(&mut self, context: &Context, entity_id: EntityId) {
+        for index in self.map.values_mut() {
+            index.add_reused_entity(context, entity_id);
+        }
+    }
+
+    /// Empties every index, so that each is rebuilt on the next refresh.
+    pub(crate) fn reset_all(&mut self) {
+        for index in self.map.values_mut() {
+            index.reset();
+        }
+    }
+
+    /// Brings every index up to date and returns the `AnyIndex::bucket_sizes` of each indexed
+    /// property along with its name.
+    pub(crate) fn all_bucket_sizes(
+        &mut self,
+        context: &Context,
+    ) -> Vec<(&'static str, Vec<(EntityId, usize)>)> {
+        let mut bucket_sizes = Vec::new();
+        for index in self.map.values_mut() {
+            index.refresh(context);
+            if let Some(sizes) = index.bucket_sizes() {
+                bucket_sizes.push((index.property_name(), sizes));
+            }
+        }
+        bucket_sizes
+    }
+
+    /// Brings every index up to date and returns the number of entities with each value of each
+    /// indexed property, keyed by property name.
+    pub(crate) fn all_value_counts(
+        &mut self,
+        context: &Context,
+    ) -> HashMap<String, HashMap<IndexValue, usize>> {
+        let mut counts = HashMap::with_capacity(self.map.len());
+        for index in self.map.values_mut() {
+            index.refresh(context);
+            if let Some(value_counts) = index.value_counts() {
+                counts.insert(index.property_name().to_string(), value_counts);
+            }
+        }
+        counts
+    }
 }
 
 /*
@@ -259,8 +481,15 @@ pub fn process_indices(
 mod test {
     // Tests in `src/people/query.rs` also exercise indexing code.
 
-    use super::IndexValue;
-    use crate::property::Property;
+    use super::{Index, IndexValue, OrderedF64};
+    use crate::{
+        context::Context,
+        entity::{ContextEntityExt, EntityData},
+        hashing::hash_str,
+        property::Property,
+        EntityId,
+        HashMap,
+    };
 
     #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
     struct Age(u8);
@@ -270,31 +499,213 @@ mod test {
         }
     }
 
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    struct Vaccinated(bool);
+    impl Property for Vaccinated {
+        fn is_indexed() -> bool {
+            true
+        }
+    }
+
+    /// Returns the entities in the index bucket for `value` and the index's `max_indexed`, or
+    /// `None` if `T` isn't indexed.
+    fn index_state<T: Property>(context: &Context, value: &T) -> Option<(Vec<EntityId>, usize)> {
+        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let index_map = entity_data.property_indexes.borrow();
+        let index = index_map.get_container_ref::<T>()?;
+        let mut entities: Vec<EntityId> = index
+            .lookup
+            .as_ref()?
+            .get(&IndexValue::new(value))
+            .map(|entities| entities.iter().copied().collect())
+            .unwrap_or_default();
+        entities.sort();
+        Some((entities, index.max_indexed))
+    }
+
+    #[test]
+    fn index_on_create() {
+        let mut context = Context::new();
+        let first = context.add_entity((Vaccinated(true), Age(30))).unwrap();
+        let second = context.add_entity((Age(30), Vaccinated(true))).unwrap();
+        let third = context.add_entity((Vaccinated(false), Age(40))).unwrap();
+
+        // No query has run, but the declared-indexed property is already indexed.
+        assert_eq!(index_state(&context, &Vaccinated(true)), Some((vec![first, second], 3)));
+        assert_eq!(index_state(&context, &Vaccinated(false)), Some((vec![third], 3)));
+        assert_eq!(index_state(&context, &Age(30)), None);
+        assert_eq!(context.query_entities((Vaccinated(true), Age(30))), vec![first, second]);
+    }
+
+    #[test]
+    fn index_on_create_after_lazy_index() {
+        let mut context = Context::new();
+        let first = context.add_entity(Age(30)).unwrap();
+        context.index_property::<Age>();
+
+        // The index hasn't caught up with `first`, so `second` waits for the next refresh too.
+        let second = context.add_entity(Age(30)).unwrap();
+        assert_eq!(index_state(&context, &Age(30)), Some((vec![], 0)));
+        assert_eq!(context.query_entities(Age(30)), vec![first, second]);
+
+        // Once it has caught up, new entities are indexed as they're created.
+        let third = context.add_entity(Age(30)).unwrap();
+        assert_eq!(index_state(&context, &Age(30)), Some((vec![first, second, third], 3)));
+    }
+
+    #[test]
+    fn add_entity_to_index() {
+        let mut context = Context::new();
+        let first = context.add_entity(Age(30)).unwrap();
+        let second = context.add_entity(Age(30)).unwrap();
+        context.add_entity(Age(40)).unwrap();
+
+        let mut index = Index::<Age>::new();
+        index.lookup = Some(HashMap::default());
+        index.add_entity(&context, first);
+        index.add_entity(&context, second);
+
+        let lookup = index.lookup.as_ref().unwrap();
+        assert_eq!(lookup.len(), 1);
+        let entities = &lookup[&IndexValue::new(&Age(30))];
+        assert!(entities.contains(&first) && entities.contains(&second));
+    }
+
+    #[test]
+    fn add_entity_without_value_to_index() {
+        let mut context = Context::new();
+        context.add_entity(()).unwrap();
+
+        let mut index = Index::<Age>::new();
+        index.lookup = Some(HashMap::default());
+        index.add_entity(&context, EntityId(0));
+        assert!(index.lookup.unwrap().is_empty());
+    }
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct Weight(OrderedF64);
+    impl Property for Weight {}
+
+    #[test]
+    fn index_float_property() {
+        let mut context = Context::new();
+        let first = context.add_entity(Weight(OrderedF64::new(70.5))).unwrap();
+        let second = context.add_entity(Weight(OrderedF64::new(70.5))).unwrap();
+        let zero = context.add_entity(Weight(OrderedF64::new(-0.0))).unwrap();
+        let nan = context.add_entity(Weight(OrderedF64::new(f64::NAN))).unwrap();
+        context.add_entity(Weight(OrderedF64::new(80.0))).unwrap();
+        context.index_property::<Weight>();
+        context.query_entities(Weight(OrderedF64::new(0.0)));
+
+        let weight = |value: f64| Weight(OrderedF64::new(value));
+        assert_eq!(index_state(&context, &weight(70.5)).unwrap().0, vec![first, second]);
+        assert_eq!(index_state(&context, &weight(0.0)).unwrap().0, vec![zero]);
+        assert_eq!(index_state(&context, &weight(-f64::NAN)).unwrap().0, vec![nan]);
+        assert_eq!(context.query_entities(weight(70.5)), vec![first, second]);
+        assert_eq!(context.query_entities(weight(f64::NAN)), vec![nan]);
+    }
+
+    #[test]
+    fn ordered_f64_order() {
+        let mut values: Vec<OrderedF64> =
+            [f64::NAN, 1.0, -0.0, -1.0].into_iter().map(OrderedF64::new).collect();
+        values.sort();
+        let values: Vec<f64> = values.into_iter().map(OrderedF64::value).collect();
+        assert_eq!(values[..3], [-1.0, 0.0, 1.0]);
+        assert!(values[3].is_nan());
+        assert!(OrderedF64::new(-0.0).value().is_sign_positive());
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    struct Count(i32);
+    impl Property for Count {}
+
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    struct Label(&'static str);
+    impl Property for Label {}
+
     #[test]
     fn test_index_value_hasher_finish2_short() {
-        let value = 42;
+        let value = Count(42);
         let index = IndexValue::new(&value);
-        assert!(matches!(index, IndexValue::Fixed(_)));
+        assert!(matches!(index, IndexValue::Fixed(..)));
     }
 
     #[test]
     fn test_index_value_hasher_finish2_long() {
-        let value = "this is a longer string that exceeds 16 bytes";
+        let value = Label("this is a longer string that exceeds 16 bytes");
         let index = IndexValue::new(&value);
-        assert!(matches!(index, IndexValue::Variable(_)));
+        assert!(matches!(index, IndexValue::Variable(..)));
     }
 
     #[test]
     fn test_index_value_compute_same_values() {
-        let value = "test value";
-        let value2 = "test value";
+        let value = Label("test value");
+        let value2 = Label("test value");
         assert_eq!(IndexValue::new(&value), IndexValue::new(&value2));
     }
 
     #[test]
     fn test_index_value_compute_different_values() {
-        let value1 = 42;
-        let value2 = 43;
+        let value1 = Count(42);
+        let value2 = Count(43);
         assert_ne!(IndexValue::new(&value1), IndexValue::new(&value2));
     }
+
+    #[test]
+    fn test_index_value_distinct_types() {
+        #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+        struct Flag(bool);
+        impl Property for Flag {}
+
+        // Both serialize to a single zero byte.
+        assert_ne!(IndexValue::new(&Age(0)), IndexValue::new(&Flag(false)));
+        assert_eq!(IndexValue::new(&Age(0)), IndexValue::new(&Age(0)));
+    }
+
+    #[test]
+    fn test_index_value_byte_order() {
+        #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+        struct Code(u32);
+        impl Property for Code {}
+
+        #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+        struct Offset(i16);
+        impl Property for Offset {}
+
+        #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+        struct Position(usize, &'static str);
+        impl Property for Position {}
+
+        // The encoding starts with the hash of the property name and is little-endian whatever
+        // the host's byte order is.
+        let prefix = |name: &str| u128::from(hash_str(name));
+        assert_eq!(
+            IndexValue::new(&Code(0x0102_0304)),
+            IndexValue::Fixed(prefix(Code::name()) | (0x0102_0304 << 64))
+        );
+        assert_eq!(
+            IndexValue::new(&Offset(-2)),
+            IndexValue::Fixed(prefix(Offset::name()) | (0xfffe << 64))
+        );
+        // `usize` is written as a `u64` on every host.
+        let mut expected = hash_str(Position::name()).to_le_bytes().to_vec();
+        expected.extend_from_slice(&[5, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(b"ixa_core");
+        expected.push(0xff);
+        assert_eq!(IndexValue::new(&Position(5, "ixa_core")), IndexValue::Variable(expected));
+        assert_eq!(IndexValue::new(&Age(12)), IndexValue::new(&Age(12)));
+    }
+
+    #[test]
+    fn test_index_value_serde_round_trip() {
+        for value in [
+            IndexValue::new(&Age(12)),
+            IndexValue::new(&Label("this is a longer string that exceeds 16 bytes")),
+        ] {
+            let serialized = serde_json::to_string(&value).unwrap();
+            let deserialized: IndexValue = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, value);
+        }
+    }
 }
@@ -1,8 +1,10 @@
 use crate::{
-    entity::EntityData,
+    context::Context,
+    entity::{ContextEntityExt, EntityData, EntityKind},
     property::Property,
     type_of,
     EntityId,
+    IxaError,
     TypeId
 };
 use seq_macro::seq;
@@ -12,7 +14,35 @@ use seq_macro::seq;
 /// the tuple syntax.
 pub trait InitializationList {
     fn has_property(&self, t: TypeId) -> bool;
-    fn set_properties(self, entity_data: &mut EntityData, entity_id: EntityId);
+    /// Adds the `TypeId` and name of each property in the list to `properties`, in order and
+    /// including repeats.
+    fn collect_properties(properties: &mut Vec<(TypeId, &'static str)>);
+    /// Checks each value in the list with `Property::validate`, so that an invalid list is
+    /// rejected before any value is stored.
+    fn validate(&self) -> Result<(), IxaError>;
+    fn set_properties<K: EntityKind>(self, entity_data: &mut EntityData<K>, entity_id: EntityId);
+    /// Sets each property in the list on an existing entity with `set_property_of()`, so that
+    /// change events are emitted and indexes are updated.
+    fn update_properties<K: EntityKind>(
+        self,
+        context: &mut Context,
+        entity_id: EntityId,
+    ) -> Result<(), IxaError>;
+    /// Registers each property in the list for the entities of kind `K`.
+    fn register_properties<K: EntityKind>(context: &mut Context);
+    /// Adds the new entity to the index of each indexed property in the list.
+    fn index_new_entity<K: EntityKind>(context: &Context, entity_id: EntityId);
+}
+
+/// Adds the new entity to the index for `T`. `T` must be registered for `K`.
+pub(super) fn index_new_entity<K: EntityKind, T: Property>(context: &Context, entity_id: EntityId) {
+    // Will not panic, as registering `T` creates the `EntityData`.
+    let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+    entity_data
+        .property_indexes
+        .borrow_mut()
+        .get_container_mut::<T>()
+        .add_new_entity(context, entity_id);
 }
 
 // Implement the query version with 0 and 1 parameters
@@ -20,7 +50,20 @@ impl InitializationList for () {
     fn has_property(&self, _: TypeId) -> bool {
         false
     }
-    fn set_properties(self, _entity_data: &mut EntityData, _entity_id: EntityId) {}
+    fn collect_properties(_properties: &mut Vec<(TypeId, &'static str)>) {}
+    fn validate(&self) -> Result<(), IxaError> {
+        Ok(())
+    }
+    fn set_properties<K: EntityKind>(self, _: &mut EntityData<K>, _: EntityId) {}
+    fn update_properties<K: EntityKind>(
+        self,
+        _: &mut Context,
+        _: EntityId,
+    ) -> Result<(), IxaError> {
+        Ok(())
+    }
+    fn register_properties<K: EntityKind>(_context: &mut Context) {}
+    fn index_new_entity<K: EntityKind>(_context: &Context, _entity_id: EntityId) {}
 }
 
 impl<T1: Property> InitializationList for T1 {
@@ -28,9 +71,33 @@ impl<T1: Property> InitializationList for T1 {
         t == type_of::<T1>()
     }
 
-    fn set_properties(self, entity_data: &mut EntityData, entity_id: EntityId) {
+    fn collect_properties(properties: &mut Vec<(TypeId, &'static str)>) {
+        properties.push((type_of::<T1>(), T1::name()));
+    }
+
+    fn validate(&self) -> Result<(), IxaError> {
+        self.check_valid()
+    }
+
+    fn set_properties<K: EntityKind>(self, entity_data: &mut EntityData<K>, entity_id: EntityId) {
         entity_data.set_property::<T1>(entity_id, self);
     }
+
+    fn update_properties<K: EntityKind>(
+        self,
+        context: &mut Context,
+        entity_id: EntityId,
+    ) -> Result<(), IxaError> {
+        context.set_property_of::<K, T1>(entity_id, self)
+    }
+
+    fn register_properties<K: EntityKind>(context: &mut Context) {
+        T1::register_for::<K>(context);
+    }
+
+    fn index_new_entity<K: EntityKind>(context: &Context, entity_id: EntityId) {
+        index_new_entity::<K, T1>(context, entity_id);
+    }
 }
 
 // Implement the versions with 1..20 parameters.
@@ -54,11 +121,51 @@ macro_rules! impl_initialization_list {
                     return false
                 }
 
-                fn set_properties(self, entity_data: &mut EntityData, entity_id: EntityId)  {
+                fn collect_properties(properties: &mut Vec<(TypeId, &'static str)>) {
+                    #(
+                        properties.push((type_of::<T~N>(), T~N::name()));
+                    )*
+                }
+
+                fn validate(&self) -> Result<(), IxaError> {
+                    #(
+                        self.N.check_valid()?;
+                    )*
+                    Ok(())
+                }
+
+                fn set_properties<K: EntityKind>(
+                    self,
+                    entity_data: &mut EntityData<K>,
+                    entity_id: EntityId,
+                ) {
                     #(
                        entity_data.set_property(entity_id, self.N );
                     )*
                 }
+
+                fn update_properties<K: EntityKind>(
+                    self,
+                    context: &mut Context,
+                    entity_id: EntityId,
+                ) -> Result<(), IxaError> {
+                    #(
+                        context.set_property_of::<K, T~N>(entity_id, self.N)?;
+                    )*
+                    Ok(())
+                }
+
+                fn register_properties<K: EntityKind>(context: &mut Context) {
+                    #(
+                        T~N::register_for::<K>(context);
+                    )*
+                }
+
+                fn index_new_entity<K: EntityKind>(context: &Context, entity_id: EntityId) {
+                    #(
+                        index_new_entity::<K, T~N>(context, entity_id);
+                    )*
+                }
             }
         });
     }
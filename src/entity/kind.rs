@@ -0,0 +1,19 @@
+/// A marker for a kind of entity. Each kind has its own `EntityData`, so entities of different
+/// kinds have separate id spaces, property values, and indexes. A model with people and
+/// households might define
+///
+/// ```ignore
+/// struct Household;
+/// impl EntityKind for Household {}
+///
+/// let household = context.add_entity_of::<Household, _>(HouseholdSize(4))?;
+/// let large = context.query_entities_of::<Household, _>(HouseholdSize(4));
+/// ```
+///
+/// The methods of `ContextEntityExt` without the `_of` suffix act on `DefaultEntity`.
+pub trait EntityKind: 'static {}
+
+/// The kind of the entities managed by the methods of `ContextEntityExt` that don't name a kind.
+pub enum DefaultEntity {}
+
+impl EntityKind for DefaultEntity {}
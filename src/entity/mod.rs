@@ -1,6 +1,9 @@
+mod builder;
 mod context_ext;
 mod data;
+mod event;
 mod index;
+mod kind;
 mod query;
 mod init_list;
 
@@ -8,7 +11,15 @@ mod init_list;
 pub(crate) use data::EntityData;
 pub(crate) use init_list::InitializationList;
 pub(crate) use context_ext::ContextEntityExtInternal;
-pub(crate) use index::{Index, IndexMap, IndexValue};
+pub(crate) use index::{Index, IndexMap};
 pub(crate) use query::Query;
 
+pub use builder::EntityBuilder;
 pub use context_ext::ContextEntityExt;
+// Used by `define_derived_property!`.
+#[allow(unused_imports)]
+pub use data::{global_dependency_changed, memoize};
+pub use event::{EntityCreatedEvent, PropertyChangeEvent};
+pub use index::{IndexValue, OrderedF64};
+pub use kind::{DefaultEntity, EntityKind};
+pub use query::QueryCompare;
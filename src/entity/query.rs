@@ -6,6 +6,7 @@ use crate::{
         ContextEntityExt,
         IndexValue,
         EntityData,
+        EntityKind,
     },
     property::Property,
     EntityId,
@@ -13,51 +14,78 @@ use crate::{
 };
 use crate::entity::ContextEntityExtInternal;
 
+/// A closure that checks whether an entity matches a property value that has no index.
+type UnindexedMatcher = Box<dyn Fn(&Context, EntityId) -> bool>;
+
 /// Encapsulates a query.
 ///
 /// [`Context::query_entities`] actually takes an instance of [`Query`], but because
 /// we implement `Query` for tuples of up to size 20, that's invisible
-/// to the caller. Do not use this trait directly.
+/// to the caller. Do not use this trait directly. Each method acts on the entities of kind `K`.
 pub trait Query {
     /// Registers each property in the query with the context and refreshes the indexes. Any work that requires
     /// a mutable reference to the context should be done here.
-    fn setup(&self, context: &mut Context);
+    fn setup<K: EntityKind>(&self, context: &mut Context);
     /// Executes the query, accumulating the results with `accumulator`.
-    fn execute_query(&self, context: &Context, accumulator: impl FnMut(EntityId));
+    fn execute_query<K: EntityKind>(&self, context: &Context, accumulator: impl FnMut(EntityId));
     /// Checks that the given entity matches the query.
-    fn match_entity(&self, context: &mut Context, entity: EntityId) -> bool;
+    fn match_entity<K: EntityKind>(&self, context: &mut Context, entity: EntityId) -> bool;
+    /// Returns an upper bound on the number of matching entities if one is cheaply available,
+    /// namely the size of the smallest index bucket the query uses. Call after `setup`.
+    fn size_hint<K: EntityKind>(&self, _context: &Context) -> Option<usize> {
+        None
+    }
+    /// Returns the exact number of matching entities if it can be read without visiting them,
+    /// as when the query is a single indexed property. Call after `setup`.
+    fn exact_count<K: EntityKind>(&self, _context: &Context) -> Option<usize> {
+        None
+    }
+}
+
+/// Returns the number of entities in the index bucket for `value`, or `None` if `T` isn't indexed.
+fn index_bucket_len<K: EntityKind, T: Property>(context: &Context, value: &T) -> Option<usize> {
+    let entity_data = context.get_data_container::<EntityData<K>>()?;
+    let index_map = entity_data.property_indexes.borrow();
+    let lookup = index_map.get_container_ref::<T>()?.lookup.as_ref()?;
+    Some(lookup.get(&IndexValue::new(value)).map_or(0, HashSet::len))
 }
 
 // The empty query
 impl Query for () {
-    fn setup(&self, _: &mut Context) {}
-    fn execute_query(&self, _context: &Context, _accumulator: impl FnMut(EntityId)){}
-    fn match_entity(&self, _context: &mut Context, _entity: EntityId) -> bool { true }
+    fn setup<K: EntityKind>(&self, _: &mut Context) {}
+    fn execute_query<K: EntityKind>(&self, _context: &Context, _accumulator: impl FnMut(EntityId)){}
+    fn match_entity<K: EntityKind>(&self, _context: &mut Context, _entity: EntityId) -> bool {
+        true
+    }
 }
 
 // The query with one parameter
 impl<T1: Property> Query for T1 {
-    fn setup(&self, context: &mut Context) {
-        if !context.is_registered::<T1>() {
-            T1::register(context);
+    fn setup<K: EntityKind>(&self, context: &mut Context) {
+        if !context.is_registered::<K, T1>() {
+            T1::register_for::<K>(context);
         }
 
         // 1. Refresh the indexes for each property in the query.
-        let mut index_map = context.get_data_container::<EntityData>()
+        let mut index_map = context.get_data_container::<EntityData<K>>()
                                    .unwrap() // ToDo: Guarantee this unwrap doesn't panic.
                                    .property_indexes
                                    .borrow_mut();
         index_map.get_container_mut::<T1>().index_unindexed_entities(context);
     }
 
-    fn execute_query(&self, context: &Context, mut accumulator: impl FnMut(EntityId)){
+    fn execute_query<K: EntityKind>(
+        &self,
+        context: &Context,
+        mut accumulator: impl FnMut(EntityId),
+    ) {
         // ToDo: Guarantee this unwrap doesn't panic.
-        let entity_data = context.get_data_container::<EntityData>().unwrap();
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
         let index_map   = entity_data.property_indexes
-                                     .borrow_mut();
+                                     .borrow();
         let mut indexes: Vec<&HashSet<EntityId>> = Vec::new();
         // A vector of closures that look up a property for an `entity_id`
-        let mut unindexed: Vec<Box<dyn Fn(&EntityData, EntityId) -> bool>> = Vec::new();
+        let mut unindexed: Vec<UnindexedMatcher> = Vec::new();
 
         {
             // 1. Refresh the indexes for each property in the query.
@@ -65,7 +93,7 @@ impl<T1: Property> Query for T1 {
 
             // 2. Collect the index entry corresponding to the value.
             let index = unsafe{ index_map.get_container_ref::<T1>().unwrap_unchecked() };
-            let hash_value = IndexValue::new(&self);
+            let hash_value = IndexValue::new(self);
             if let Some(lookup) = &index.lookup {
                 if let Some(entities) = lookup.get(&hash_value) {
                     indexes.push(entities);
@@ -77,10 +105,11 @@ impl<T1: Property> Query for T1 {
                 // No index, so we'll get to this after.
                 unindexed.push(
                     Box::new(move
-                    |entity_data: &EntityData, entity_id: EntityId| {
-                        match entity_data.get_property_ref::<T1>(entity_id) {
+                    |context: &Context, entity_id: EntityId| {
+                        // Computed so that derived properties match, too.
+                        match T1::compute_for::<K>(context, entity_id) {
                             Some(value) => {
-                                hash_value == IndexValue::new(value)
+                                hash_value == IndexValue::new(&value)
                             }
                             _ => { false }
                         }
@@ -121,7 +150,7 @@ impl<T1: Property> Query for T1 {
 
             // (2) check the unindexed properties
             for hash_lookup in &unindexed {
-                if !hash_lookup(entity_data, entity_id) {
+                if !hash_lookup(context, entity_id) {
                     continue 'outer;
                 }
             }
@@ -131,8 +160,17 @@ impl<T1: Property> Query for T1 {
         }
     }
 
-    fn match_entity(&self, context: &mut Context, entity: EntityId) -> bool {
-        match context.get_property::<T1>(entity) {
+    fn size_hint<K: EntityKind>(&self, context: &Context) -> Option<usize> {
+        index_bucket_len::<K, T1>(context, self)
+    }
+
+    fn exact_count<K: EntityKind>(&self, context: &Context) -> Option<usize> {
+        // The bucket holds exactly the entities with the value, as `setup` refreshed the index.
+        index_bucket_len::<K, T1>(context, self)
+    }
+
+    fn match_entity<K: EntityKind>(&self, context: &mut Context, entity: EntityId) -> bool {
+        match context.get_property_of::<K, T1>(entity) {
 
             Some(value) if &value == self => {
                true
@@ -161,17 +199,14 @@ macro_rules! impl_query {
                 )*
             )
             {
-                fn setup(&self, context: &mut Context) {
+                fn setup<K: EntityKind>(&self, context: &mut Context) {
                     #(
-                        if !context.get_data_container_mut::<EntityData>()
-                              .registered_derived_properties
-                              .contains(&$crate::type_of::<T~N>())
-                        {
-                            <T~N>::register(context);
+                        if !context.is_registered::<K, T~N>() {
+                            <T~N>::register_for::<K>(context);
                         }
                     )*
                     // 1. Refresh the indexes for each property in the query.
-                    let mut index_map = context.get_data_container::<EntityData>()
+                    let mut index_map = context.get_data_container::<EntityData<K>>()
                                                .unwrap() // ToDo: Guarantee this unwrap doesn't panic.
                                                .property_indexes
                                                .borrow_mut();
@@ -180,14 +215,18 @@ macro_rules! impl_query {
                 )*
                 }
 
-                fn execute_query(&self, context: &Context, mut accumulator: impl FnMut(EntityId)) {
+                fn execute_query<K: EntityKind>(
+                    &self,
+                    context: &Context,
+                    mut accumulator: impl FnMut(EntityId),
+                ) {
                     // ToDo: Guarantee this unwrap doesn't panic.
-                    let entity_data = context.get_data_container::<EntityData>().unwrap();
+                    let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
                     let index_map   = entity_data.property_indexes
-                                                .borrow_mut();
+                                                .borrow();
                     let mut indexes: Vec<&HashSet<EntityId>> = Vec::new();
                     // A vector of closures that look up a property for an `entity_id`
-                    let mut unindexed: Vec<Box<dyn Fn(&EntityData, EntityId) -> bool>> = Vec::new();
+                    let mut unindexed: Vec<UnindexedMatcher> = Vec::new();
 
                     // 1. Refresh the indexes for each property in the query.
                     //    Done in setup.
@@ -209,10 +248,11 @@ macro_rules! impl_query {
                             unindexed.push(
                                 Box::new(
                                     move
-                                    |entity_data: &EntityData, entity_id: EntityId| {
-                                        match entity_data.get_property_ref::<T~N>(entity_id) {
+                                    |context: &Context, entity_id: EntityId| {
+                                        // Computed so that derived properties match, too.
+                                        match <T~N>::compute_for::<K>(context, entity_id) {
                                             Some(value) => {
-                                                hash_value == IndexValue::new(value)
+                                                hash_value == IndexValue::new(&value)
                                             }
                                             _ => { false }
                                         }
@@ -253,7 +293,7 @@ macro_rules! impl_query {
 
                         // (2) check the unindexed properties
                         for hash_lookup in &unindexed {
-                            if !hash_lookup(entity_data, entity_id) {
+                            if !hash_lookup(context, entity_id) {
                                 continue 'outer;
                             }
                         }
@@ -263,9 +303,24 @@ macro_rules! impl_query {
                     }
                 }
 
-                fn match_entity(&self, context: &mut Context, entity: EntityId) -> bool {
+                fn size_hint<K: EntityKind>(&self, context: &Context) -> Option<usize> {
+                    [
+                        #(
+                            index_bucket_len::<K, T~N>(context, &self.N),
+                        )*
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .min()
+                }
+
+                fn match_entity<K: EntityKind>(
+                    &self,
+                    context: &mut Context,
+                    entity: EntityId,
+                ) -> bool {
                     #(
-                        match context.get_property::<T~N>(entity) {
+                        match context.get_property_of::<K, T~N>(entity) {
 
                             Some(value) if value == self.N => {
                                 /* pass through */
@@ -290,6 +345,68 @@ seq!(Z in 1..20 {
     impl_query!(Z);
 });
 
+/// A query that relates two properties of the same entity, matching the entities that have values
+/// for both `A` and `B` for which the comparator returns `true`. Because the match depends on both
+/// values together, this query can't use indexes and scans every entity.
+///
+/// Example:
+/// ```ignore
+/// #[derive(Copy, Clone, PartialEq, Debug, Hash)]
+/// struct InfectionTime(u32);
+/// impl Property for InfectionTime {}
+///
+/// #[derive(Copy, Clone, PartialEq, Debug, Hash)]
+/// struct RecoveryTime(u32);
+/// impl Property for RecoveryTime {}
+///
+/// let late = context.query_entities(QueryCompare::new(
+///     |recovery: &RecoveryTime, infection: &InfectionTime| recovery.0 > infection.0 + 14,
+/// ));
+/// ```
+pub struct QueryCompare<A: Property, B: Property>(pub fn(&A, &B) -> bool);
+
+impl<A: Property, B: Property> QueryCompare<A, B> {
+    pub fn new(comparator: fn(&A, &B) -> bool) -> Self {
+        Self(comparator)
+    }
+
+    fn matches(&self, a: Option<A>, b: Option<B>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => (self.0)(&a, &b),
+            _ => false,
+        }
+    }
+}
+
+impl<A: Property, B: Property> Query for QueryCompare<A, B> {
+    fn setup<K: EntityKind>(&self, context: &mut Context) {
+        A::register_for::<K>(context);
+        B::register_for::<K>(context);
+    }
+
+    fn execute_query<K: EntityKind>(
+        &self,
+        context: &Context,
+        mut accumulator: impl FnMut(EntityId),
+    ) {
+        // Will not panic, as `setup` creates the `EntityData` when it registers the properties.
+        let entity_data = context.get_data_container::<EntityData<K>>().unwrap();
+        for entity_id in entity_data.entity_iterator() {
+            let a = A::compute_for::<K>(context, entity_id);
+            let b = B::compute_for::<K>(context, entity_id);
+            if self.matches(a, b) {
+                accumulator(entity_id);
+            }
+        }
+    }
+
+    fn match_entity<K: EntityKind>(&self, context: &mut Context, entity: EntityId) -> bool {
+        let a = context.get_property_of::<K, A>(entity);
+        let b = context.get_property_of::<K, B>(entity);
+        self.matches(a, b)
+    }
+}
+
 /// Helper utility for combining two queries, useful if you want
 /// to iteratively construct a query in multiple parts.
 ///
@@ -347,7 +464,10 @@ mod tests {
     use crate::define_derived_property;
     use crate::entity::data::EntityData;
     use crate::property::Property;
-    use crate::entity::context_ext::{ContextEntityExt, ContextEntityExtInternal};
+    use crate::entity::context_ext::ContextEntityExt;
+    use crate::entity::query::{index_bucket_len, Query, QueryCompare};
+    use crate::EntityId;
+    use crate::entity::{DefaultEntity, EntityKind};
 
     #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
     struct Age(u8);
@@ -425,6 +545,15 @@ mod tests {
         assert_eq!(entities.len(), 1);
     }
 
+    #[test]
+    fn index_property_reports_creation() {
+        let mut context = Context::new();
+        let _ = context.add_entity(RiskCategory::High).unwrap();
+        assert!(context.index_property::<RiskCategory>());
+        assert!(!context.index_property::<RiskCategory>());
+        assert!(property_is_indexed::<RiskCategory>(&mut context));
+    }
+
     #[test]
     fn query_entity_macro_change() {
         let mut context = Context::new();
@@ -432,16 +561,34 @@ mod tests {
 
         let entities = context.query_entities(RiskCategory::High);
         assert_eq!(entities.len(), 1);
-        let entities = context.query_entities(RiskCategory::High);
+        let entities = context.query_entities(RiskCategory::Low);
         assert_eq!(entities.len(), 0);
 
-        context.set_property(person1, RiskCategory::High);
+        context.set_property(person1, RiskCategory::Low).unwrap();
         let entities = context.query_entities(RiskCategory::High);
         assert_eq!(entities.len(), 0);
-        let entities = context.query_entities(RiskCategory::High);
+        let entities = context.query_entities(RiskCategory::Low);
         assert_eq!(entities.len(), 1);
     }
 
+    #[test]
+    fn query_entity_indexed_change() {
+        let mut context = Context::new();
+        context.index_property::<RiskCategory>();
+        let person1 = context.add_entity(RiskCategory::High).unwrap();
+        let person2 = context.add_entity(RiskCategory::High).unwrap();
+        assert_eq!(context.query_entities(RiskCategory::High), vec![person1, person2]);
+
+        context.set_property(person1, RiskCategory::Low).unwrap();
+        assert_eq!(context.query_entities(RiskCategory::High), vec![person2]);
+        assert_eq!(context.query_entities(RiskCategory::Low), vec![person1]);
+        assert_eq!(context.query_entity_count(RiskCategory::High), 1);
+
+        // Setting the same value again leaves the index unchanged.
+        context.set_property(person1, RiskCategory::Low).unwrap();
+        assert_eq!(context.query_entities(RiskCategory::Low), vec![person1]);
+    }
+
     #[test]
     fn query_entity_index_after_add() {
         let mut context = Context::new();
@@ -475,13 +622,41 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Property not initialized")]
-    // This will panic when we query.
-    fn query_entities_add_after_index_panic() {
+    // Entities without a value are left out of the index until they get one.
+    fn query_entities_add_after_index_without_value() {
         let mut context = Context::new();
-        context.add_entity(()).unwrap();
+        let entity_id = context.add_entity(()).unwrap();
         context.index_property::<RiskCategory>();
-        context.query_entities(RiskCategory::High);
+        assert!(context.query_entities(RiskCategory::High).is_empty());
+
+        context.set_property(entity_id, RiskCategory::High).unwrap();
+        assert_eq!(context.query_entities(RiskCategory::High), vec![entity_id]);
+    }
+
+    #[test]
+    fn query_entities_capacity_from_index() {
+        let mut context = Context::new();
+        context.index_property::<RiskCategory>();
+        for i in 0..1000 {
+            let category = if i % 4 == 0 { RiskCategory::High } else { RiskCategory::Low };
+            context.add_entity((category, Age((i % 100) as u8))).unwrap();
+        }
+
+        // A single indexed property sizes the result exactly.
+        let high = context.query_entities(RiskCategory::High);
+        assert_eq!(high.len(), 250);
+        assert_eq!(high.capacity(), 250);
+
+        // An intersection is sized to the smallest index bucket.
+        context.index_property::<Age>();
+        let high_and_forty = context.query_entities((RiskCategory::High, Age(40)));
+        assert_eq!(high_and_forty.len(), 10);
+        assert_eq!(high_and_forty.capacity(), 10);
+
+        // Without an index there's no hint, but the result is the same.
+        let mut unindexed = Context::new();
+        unindexed.add_entity(RiskCategory::High).unwrap();
+        assert_eq!(unindexed.query_entities(RiskCategory::High).len(), 1);
     }
 
     #[test]
@@ -527,6 +702,27 @@ mod tests {
         assert_eq!(entities.len(), 2);
     }
 
+    #[test]
+    fn query_entities_sorted() {
+        let mut context = Context::new();
+        let entities: Vec<EntityId> =
+            (0..100).map(|_| context.add_entity((Age(1), RiskCategory::Low)).unwrap()).collect();
+        context.index_property::<Age>();
+        // Update the entities in reverse order, so they aren't indexed in order of id.
+        for entity_id in entities.iter().rev().step_by(2) {
+            context.set_property(*entity_id, Age(2)).unwrap();
+        }
+
+        let mut expected: Vec<EntityId> = entities.iter().rev().step_by(2).copied().collect();
+        expected.reverse();
+        assert_eq!(context.query_entities(Age(2)), expected);
+        assert_eq!(context.query_entities((Age(2), RiskCategory::Low)), expected);
+
+        let mut unordered = context.query_entities_unordered(Age(2));
+        unordered.sort();
+        assert_eq!(unordered, expected);
+    }
+
     #[test]
     fn query_derived_prop() {
         let mut context = Context::new();
@@ -544,7 +740,7 @@ mod tests {
         assert_eq!(seniors.len(), 1, "One senior");
         assert_eq!(not_seniors.len(), 1, "One non-senior");
 
-        context.set_property(person, Age(65));
+        context.set_property(person, Age(65)).unwrap();
 
         let not_seniors = context.query_entities(Senior(false));
         let seniors = context.query_entities(Senior(true));
@@ -571,7 +767,7 @@ mod tests {
         assert_eq!(seniors.len(), 1, "One senior");
         assert_eq!(not_seniors.len(), 1, "One non-senior");
 
-        context.set_property(person, Age(65));
+        context.set_property(person, Age(65)).unwrap();
 
         let not_seniors = context.query_entities(Senior(false));
         let seniors = context.query_entities(Senior(true));
@@ -579,6 +775,173 @@ mod tests {
         assert_eq!(seniors.len(), 2, "Two seniors");
         assert_eq!(not_seniors.len(), 0, "No non-seniors");
     }
+
+    #[test]
+    fn derived_index_updated_on_dependency_change() {
+        let mut context = Context::new();
+
+        #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+        struct Senior(bool);
+        define_derived_property!(Senior, [Age], |age| Some(Senior(age >= Age(65))));
+
+        context.index_property::<Senior>();
+        let person = context.add_entity(Age(64)).unwrap();
+        let _ = context.add_entity(Age(88)).unwrap();
+        let _ = context.query_entities(Senior(true));
+
+        context.set_property(person, Age(65)).unwrap();
+        // The buckets are correct before any query refreshes the index.
+        assert_eq!(index_bucket_len::<DefaultEntity, _>(&context, &Senior(true)), Some(2));
+        assert_eq!(index_bucket_len::<DefaultEntity, _>(&context, &Senior(false)), Some(0));
+
+        context.set_property(person, Age(30)).unwrap();
+        assert_eq!(index_bucket_len::<DefaultEntity, _>(&context, &Senior(true)), Some(1));
+        assert_eq!(context.query_entities(Senior(false)), vec![person]);
+    }
+
+    #[test]
+    fn nested_queries() {
+        let mut context = Context::new();
+        context.index_property::<Age>();
+        for age in [30, 30, 40] {
+            context.add_entity((Age(age), RiskCategory::High)).unwrap();
+        }
+        context.add_entity((Age(30), RiskCategory::Low)).unwrap();
+        let outer = (Age(30), RiskCategory::High);
+        let inner = Age(30);
+        outer.setup::<DefaultEntity>(&mut context);
+        inner.setup::<DefaultEntity>(&mut context);
+
+        // The outer query holds its borrow of the indexes while the inner query runs.
+        let context = &context;
+        let mut pairs = 0;
+        outer.execute_query::<DefaultEntity>(context, |_| {
+            inner.execute_query::<DefaultEntity>(context, |_| pairs += 1);
+        });
+        assert_eq!(pairs, 2 * 3);
+    }
+
+    #[test]
+    fn match_entity_tuple() {
+        let mut context = Context::new();
+        let person = context.add_entity((Age(42), RiskCategory::High)).unwrap();
+        let other = context.add_entity((Age(42), RiskCategory::Low)).unwrap();
+        let no_risk = context.add_entity(Age(42)).unwrap();
+        context.index_property::<Age>();
+
+        assert!(context.match_entity(person, ()));
+        assert!(context.match_entity(person, (Age(42), RiskCategory::High)));
+        assert!(!context.match_entity(other, (Age(42), RiskCategory::High)));
+        assert!(!context.match_entity(person, (Age(43), RiskCategory::High)));
+        // An entity with no value for a property in the query doesn't match.
+        assert!(!context.match_entity(no_risk, (Age(42), RiskCategory::High)));
+    }
+
+    #[test]
+    fn query_entity_count_from_index_bucket() {
+        let mut context = Context::new();
+        context.index_property::<Age>();
+        for id in 0..5000u32 {
+            let risk = if id % 3 == 0 { RiskCategory::High } else { RiskCategory::Low };
+            context.add_entity((Age((id % 100) as u8), risk)).unwrap();
+        }
+        context.remove_entity(EntityId(1)).unwrap();
+
+        // Indexed, so counted from the bucket.
+        for age in [0, 1, 42, 99, 100] {
+            assert!(Age(age).exact_count::<DefaultEntity>(&context).is_some());
+            assert_eq!(
+                context.query_entity_count(Age(age)),
+                context.query_entities(Age(age)).len()
+            );
+        }
+        // Unindexed, so counted by scanning.
+        for risk in [RiskCategory::High, RiskCategory::Low] {
+            assert!(risk.exact_count::<DefaultEntity>(&context).is_none());
+            assert_eq!(context.query_entity_count(risk), context.query_entities(risk).len());
+        }
+        assert_eq!(context.query_entity_count(RiskCategory::High), 1667);
+    }
+
+    #[test]
+    fn derived_index_updated_on_each_dependency_change() {
+        let mut context = Context::new();
+
+        #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+        struct VulnerableSenior(bool);
+        define_derived_property!(VulnerableSenior, [Age, RiskCategory], |age, risk| {
+            Some(VulnerableSenior(age >= Age(65) && risk == RiskCategory::High))
+        });
+
+        context.index_property::<VulnerableSenior>();
+        let person = context.add_entity((Age(64), RiskCategory::High)).unwrap();
+        let _ = context.add_entity((Age(88), RiskCategory::Low)).unwrap();
+        let _ = context.query_entities(VulnerableSenior(true));
+        let bucket_len = |context: &Context, value: bool| {
+            index_bucket_len::<DefaultEntity, _>(context, &VulnerableSenior(value))
+        };
+        assert_eq!(bucket_len(&context, true), Some(0));
+
+        // Setting either dependency moves the entity between buckets without a query.
+        context.set_property(person, Age(70)).unwrap();
+        assert_eq!(bucket_len(&context, true), Some(1));
+        assert_eq!(bucket_len(&context, false), Some(1));
+
+        context.set_property(person, RiskCategory::Low).unwrap();
+        assert_eq!(bucket_len(&context, true), Some(0));
+        assert_eq!(bucket_len(&context, false), Some(2));
+    }
+
+    #[derive(Copy, Clone, PartialEq, Debug, Hash)]
+    struct InfectionTime(u32);
+    impl Property for InfectionTime {}
+
+    #[derive(Copy, Clone, PartialEq, Debug, Hash)]
+    struct RecoveryTime(u32);
+    impl Property for RecoveryTime {}
+
+    fn recovers_late(recovery: &RecoveryTime, infection: &InfectionTime) -> bool {
+        recovery.0 > infection.0 + 7
+    }
+
+    #[test]
+    fn query_compare() {
+        let mut context = Context::new();
+        let slow = context.add_entity((InfectionTime(1), RecoveryTime(20))).unwrap();
+        let fast = context.add_entity((InfectionTime(5), RecoveryTime(10))).unwrap();
+        // Entities missing either property never match.
+        context.add_entity(InfectionTime(0)).unwrap();
+        context.add_entity(RecoveryTime(100)).unwrap();
+
+        assert_eq!(context.query_entities(QueryCompare::new(recovers_late)), vec![slow]);
+        assert!(context.match_entity(slow, QueryCompare::new(recovers_late)));
+        assert!(!context.match_entity(fast, QueryCompare::new(recovers_late)));
+    }
+
+    struct Household;
+    impl EntityKind for Household {}
+
+    #[test]
+    fn query_entity_kinds_separately() {
+        let mut context = Context::new();
+        context.index_property::<Age>();
+        context.index_property_of::<Household, Age>();
+        let person = context.add_entity(Age(30)).unwrap();
+        context.add_entity(Age(60)).unwrap();
+        let household = context.add_entity_of::<Household, _>(Age(30)).unwrap();
+        context.add_entity_of::<Household, _>(Age(30)).unwrap();
+
+        assert_eq!(context.query_entities(Age(30)), vec![person]);
+        let households = context.query_entities_of::<Household, _>(Age(30));
+        assert_eq!(households.len(), 2);
+        assert!(households.contains(&household));
+        assert_eq!(context.query_entity_count_of::<Household, _>(Age(60)), 0);
+
+        // Each kind has its own index.
+        assert_eq!(index_bucket_len::<DefaultEntity, _>(&context, &Age(30)), Some(1));
+        assert_eq!(index_bucket_len::<Household, _>(&context, &Age(30)), Some(2));
+        assert_eq!(index_bucket_len::<Household, _>(&context, &Age(60)), Some(0));
+    }
 /*
     #[test]
     fn query_and_returns_entities() {
@@ -1,6 +1,7 @@
 //! Provides `IxaError` and wraps other errors.
 use std::fmt::{self, Debug, Display};
 use std::io;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
@@ -9,9 +10,26 @@ use std::io;
 pub enum IxaError {
     IoError(io::Error),
     JsonError(serde_json::Error),
-    // CsvError(csv::Error),
+    CsvError(csv::Error),
     Utf8Error(std::string::FromUtf8Error),
     ParseIntError(std::num::ParseIntError),
+    /// A property that must be registered for the operation isn't. Holds the property name.
+    PropertyNotRegistered(String),
+    /// A checkpoint holds a data plugin that isn't registered with
+    /// `ContextCheckpointExt::register_checkpointable()`. Holds the plugin's type name.
+    PluginNotRegistered(String),
+    /// A new entity wasn't given a value for a required property. Holds the property name.
+    MissingRequiredProperty(String),
+    /// A report file already exists and the report options don't allow overwriting it. Holds the
+    /// path of the file.
+    ReportFileExists(PathBuf),
+    /// Reading or adding the entity for the row on `line` of CSV data failed with `error`.
+    /// `path` is the file the data was read from, if any.
+    CsvRowError {
+        path: Option<PathBuf>,
+        line: u64,
+        error: Box<IxaError>,
+    },
     IxaError(String),
 }
 
@@ -26,12 +44,12 @@ impl From<serde_json::Error> for IxaError {
         IxaError::JsonError(error)
     }
 }
-// 
-// impl From<csv::Error> for IxaError {
-//     fn from(error: csv::Error) -> Self {
-//         IxaError::CsvError(error)
-//     }
-// }
+
+impl From<csv::Error> for IxaError {
+    fn from(error: csv::Error) -> Self {
+        IxaError::CsvError(error)
+    }
+}
 
 impl From<std::string::FromUtf8Error> for IxaError {
     fn from(error: std::string::FromUtf8Error) -> Self {
@@ -57,11 +75,100 @@ impl From<&str> for IxaError {
     }
 }
 
-impl std::error::Error for IxaError {}
+impl std::error::Error for IxaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IxaError::IoError(error) => Some(error),
+            IxaError::JsonError(error) => Some(error),
+            IxaError::CsvError(error) => Some(error),
+            IxaError::Utf8Error(error) => Some(error),
+            IxaError::ParseIntError(error) => Some(error),
+            IxaError::CsvRowError { error, .. } => Some(error.as_ref()),
+            IxaError::PropertyNotRegistered(_)
+            | IxaError::PluginNotRegistered(_)
+            | IxaError::MissingRequiredProperty(_)
+            | IxaError::ReportFileExists(_)
+            | IxaError::IxaError(_) => None,
+        }
+    }
+}
 
 impl Display for IxaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error: {self:?}")?;
-        Ok(())
+        match self {
+            IxaError::IoError(error) => write!(f, "I/O error: {error}"),
+            IxaError::JsonError(error) => write!(f, "JSON error: {error}"),
+            IxaError::CsvError(error) => write!(f, "CSV error: {error}"),
+            IxaError::Utf8Error(error) => write!(f, "UTF-8 error: {error}"),
+            IxaError::ParseIntError(error) => write!(f, "Integer parse error: {error}"),
+            IxaError::PropertyNotRegistered(name) => write!(f, "Property {name} is not registered"),
+            IxaError::PluginNotRegistered(name) => {
+                write!(f, "Data plugin {name} is not registered as checkpointable")
+            }
+            IxaError::MissingRequiredProperty(name) => {
+                write!(f, "Missing initial value for required property {name}")
+            }
+            IxaError::ReportFileExists(path) => write!(
+                f,
+                "File already exists: {}. Please set `overwrite` to true in the file \
+                 configuration and rerun.",
+                path.display()
+            ),
+            IxaError::CsvRowError { path: Some(path), line, error } => {
+                write!(f, "{} line {line}: {error}", path.display())
+            }
+            IxaError::CsvRowError { path: None, line, error } => write!(f, "line {line}: {error}"),
+            IxaError::IxaError(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::Context, global_properties::ContextGlobalPropertiesExt};
+    use std::{error::Error, path::Path};
+
+    #[test]
+    fn io_error() {
+        let mut context = Context::new();
+        let error = context
+            .load_global_properties(Path::new("no/such/directory/properties.json"))
+            .unwrap_err();
+        assert!(matches!(&error, IxaError::IoError(io_error)
+            if io_error.kind() == io::ErrorKind::NotFound));
+        assert!(error.to_string().starts_with("I/O error: "), "{error}");
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            IxaError::MissingRequiredProperty("Age".to_string()).to_string(),
+            "Missing initial value for required property Age"
+        );
+        assert_eq!(
+            IxaError::PropertyNotRegistered("Age".to_string()).to_string(),
+            "Property Age is not registered"
+        );
+        assert_eq!(
+            IxaError::PluginNotRegistered("Draws".to_string()).to_string(),
+            "Data plugin Draws is not registered as checkpointable"
+        );
+        assert_eq!(
+            IxaError::ReportFileExists(PathBuf::from("output/incidence.csv")).to_string(),
+            "File already exists: output/incidence.csv. Please set `overwrite` to true in the \
+             file configuration and rerun."
+        );
+        assert_eq!(
+            IxaError::CsvRowError {
+                path: Some(PathBuf::from("census.csv")),
+                line: 3,
+                error: Box::new(IxaError::from("bad row")),
+            }
+            .to_string(),
+            "census.csv line 3: bad row"
+        );
+        assert_eq!(IxaError::from("Something went wrong").to_string(), "Something went wrong");
     }
 }
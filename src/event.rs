@@ -0,0 +1,322 @@
+//! Events are how modules notify each other that something happened without needing to know who
+//! is listening. Any `Clone + 'static` type can be an event. Handlers are registered with
+//! [`ContextEventExt::subscribe_to_event()`], and every handler subscribed to an event type is
+//! called, in the order it subscribed, each time an event of that type is emitted with
+//! [`ContextEventExt::emit_event()`].
+//!
+//! Subscribing returns a [`SubscriptionId`] that can later be passed to
+//! [`ContextEventExt::unsubscribe()`] to remove the handler. Unsubscribing from within a handler
+//! is allowed: the handler isn't called again, even by the dispatch in progress, and it is
+//! removed from its list once the outermost `emit_event()` call has completed.
+
+use crate::{
+    context::{Context, DataPlugin},
+    type_of,
+    HashMap,
+    HashSet,
+    TypeId,
+};
+use std::{any::Any, rc::Rc};
+
+/// A handle to a subscribed event handler, used to unsubscribe it.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SubscriptionId(usize);
+
+type EventHandler<E> = Rc<dyn Fn(&mut Context, E)>;
+
+/// The handlers for a single event type in subscription order.
+struct HandlerList<E> {
+    handlers: Vec<(SubscriptionId, EventHandler<E>)>,
+}
+
+/// Lets `EventData` remove a handler without knowing the type of event it handles.
+trait AnyHandlerList: Any {
+    /// Removes the handler with the given id, returning `true` if it was in this list.
+    fn remove(&mut self, subscription_id: SubscriptionId) -> bool;
+}
+
+impl<E: 'static> AnyHandlerList for HandlerList<E> {
+    fn remove(&mut self, subscription_id: SubscriptionId) -> bool {
+        let len = self.handlers.len();
+        self.handlers.retain(|(id, _)| *id != subscription_id);
+        self.handlers.len() != len
+    }
+}
+
+struct EventData {
+    /// Maps the `TypeId` of an event type `E` to its `HandlerList<E>`.
+    handlers: HashMap<TypeId, Box<dyn AnyHandlerList>>,
+    next_subscription_id: usize,
+    /// How many `emit_event` calls are currently on the stack.
+    dispatch_depth: usize,
+    /// Handlers unsubscribed during a dispatch. They are skipped right away and removed from
+    /// their lists once the outermost dispatch completes.
+    unsubscribed: HashSet<SubscriptionId>,
+    /// How many events have been emitted, including events no handler was subscribed to.
+    events_emitted: usize,
+}
+
+impl DataPlugin for EventData {
+    #[allow(non_upper_case_globals)]
+    const new: &'static dyn Fn() -> Self = &|| {
+        EventData {
+            handlers: HashMap::default(),
+            next_subscription_id: 0,
+            dispatch_depth: 0,
+            unsubscribed: HashSet::default(),
+            events_emitted: 0,
+        }
+    };
+}
+
+impl EventData {
+    fn get_handler_list_mut<E: Clone + 'static>(&mut self) -> &mut HandlerList<E> {
+        let list: &mut dyn Any = self
+            .handlers
+            .entry(type_of::<E>())
+            .or_insert_with(|| Box::new(HandlerList::<E> { handlers: Vec::new() }))
+            .as_mut();
+        // Will never panic, as only a `HandlerList<E>` can be mapped to by `type_of::<E>()`.
+        list.downcast_mut().unwrap()
+    }
+
+    fn remove_handler(&mut self, subscription_id: SubscriptionId) {
+        for list in self.handlers.values_mut() {
+            if list.remove(subscription_id) {
+                return;
+            }
+        }
+    }
+}
+
+pub trait ContextEventExt {
+    /// Registers `handler` to be called whenever an event of type `E` is emitted. Returns a
+    /// `SubscriptionId` that can be passed to `unsubscribe()` to remove the handler.
+    fn subscribe_to_event<E: Clone + 'static>(
+        &mut self,
+        handler: impl Fn(&mut Context, E) + 'static,
+    ) -> SubscriptionId;
+
+    /// Removes the handler with the given `SubscriptionId`. If called while an event is being
+    /// dispatched, the handler isn't called again, including later in the same dispatch.
+    /// Unsubscribing a handler that has already been removed does nothing.
+    fn unsubscribe(&mut self, subscription_id: SubscriptionId);
+
+    /// Calls every handler subscribed to events of type `E` with a copy of `event`.
+    fn emit_event<E: Clone + 'static>(&mut self, event: E);
+
+    /// Returns how many events have been emitted so far, including events that no handler was
+    /// subscribed to.
+    fn events_emitted(&self) -> usize;
+}
+
+impl ContextEventExt for Context {
+    fn subscribe_to_event<E: Clone + 'static>(
+        &mut self,
+        handler: impl Fn(&mut Context, E) + 'static,
+    ) -> SubscriptionId {
+        let event_data = self.get_data_container_mut::<EventData>();
+        let subscription_id = SubscriptionId(event_data.next_subscription_id);
+        event_data.next_subscription_id += 1;
+        event_data
+            .get_handler_list_mut::<E>()
+            .handlers
+            .push((subscription_id, Rc::new(handler)));
+        subscription_id
+    }
+
+    fn unsubscribe(&mut self, subscription_id: SubscriptionId) {
+        let event_data = self.get_data_container_mut::<EventData>();
+        if event_data.dispatch_depth > 0 {
+            event_data.unsubscribed.insert(subscription_id);
+        } else {
+            event_data.remove_handler(subscription_id);
+        }
+    }
+
+    fn emit_event<E: Clone + 'static>(&mut self, event: E) {
+        let event_data = self.get_data_container_mut::<EventData>();
+        event_data.events_emitted += 1;
+        // Handlers get a mutable context, so we call them from a copy of the handler list.
+        let handlers: Vec<(SubscriptionId, EventHandler<E>)> = event_data
+            .get_handler_list_mut::<E>()
+            .handlers
+            .iter()
+            .map(|(subscription_id, handler)| (*subscription_id, Rc::clone(handler)))
+            .collect();
+        if handlers.is_empty() {
+            return;
+        }
+
+        event_data.dispatch_depth += 1;
+        for (subscription_id, handler) in handlers {
+            // An earlier handler may have unsubscribed this one.
+            if self.get_data_container_mut::<EventData>().unsubscribed.contains(&subscription_id) {
+                continue;
+            }
+            handler(self, event.clone());
+        }
+
+        let event_data = self.get_data_container_mut::<EventData>();
+        event_data.dispatch_depth -= 1;
+        if event_data.dispatch_depth == 0 {
+            for subscription_id in std::mem::take(&mut event_data.unsubscribed) {
+                event_data.remove_handler(subscription_id);
+            }
+        }
+    }
+
+    fn events_emitted(&self) -> usize {
+        self.get_data_container::<EventData>()
+            .map_or(0, |event_data| event_data.events_emitted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Ping(u32);
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Pong;
+
+    #[test]
+    fn events_emitted() {
+        let mut context = Context::new();
+        assert_eq!(context.events_emitted(), 0);
+        // Events without handlers still count.
+        context.emit_event(Pong);
+        context.subscribe_to_event(|context, _: Ping| context.emit_event(Pong));
+        context.emit_event(Ping(1));
+        assert_eq!(context.events_emitted(), 3);
+    }
+
+    #[test]
+    fn emit_calls_handlers_in_subscription_order() {
+        let mut context = Context::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let calls_first = calls.clone();
+        context.subscribe_to_event(move |_, event: Ping| {
+            calls_first.borrow_mut().push(("first", event.0));
+        });
+        let calls_second = calls.clone();
+        context.subscribe_to_event(move |_, event: Ping| {
+            calls_second.borrow_mut().push(("second", event.0));
+        });
+
+        context.emit_event(Ping(7));
+        assert_eq!(*calls.borrow(), vec![("first", 7), ("second", 7)]);
+    }
+
+    #[test]
+    fn emit_without_subscribers() {
+        let mut context = Context::new();
+        context.emit_event(Pong);
+    }
+
+    #[test]
+    fn unsubscribe_stops_handler() {
+        let mut context = Context::new();
+        let count = Rc::new(Cell::new(0));
+
+        let count_clone = count.clone();
+        let subscription_id = context.subscribe_to_event(move |_, _: Ping| {
+            count_clone.set(count_clone.get() + 1);
+        });
+
+        context.emit_event(Ping(1));
+        context.unsubscribe(subscription_id);
+        context.emit_event(Ping(2));
+
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn unsubscribe_leaves_other_handlers() {
+        let mut context = Context::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let calls_clone = calls.clone();
+        let first_id = context.subscribe_to_event(move |_, Ping(n): Ping| {
+            calls_clone.borrow_mut().push(("first", n));
+        });
+        let calls_clone = calls.clone();
+        context.subscribe_to_event(move |_, Ping(n): Ping| {
+            calls_clone.borrow_mut().push(("second", n));
+        });
+
+        context.unsubscribe(first_id);
+        context.emit_event(Ping(1));
+
+        assert_eq!(*calls.borrow(), vec![("second", 1)]);
+    }
+
+    #[test]
+    fn unsubscribe_during_dispatch_is_deferred() {
+        let mut context = Context::new();
+        let count = Rc::new(Cell::new(0));
+        let own_id: Rc<Cell<Option<SubscriptionId>>> = Rc::new(Cell::new(None));
+
+        let count_clone = count.clone();
+        let own_id_clone = own_id.clone();
+        let subscription_id = context.subscribe_to_event(move |context, _: Ping| {
+            count_clone.set(count_clone.get() + 1);
+            context.unsubscribe(own_id_clone.get().unwrap());
+        });
+        own_id.set(Some(subscription_id));
+
+        context.emit_event(Ping(1));
+        context.emit_event(Ping(2));
+
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn unsubscribe_during_dispatch_skips_later_handler() {
+        let mut context = Context::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let second_id: Rc<Cell<Option<SubscriptionId>>> = Rc::new(Cell::new(None));
+
+        let calls_clone = calls.clone();
+        let second_id_clone = second_id.clone();
+        context.subscribe_to_event(move |context, Ping(n): Ping| {
+            calls_clone.borrow_mut().push(("first", n));
+            context.unsubscribe(second_id_clone.get().unwrap());
+        });
+        let calls_clone = calls.clone();
+        second_id.set(Some(context.subscribe_to_event(move |_, Ping(n): Ping| {
+            calls_clone.borrow_mut().push(("second", n));
+        })));
+
+        context.emit_event(Ping(1));
+        context.emit_event(Ping(2));
+
+        assert_eq!(*calls.borrow(), vec![("first", 1), ("first", 2)]);
+    }
+
+    #[test]
+    fn handlers_are_keyed_by_event_type() {
+        let mut context = Context::new();
+        let pings = Rc::new(Cell::new(0));
+        let pongs = Rc::new(Cell::new(0));
+
+        let pings_clone = pings.clone();
+        context.subscribe_to_event(move |_, _: Ping| pings_clone.set(pings_clone.get() + 1));
+        let pongs_clone = pongs.clone();
+        let pong_id = context.subscribe_to_event(move |_, _: Pong| {
+            pongs_clone.set(pongs_clone.get() + 1);
+        });
+
+        context.emit_event(Ping(1));
+        context.unsubscribe(pong_id);
+        context.emit_event(Pong);
+        context.emit_event(Ping(2));
+
+        assert_eq!(pings.get(), 2);
+        assert_eq!(pongs.get(), 0);
+    }
+}
@@ -1,8 +1,7 @@
 //! A generic mechanism for storing context-wide data.
 //!
-//! Global properties are not mutable and represent variables that are
-//! required in a global scope during the simulation, such as
-//! simulation parameters.
+//! Global properties represent variables that are required in a
+//! global scope during the simulation, such as simulation parameters.
 //! A global property can be of any type, and is is just a value
 //! stored in the context. Global properties are defined by the
 //! [`define_global_property!()`] macro and can then be
@@ -11,20 +10,31 @@
 //! * Directly by using [`Context::set_global_property_value()`]
 //! * Loaded from a configuration file using [`Context::load_global_properties()`]
 //!
-//! Attempting to change a global property which has been set already
-//! will result in an error.
+//! Setting a global property which has been set already replaces its
+//! value and, if the value is different, emits a [`GlobalPropertyChangeEvent`], so that modules
+//! that depend on the property can react. Loading a property from a
+//! configuration file when it already has a value is an error.
 //!
 //! Global properties can be read with [`Context::get_global_property_value()`]
-use crate::{HashMap, HashMapExt, context::Context, error::IxaError, trace, New};
+use crate::{
+    HashMap,
+    HashMapExt,
+    context::Context,
+    error::IxaError,
+    event::ContextEventExt,
+    trace,
+    type_of,
+    New,
+};
 // use serde::de::DeserializeOwned;
 use std::{
     any::{Any, TypeId},
     cell::RefCell,
-    collections::hash_map::Entry,
     fmt::Debug,
     fs,
     io::BufReader,
     path::Path,
+    rc::Rc,
     sync::{
         Arc,
         LazyLock,
@@ -36,12 +46,31 @@ use serde::de::DeserializeOwned;
 /// The trait representing a global property. Do not use this
 /// directly, but instead define global properties with
 /// [`define_global_property()`]
-pub trait GlobalProperty: New {
+pub trait GlobalProperty: New + serde::Serialize {
     #[allow(clippy::missing_errors_doc)]
     // A function which validates the global property.
     fn validate(value: &Self) -> Result<(), IxaError>;
 }
 
+/// Emitted when a global property that already has a value is set to a different one. It is not
+/// emitted when a global property is set for the first time or set to the value it already has.
+pub struct GlobalPropertyChangeEvent<G: GlobalProperty> {
+    /// The value being replaced.
+    pub previous: Rc<G>,
+    /// The new value.
+    pub current: Rc<G>,
+}
+
+// A derived `Clone` would require `G: Clone`.
+impl<G: GlobalProperty> Clone for GlobalPropertyChangeEvent<G> {
+    fn clone(&self) -> Self {
+        GlobalPropertyChangeEvent {
+            previous: Rc::clone(&self.previous),
+            current: Rc::clone(&self.current),
+        }
+    }
+}
+
 
 type PropertySetterFn =
     dyn Fn(&mut Context, serde_json::Value) -> Result<(), IxaError> + Send + Sync;
@@ -65,9 +94,9 @@ pub static GLOBAL_PROPERTIES: LazyLock<Mutex<RefCell<HashMap<String, Arc<Propert
     LazyLock::new(|| Mutex::new(RefCell::new(HashMap::new())));
 
 #[allow(clippy::missing_panics_doc)]
-pub fn add_global_property<T: GlobalProperty>(name: &str)
+pub fn add_global_property<T>(name: &str)
 where
-    for<'de> T: serde::Deserialize<'de> + serde::Serialize,
+    for<'de> T: GlobalProperty + serde::Deserialize<'de> + serde::Serialize,
 {
     trace!("Adding global property {}", name);
     let properties = GLOBAL_PROPERTIES.lock().unwrap();
@@ -76,21 +105,22 @@ where
         .insert(
             name.to_string(),
             Arc::new(PropertyAccessors {
-                setter: Box::new(
-                    |context: &mut Context, value| -> Result<(), IxaError> {
-                        let val: T = serde_json::from_value(value).map_err(|e| IxaError::from(e))?;
+                setter: Box::new({
+                    let name = name.to_string();
+                    move |context: &mut Context, value| -> Result<(), IxaError> {
+                        let val: T = serde_json::from_value(value).map_err(IxaError::from)?;
                         T::validate(&val)?;
-                        // if context.get_global_property_value(T::new()).is_some() {
-                        //     return Err(IxaError::IxaError(format!("Duplicate property {name}")));
-                        // }
+                        if context.get_global_property_value::<T>().is_some() {
+                            return Err(IxaError::IxaError(format!("Duplicate property {name}")));
+                        }
                         context.set_global_property_value::<T>(val)?;
                         Ok(())
                     }
-                ),
+                }),
                 getter: Box::new(|context: &Context| -> Result<Option<String>, IxaError> {
                     let value = context.get_global_property_value::<T>();
                     match value {
-                        Some(val) => Ok(Some(serde_json::to_string(val).map_err(|e| IxaError::from(e))?)),
+                        Some(val) => Ok(Some(serde_json::to_string(val).map_err(IxaError::from)?)),
                         None => Ok(None),
                     }
                 }),
@@ -114,12 +144,12 @@ macro_rules! define_global_property {
     ($global_property:ty, $validate: expr) => {
         
         impl $crate::global_properties::GlobalProperty for $global_property {
-            fn validate(val: & $global_property) -> Result<(), $crate::error::IxaError> {
+            fn validate(val: & $global_property) -> Result<(), $crate::IxaError> {
                 $validate(val)
             }
         }
-        
-        impl New for $global_property {
+
+        impl $crate::New for $global_property {
             const new: &'static dyn Fn() -> Self = &<$global_property>::default;
         }
 
@@ -139,12 +169,14 @@ macro_rules! define_global_property {
         define_global_property!($global_property, |_| { Ok(()) });
     };
 }
+#[allow(unused_imports)]
 pub use define_global_property;
 
 
 #[derive(Default)]
 struct GlobalPropertiesData {
-    global_property_container: HashMap<TypeId, Box<dyn Any>>,
+    /// Maps the `TypeId` of each global property type `T` to an `Rc<T>` holding its value.
+    global_property_container: HashMap<TypeId, Rc<dyn Any>>,
 }
 
 impl New for GlobalPropertiesData {
@@ -152,10 +184,13 @@ impl New for GlobalPropertiesData {
 }
 
 pub trait ContextGlobalPropertiesExt {
-    /// Set the value of a global property of type T
+    /// Set the value of a global property of type T. If the property already has a value, the
+    /// value is replaced, and a `GlobalPropertyChangeEvent<T>` is emitted if the new value is
+    /// different. Derived properties that depend on `T` are recomputed, and their indexes
+    /// rebuilt, the next time they are read or queried.
     ///
     /// # Errors
-    /// Will return an error if an attempt is made to change a value.
+    /// Will return an error if the value fails the property's validation.
     fn set_global_property_value<T: GlobalProperty + 'static>(
         &mut self,
         value: T,
@@ -189,7 +224,7 @@ pub trait ContextGlobalPropertiesExt {
     ///
     /// The expected structure is a dictionary with each name being
     /// the name of the struct prefixed with the crate name, as in:
-    /// `ixa.NumFluVariants` and the value being an object which can
+    /// `ixa_core.NumFluVariants` and the value being an object which can
     /// serde deserialize into the relevant struct.
     ///
     /// Ixa automatically knows about any property defined with
@@ -210,25 +245,20 @@ pub trait ContextGlobalPropertiesExt {
 }
 
 impl GlobalPropertiesData {
+    /// Stores `value`, returning the value it replaces, if any.
     fn set_global_property_value<T: GlobalProperty + 'static>(
         &mut self,
-        value: T,
-    ) -> Result<(), IxaError> {
-        match self.global_property_container.entry(TypeId::of::<T>()) {
-            Entry::Vacant(entry) => {
-                entry.insert(Box::new(value));
-                Ok(())
-            }
-            // Note: If we change global properties to be mutable, we'll need to
-            // update define_derived_property to either handle updates or only
-            // allow immutable properties.
-            Entry::Occupied(_) => Err(IxaError::from("Entry already exists")),
-        }
+        value: Rc<T>,
+    ) -> Option<Rc<T>> {
+        self.global_property_container
+            .insert(type_of::<T>(), value)
+            // Will never panic, as only an `Rc<T>` can be mapped to by `type_of::<T>()`.
+            .map(|previous| previous.downcast::<T>().unwrap())
     }
 
     #[must_use]
     fn get_global_property_value<T: GlobalProperty + 'static>(&self) -> Option<&T> {
-        let data_container = self.global_property_container.get(&TypeId::of::<T>());
+        let data_container = self.global_property_container.get(&type_of::<T>());
 
         match data_container {
             Some(property) => Some(property.downcast_ref::<T>().unwrap()),
@@ -243,8 +273,19 @@ impl ContextGlobalPropertiesExt for Context {
         value: T,
     ) -> Result<(), IxaError> {
         T::validate(&value)?;
+        let current = Rc::new(value);
         let data_container = self.get_data_container_mut::<GlobalPropertiesData>();
-        data_container.set_global_property_value(value)
+        if let Some(previous) = data_container.set_global_property_value(Rc::clone(&current)) {
+            // Global properties needn't implement `PartialEq`, so values are compared serialized.
+            let unchanged = matches!(
+                (serde_json::to_value(&*previous), serde_json::to_value(&*current)),
+                (Ok(previous), Ok(current)) if previous == current
+            );
+            if !unchanged {
+                self.emit_event(GlobalPropertyChangeEvent { previous, current });
+            }
+        }
+        Ok(())
     }
 
     #[allow(unused_variables)]
@@ -302,7 +343,10 @@ impl ContextGlobalPropertiesExt for Context {
 mod test {
     use super::*;
     use crate::context::Context;
+    use crate::define_derived_property;
+    use crate::entity::ContextEntityExt;
     use crate::error::IxaError;
+    use crate::property::Property;
     use serde::{Deserialize, Serialize};
     use std::path::PathBuf;
     use tempfile::tempdir;
@@ -339,18 +383,47 @@ mod test {
         assert_eq!(global_params.days, params.days);
         assert_eq!(global_params.diseases, params.diseases);
 
-        // Setting again should fail because global properties are immutable.
-        assert!(context
+        // Setting again replaces the value.
+        context
             .set_global_property_value::<DiseaseParams>(params2.clone())
-            .is_err());
-
-        // Check that the value is unchanged.
+            .unwrap();
         let global_params = context
             .get_global_property_value::<DiseaseParams>()
             .unwrap()
             .clone();
-        assert_eq!(global_params.days, params.days);
-        assert_eq!(global_params.diseases, params.diseases);
+        assert_eq!(global_params.days, params2.days);
+        assert_eq!(global_params.diseases, params2.diseases);
+    }
+
+    #[test]
+    fn global_property_change_event() {
+        let mut context = Context::new();
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let changes_clone = Rc::clone(&changes);
+        context.subscribe_to_event(
+            move |_, event: GlobalPropertyChangeEvent<DiseaseParams>| {
+                changes_clone
+                    .borrow_mut()
+                    .push((event.previous.days, event.current.days));
+            },
+        );
+
+        context
+            .set_global_property_value(DiseaseParams { days: 10, diseases: 2 })
+            .unwrap();
+        assert!(changes.borrow().is_empty());
+
+        context
+            .set_global_property_value(DiseaseParams { days: 12, diseases: 2 })
+            .unwrap();
+        context
+            .set_global_property_value(DiseaseParams { days: 14, diseases: 2 })
+            .unwrap();
+        // Setting the value it already has is not a change.
+        context
+            .set_global_property_value(DiseaseParams { days: 14, diseases: 2 })
+            .unwrap();
+        assert_eq!(*changes.borrow(), vec![(10, 12), (12, 14)]);
     }
 
     #[test]
@@ -426,7 +499,7 @@ mod test {
             .join("tests/data/global_properties_missing.json");
         match context.load_global_properties(&path) {
             Err(IxaError::IxaError(msg)) => {
-                assert_eq!(msg, "No global property: ixa.PropertyUnknown");
+                assert_eq!(msg, "No global property: ixa_core.PropertyUnknown");
             }
             _ => panic!("Unexpected error type"),
         }
@@ -512,7 +585,7 @@ mod test {
     fn list_registered_global_properties() {
         let context = Context::new();
         let properties = context.list_registered_global_properties();
-        assert!(properties.contains(&"ixa.DiseaseParams".to_string()));
+        assert!(properties.contains(&"ixa_core.DiseaseParams".to_string()));
     }
 
     #[test]
@@ -527,8 +600,90 @@ mod test {
             )
             .unwrap();
         let serialized = context
-            .get_serialized_value_by_string("ixa.DiseaseParams")
+            .get_serialized_value_by_string("ixa_core.DiseaseParams")
             .unwrap();
         assert_eq!(serialized, Some("{\"days\":10,\"diseases\":2}".to_string()));
     }
+
+    #[derive(Serialize, Deserialize, Debug, Copy, Clone, Default)]
+    pub struct SeniorAge(u8);
+    define_global_property!(SeniorAge);
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct Age(u8);
+    impl Property for Age {}
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct Senior(bool);
+    fn is_senior(age: Age, senior_age: SeniorAge) -> Option<Senior> {
+        Some(Senior(age.0 >= senior_age.0))
+    }
+    define_derived_property!(Senior, [Age], [SeniorAge], |age, senior_age| {
+        is_senior(age, senior_age)
+    });
+
+    #[test]
+    fn derived_property_with_global_dependency() {
+        let mut context = Context::new();
+        context
+            .set_global_property_value(SeniorAge(65))
+            .unwrap();
+        let young = context.add_entity(Age(64)).unwrap();
+        let old = context.add_entity(Age(88)).unwrap();
+
+        assert_eq!(context.get_property::<Senior>(young), Some(Senior(false)));
+        assert_eq!(context.get_property::<Senior>(old), Some(Senior(true)));
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    struct MemoizedSenior(bool);
+    crate::define_memoized_derived_property!(
+        MemoizedSenior,
+        [Age],
+        [SeniorAge],
+        |age, senior_age| is_senior(age, senior_age).map(|Senior(senior)| MemoizedSenior(senior))
+    );
+
+    #[test]
+    fn memoized_derived_property_with_global_dependency() {
+        let mut context = Context::new();
+        context.set_global_property_value(SeniorAge(65)).unwrap();
+        let entity_id = context.add_entity(Age(70)).unwrap();
+        assert_eq!(context.get_property::<MemoizedSenior>(entity_id), Some(MemoizedSenior(true)));
+
+        // Changing the global property clears the cached value.
+        context.set_global_property_value(SeniorAge(75)).unwrap();
+        assert_eq!(
+            context.get_property::<MemoizedSenior>(entity_id),
+            Some(MemoizedSenior(false))
+        );
+    }
+
+    #[test]
+    fn indexed_derived_property_with_global_dependency() {
+        let mut context = Context::new();
+        context.set_global_property_value(SeniorAge(65)).unwrap();
+        context.index_property::<Senior>();
+        context.index_property::<MemoizedSenior>();
+        let young = context.add_entity(Age(64)).unwrap();
+        let old = context.add_entity(Age(70)).unwrap();
+        assert_eq!(context.query_entities(Senior(true)), vec![old]);
+        assert_eq!(context.query_entities(MemoizedSenior(true)), vec![old]);
+
+        // Changing the global property rebuilds the indexes.
+        context.set_global_property_value(SeniorAge(60)).unwrap();
+        assert_eq!(context.query_entities(Senior(true)), vec![young, old]);
+        assert_eq!(context.query_entities(MemoizedSenior(true)), vec![young, old]);
+        context.set_global_property_value(SeniorAge(75)).unwrap();
+        assert!(context.query_entities(Senior(true)).is_empty());
+        assert_eq!(context.query_entity_count(MemoizedSenior(false)), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Global property SeniorAge not initialized")]
+    fn derived_property_with_unset_global_dependency() {
+        let mut context = Context::new();
+        let entity_id = context.add_entity(Age(64)).unwrap();
+        context.get_property::<Senior>(entity_id);
+    }
 }
@@ -12,23 +12,64 @@
 //! `HashMapExt` trait extension. Similarly, for `HashSet` and `HashSetExt`.The traits need only be
 //! in scope.
 //!
-//! The `hash_usize` free function is a convenience function used in `crate::random::get_rng`.
+//! The hasher starts from a fixed seed, zero by default, so the iteration order of a map or set
+//! is the same on every run. `HashMapExt::with_seed` and `HashSetExt::with_seed` create a map or
+//! set with a different seed.
+//!
+//! The `hash_str` free function is the hash `ContextRandomExt` uses to derive the seed of each
+//! random number generator stream from the base seed.
+
+use rustc_hash::FxHasher;
+use std::hash::{BuildHasher, Hasher};
+
+/// Builds `FxHasher`s that start from a fixed seed. Unlike the standard library's `RandomState`,
+/// the seed is never random, so the iteration order of a `HashMap` or `HashSet` depends only on
+/// its seed and the sequence of insertions and removals. The default seed is zero.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SeededState {
+    seed: usize,
+}
+
+impl SeededState {
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        // Truncated on 32-bit hosts.
+        SeededState { seed: seed as usize }
+    }
+}
 
-pub use rustc_hash::{
-    FxHashMap as HashMap,
-    FxHashSet as HashSet
-};
-use std::hash::Hasher;
+impl BuildHasher for SeededState {
+    type Hasher = FxHasher;
+
+    fn build_hasher(&self) -> FxHasher {
+        FxHasher::with_seed(self.seed)
+    }
+}
+
+pub type HashMap<K, V> = std::collections::HashMap<K, V, SeededState>;
+pub type HashSet<T> = std::collections::HashSet<T, SeededState>;
 
 /// Provides API parity with `std::collections::HashMap`.
 pub trait HashMapExt {
     fn new() -> Self;
+    /// Creates an empty map with space for at least `capacity` entries.
+    fn with_capacity(capacity: usize) -> Self;
+    /// Creates an empty map whose hasher starts from `seed`.
+    fn with_seed(seed: u64) -> Self;
 }
 
 impl<K, V> HashMapExt for HashMap<K, V> {
     fn new() -> Self {
         HashMap::default()
     }
+
+    fn with_capacity(capacity: usize) -> Self {
+        HashMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    fn with_seed(seed: u64) -> Self {
+        HashMap::with_hasher(SeededState::with_seed(seed))
+    }
 }
 
 // Note that trait aliases are not yet stabilized in rustc.
@@ -36,17 +77,74 @@ impl<K, V> HashMapExt for HashMap<K, V> {
 /// Provides API parity with `std::collections::HashSet`.
 pub trait HashSetExt {
     fn new() -> Self;
+    /// Creates an empty set with space for at least `capacity` values.
+    fn with_capacity(capacity: usize) -> Self;
+    /// Creates an empty set whose hasher starts from `seed`.
+    fn with_seed(seed: u64) -> Self;
 }
 
 impl<T> HashSetExt for HashSet<T> {
     fn new() -> Self {
         HashSet::default()
     }
+
+    fn with_capacity(capacity: usize) -> Self {
+        HashSet::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    fn with_seed(seed: u64) -> Self {
+        HashSet::with_hasher(SeededState::with_seed(seed))
+    }
 }
 
-/// A convenience method to compute the hash of a `&str`.
+/// Computes the deterministic hash of a `&str`. The seed of each random number generator stream
+/// is `base_seed.wrapping_add(hash_str(name))`, where `name` is the name given to `define_rng!`,
+/// so a stream's seed can be reproduced outside the simulation. The value for a given string is
+/// stable across runs, platforms, and releases.
+#[must_use]
 pub fn hash_str(data: &str) -> u64 {
-    let mut hasher = rustc_hash::FxHasher::default();
+    let mut hasher = FxHasher::default();
     hasher.write(data.as_bytes());
     hasher.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_capacity() {
+        let map: HashMap<u32, u32> = HashMapExt::with_capacity(100);
+        assert!(map.capacity() >= 100);
+        assert!(map.is_empty());
+
+        let set: HashSet<u32> = HashSetExt::with_capacity(100);
+        assert!(set.capacity() >= 100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn with_seed_iteration_order() {
+        let build = |seed: u64| {
+            let mut map: HashMap<u64, u64> = HashMapExt::with_seed(seed);
+            let mut set: HashSet<u64> = HashSetExt::with_seed(seed);
+            for i in 0..1000 {
+                map.insert(i * 7919, i);
+                set.insert(i * 104_729);
+            }
+            (
+                map.into_iter().collect::<Vec<_>>(),
+                set.into_iter().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(build(42), build(42));
+        assert_eq!(build(0), build(0));
+    }
+
+    #[test]
+    fn hash_str_is_stable() {
+        assert_eq!(hash_str(""), 17_606_491_139_363_777_937);
+        assert_eq!(hash_str("FooRng"), 12_356_894_620_736_791_405);
+        assert_eq!(hash_str("ixa"), 16_023_445_591_045_750_102);
+    }
+}
@@ -4,17 +4,26 @@ pub mod any_map;
 mod context;
 mod new_trait;
 mod entity;
+mod event;
 mod property;
 mod property_map;
 mod error;
+mod plans;
 mod random;
-mod hashing;
+mod report;
+mod summary;
+pub mod hashing;
 pub mod log;
 mod trait_map;
-mod global_properties;
+pub mod global_properties;
+pub mod snapshot;
+pub mod checkpoint;
 
 // Re-exports
 pub use rand;
+pub use rand_chacha;
+pub use serde_json;
+pub use csv;
 pub use paste;
 pub use ctor;
 
@@ -23,11 +32,31 @@ pub use std::any::TypeId;
 // pub(crate) use typeid::of as type_of;
 pub use new_trait::New;
 
+pub use checkpoint::{Checkpoint, Checkpointable, ContextCheckpointExt};
 pub use context::Context;
 pub use error::IxaError;
-pub use entity::ContextEntityExt;
+pub use entity::{
+    ContextEntityExt,
+    DefaultEntity,
+    EntityBuilder,
+    EntityCreatedEvent,
+    EntityKind,
+    IndexValue,
+    OrderedF64,
+    PropertyChangeEvent,
+    QueryCompare,
+};
+pub use event::{ContextEventExt, SubscriptionId};
+pub use global_properties::{
+    ContextGlobalPropertiesExt,
+    GlobalProperty,
+    GlobalPropertyChangeEvent,
+};
 pub use property::Property;
+pub use plans::{ContextPlanExt, PlanId};
 pub use random::{ContextRandomExt, RngId};
+pub use report::{ConfigReportOptions, ContextReportExt, Report};
+pub use summary::{ContextSummaryExt, Summary};
 pub use log::{debug, error, info, trace, warn};
 pub use hashing::{HashMap, HashMapExt, HashSet, HashSetExt};
 
@@ -40,3 +69,12 @@ pub fn type_of<T: 'static>() -> TypeId {
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct EntityId(pub(crate) usize);
 
+impl EntityId {
+    /// The position of the entity among the entities of its kind, for indexing external arrays
+    /// by entity. Use `ContextEntityExt::entity_id_from_index()` to get the id back.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
@@ -19,6 +19,8 @@
 //! option `--log-level <level>`. Log messages can also be controlled programmatically. Logging
 //! can be enabled/disabled from code using the functions:
 //!
+//!  - `init_logging(level: LevelFilter)`: enables log messages with priority at least `level` and
+//!    resets per-module filters to the defaults
 //!  - `enable_logging()`: turns on all log messages
 //!  - `disable_logging()`: turns off all log messages
 //!  - `set_log_level(level: LevelFilter)`: enables only log messages with priority at least `level`
@@ -216,6 +218,15 @@ impl LogConfiguration {
     }
 }
 
+/// Initializes logging with `level` as the global level filter and the default module filters,
+/// replacing any filters set earlier. Per-module filters can then be added with
+/// `set_module_filter()`.
+pub fn init_logging(level: LevelFilter) {
+    let mut log_configuration = get_log_configuration();
+    log_configuration.module_configurations = LogConfiguration::default().module_configurations;
+    log_configuration.set_log_level(level);
+}
+
 /// Enables the logger with no global level filter / full logging. Equivalent to
 /// `set_log_level(LevelFilter::Trace)`.
 pub fn enable_logging() {
@@ -261,9 +272,16 @@ fn get_log_configuration() -> MutexGuard<'static, LogConfiguration> {
 
 #[cfg(test)]
 mod tests {
-    use super::{get_log_configuration, remove_module_filter, set_log_level, set_module_filters};
+    use super::{
+        get_log_configuration,
+        init_logging,
+        remove_module_filter,
+        set_log_level,
+        set_module_filter,
+        set_module_filters,
+    };
     // use crate::tests::run_external_runner;
-    use log::{error, trace, LevelFilter};
+    use log::{error, log_enabled, trace, Level, LevelFilter};
     use std::sync::{LazyLock, Mutex};
 
     // Force logging tests to run serially for consistent behavior.
@@ -352,4 +370,25 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn module_filter_drops_records() {
+        let _guard = TEST_MUTEX.lock().expect("Mutex poisoned");
+        init_logging(LevelFilter::Info);
+        set_module_filter("ixa_core::random", LevelFilter::Warn);
+        set_module_filter("ixa_core::entity::query", LevelFilter::Trace);
+
+        assert!(!log_enabled!(target: "ixa_core::random", Level::Info));
+        assert!(log_enabled!(target: "ixa_core::random", Level::Warn));
+        assert!(log_enabled!(target: "ixa_core::entity::query", Level::Trace));
+        // Modules without a filter use the global level.
+        assert!(log_enabled!(target: "ixa_core::plans", Level::Info));
+        assert!(!log_enabled!(target: "ixa_core::plans", Level::Debug));
+
+        // Initializing again discards the filters.
+        init_logging(LevelFilter::Info);
+        assert!(log_enabled!(target: "ixa_core::random", Level::Info));
+        assert!(!log_enabled!(target: "ixa_core::entity::query", Level::Trace));
+        assert_eq!(get_log_configuration().module_configurations.len(), 1);
+    }
 }
@@ -65,6 +65,10 @@ pub trait New: Any + 'static {
     /// A constant reference to a constructor
     #[allow(non_upper_case_globals)]
     const new: &'static dyn Fn() -> Self;
+
+    /// Called on a data container when the `Context` that owns it is dropped. Does nothing by
+    /// default.
+    fn on_context_drop(&mut self) {}
 }
 
 // This is how you would implement this for your types.
@@ -84,8 +88,8 @@ mod tests {
 
     #[test]
     fn test_new_vec() {
-        let mut str_vec = Vec::<&str>::new();
-        str_vec.push(&"duck");
+        let mut str_vec = <Vec<&str> as New>::new();
+        str_vec.push("duck");
     }
 
     #[test]
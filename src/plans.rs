@@ -0,0 +1,699 @@
+//! A discrete-event scheduler. A _plan_ is a callback that is scheduled to run at a given
+//! simulation time. Plans are added with [`ContextPlanExt::add_plan()`] and run in time order by
+//! [`ContextPlanExt::execute()`], which advances the simulation clock to the time of each plan
+//! as it runs it. Execution continues until there are no plans left or a plan calls
+//! [`ContextPlanExt::shutdown()`]. [`ContextPlanExt::add_plan_in()`] schedules a plan a given
+//! delay after the current time instead of at an absolute time.
+//!
+//! Plans scheduled for exactly the same time run in order of priority, lowest first, and then in
+//! the order they were added, so a simulation with a fixed random seed always executes its plans
+//! in the same order. Plans added with `add_plan()` have priority 0; use
+//! [`ContextPlanExt::add_plan_with_priority()`] to run a plan before or after the others at its
+//! time.
+//!
+//! A _periodic plan_, added with [`ContextPlanExt::add_periodic_plan()`], reschedules itself each
+//! time it runs until it is canceled. Because the queue is never empty while a periodic plan is
+//! active, a simulation that uses one must call `shutdown()` to end.
+
+use crate::{
+    context::{Context, DataPlugin},
+    report::ContextReportExt,
+    trace,
+    HashMap,
+    HashSet,
+};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    rc::Rc,
+};
+
+type Callback = dyn FnOnce(&mut Context);
+type PeriodicCallback = Rc<dyn Fn(&mut Context)>;
+
+/// A handle to a scheduled plan, used to cancel it.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct PlanId(u64);
+
+/// An entry in the plan queue. Sequence numbers are handed out in increasing order as entries are
+/// pushed, so the sequence number breaks ties between plans with equal times and priorities. A
+/// periodic plan keeps its `PlanId` across recurrences but gets a new sequence number each time.
+#[derive(Copy, Clone, Debug)]
+struct PlanEntry {
+    time: f64,
+    priority: i64,
+    sequence: u64,
+    plan_id: PlanId,
+}
+
+// `BinaryHeap` is a max-heap, so entries are ordered in reverse: the entry with the earliest time
+// (and the lowest priority, then the lowest sequence number, among equal times) is the greatest.
+impl Ord for PlanEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .time
+            .total_cmp(&self.time)
+            .then_with(|| other.priority.cmp(&self.priority))
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for PlanEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PlanEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PlanEntry {}
+
+pub(crate) struct PlanData {
+    current_time: f64,
+    shutdown_requested: bool,
+    next_plan_id: u64,
+    next_sequence: u64,
+    queue: BinaryHeap<PlanEntry>,
+    /// The callbacks of plans that are still scheduled. Canceling a plan removes its callback, and
+    /// entries without a callback are discarded once they reach the top of `queue`.
+    callbacks: HashMap<PlanId, Box<Callback>>,
+    /// The periodic plans that haven't been canceled. A periodic plan only reschedules itself if
+    /// it is still in this set, which lets a plan cancel itself while it runs.
+    periodic_plans: HashSet<PlanId>,
+    /// How many plans `execute()` has run, counting each recurrence of a periodic plan.
+    plans_executed: usize,
+}
+
+impl DataPlugin for PlanData {
+    #[allow(non_upper_case_globals)]
+    const new: &'static dyn Fn() -> Self = &|| {
+        PlanData {
+            current_time: 0.0,
+            shutdown_requested: false,
+            next_plan_id: 0,
+            next_sequence: 0,
+            queue: BinaryHeap::new(),
+            callbacks: HashMap::default(),
+            periodic_plans: HashSet::default(),
+            plans_executed: 0,
+        }
+    };
+}
+
+impl PlanData {
+    fn next_plan_id(&mut self) -> PlanId {
+        let plan_id = PlanId(self.next_plan_id);
+        self.next_plan_id += 1;
+        plan_id
+    }
+
+    /// Queues `callback` to run at `time` with the given priority under the given `PlanId`.
+    fn schedule(&mut self, plan_id: PlanId, time: f64, priority: i64, callback: Box<Callback>) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.queue.push(PlanEntry { time, priority, sequence, plan_id });
+        self.callbacks.insert(plan_id, callback);
+    }
+
+    /// Pops the next plan that hasn't been canceled, returning its time and callback.
+    fn pop_plan(&mut self) -> Option<(f64, Box<Callback>)> {
+        let entry = self.queue.pop()?;
+        // The top of the queue is never canceled, so the callback is present.
+        let callback = self.callbacks.remove(&entry.plan_id).unwrap();
+        self.discard_canceled();
+        Some((entry.time, callback))
+    }
+
+    /// Pops canceled entries off the top of the queue, so that the top entry, if any, is the next
+    /// plan that will run.
+    fn discard_canceled(&mut self) {
+        while let Some(entry) = self.queue.peek() {
+            if self.callbacks.contains_key(&entry.plan_id) {
+                break;
+            }
+            self.queue.pop();
+        }
+    }
+
+    /// Returns the time of the next plan that will run, if any.
+    fn next_plan_time(&self) -> Option<f64> {
+        self.queue.peek().map(|entry| entry.time)
+    }
+
+    /// The number of plans that are scheduled and haven't been canceled.
+    pub(crate) fn pending_plan_count(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    /// Discards every scheduled plan and sets the clock and the count of executed plans, as
+    /// when restoring a checkpoint. Plan ids keep increasing, so ids handed out before the reset
+    /// never refer to plans added after it.
+    pub(crate) fn reset(&mut self, current_time: f64, plans_executed: usize) {
+        self.current_time = current_time;
+        self.plans_executed = plans_executed;
+        self.shutdown_requested = false;
+        self.queue.clear();
+        self.callbacks.clear();
+        self.periodic_plans.clear();
+    }
+}
+
+pub trait ContextPlanExt {
+    /// Schedules `callback` to run at simulation time `time`. Returns a `PlanId` that can be
+    /// passed to `cancel_plan()` to keep the plan from running. Plans with equal times run in
+    /// the order they were added.
+    ///
+    /// # Panics
+    /// Panics if `time` is NaN, infinite, or earlier than the current time.
+    fn add_plan(&mut self, time: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId;
+
+    /// Like `add_plan()`, but schedules `callback` to run `delay` after the current time rather
+    /// than at an absolute time.
+    ///
+    /// # Panics
+    /// Panics if `delay` is NaN, infinite, or negative.
+    fn add_plan_in(&mut self, delay: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId;
+
+    /// Like `add_plan()`, but among plans scheduled for the same time, plans with lower
+    /// `priority` run first, and plans with equal priorities run in the order they were added.
+    /// `add_plan()` and periodic plans use priority 0.
+    ///
+    /// # Panics
+    /// Panics if `time` is NaN, infinite, or earlier than the current time.
+    fn add_plan_with_priority(
+        &mut self,
+        time: f64,
+        priority: i64,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> PlanId;
+
+    /// Schedules `callback` to run at the current time and then every `period` after that until
+    /// the plan is canceled or the simulation shuts down. Returns a `PlanId` that can be passed
+    /// to `cancel_plan()` to stop all future recurrences.
+    ///
+    /// # Panics
+    /// Panics if `period` is not a positive, finite number.
+    fn add_periodic_plan(
+        &mut self,
+        period: f64,
+        callback: impl Fn(&mut Context) + 'static,
+    ) -> PlanId;
+
+    /// Cancels the plan with the given `PlanId` so that it never runs. Canceling a plan that has
+    /// already run or been canceled does nothing.
+    fn cancel_plan(&mut self, plan_id: PlanId);
+
+    /// Runs plans in time order until there are no plans left or `shutdown()` is called, then
+    /// flushes all reports.
+    fn execute(&mut self);
+
+    /// Like `execute()`, but only runs plans scheduled before `max_time`, so a simulation can
+    /// end at a fixed time without a shutdown plan. Plans scheduled at or after `max_time`,
+    /// including recurrences of periodic plans, stay in the queue, and the clock is left at the
+    /// time of the last plan that ran. Reports are flushed when it returns.
+    fn run_until(&mut self, max_time: f64);
+
+    /// Stops `execute()` once the currently running plan returns. Plans that have not run yet
+    /// remain in the queue and run on the next call to `execute()` or `run_until()`.
+    fn shutdown(&mut self);
+
+    /// Returns the current simulation time, which is the time of the plan that is currently
+    /// running (or that ran most recently).
+    fn get_current_time(&self) -> f64;
+
+    /// Returns how much simulation time remains until the next plan runs, or `None` if no plans
+    /// are scheduled. A continuous model coupled to the simulation can integrate this far before
+    /// the next discrete event.
+    fn time_to_next_event(&self) -> Option<f64>;
+
+    /// Returns the time of the next plan that will run, or `None` if no plans are scheduled.
+    /// Canceled plans are skipped, and the plan is left in the queue.
+    fn peek_next_plan_time(&self) -> Option<f64>;
+
+    /// Returns how many plans have run so far, counting each recurrence of a periodic plan.
+    fn plans_executed(&self) -> usize;
+}
+
+impl ContextPlanExt for Context {
+    fn add_plan(&mut self, time: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId {
+        self.add_plan_with_priority(time, 0, callback)
+    }
+
+    fn add_plan_in(&mut self, delay: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId {
+        assert!(delay.is_finite() && delay >= 0.0, "Delay is invalid: {delay}");
+        let time = self.get_current_time() + delay;
+        self.add_plan(time, callback)
+    }
+
+    fn add_plan_with_priority(
+        &mut self,
+        time: f64,
+        priority: i64,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> PlanId {
+        let plan_data = self.get_data_container_mut::<PlanData>();
+        assert!(
+            time.is_finite() && time >= plan_data.current_time,
+            "Time is invalid: {time}"
+        );
+
+        let plan_id = plan_data.next_plan_id();
+        plan_data.schedule(plan_id, time, priority, Box::new(callback));
+        plan_id
+    }
+
+    fn add_periodic_plan(
+        &mut self,
+        period: f64,
+        callback: impl Fn(&mut Context) + 'static,
+    ) -> PlanId {
+        assert!(
+            period.is_finite() && period > 0.0,
+            "Period is invalid: {period}"
+        );
+
+        let plan_data = self.get_data_container_mut::<PlanData>();
+        let plan_id = plan_data.next_plan_id();
+        plan_data.periodic_plans.insert(plan_id);
+        let time = plan_data.current_time;
+        schedule_recurrence(self, plan_id, time, period, Rc::new(callback));
+        plan_id
+    }
+
+    fn cancel_plan(&mut self, plan_id: PlanId) {
+        let plan_data = self.get_data_container_mut::<PlanData>();
+        plan_data.callbacks.remove(&plan_id);
+        plan_data.periodic_plans.remove(&plan_id);
+        plan_data.discard_canceled();
+    }
+
+    fn execute(&mut self) {
+        run_plans(self, None);
+    }
+
+    fn run_until(&mut self, max_time: f64) {
+        run_plans(self, Some(max_time));
+    }
+
+    fn shutdown(&mut self) {
+        trace!("shutdown requested");
+        self.get_data_container_mut::<PlanData>().shutdown_requested = true;
+    }
+
+    fn get_current_time(&self) -> f64 {
+        self.get_data_container::<PlanData>()
+            .map_or(0.0, |plan_data| plan_data.current_time)
+    }
+
+    fn time_to_next_event(&self) -> Option<f64> {
+        let plan_data = self.get_data_container::<PlanData>()?;
+        plan_data
+            .next_plan_time()
+            .map(|time| time - plan_data.current_time)
+    }
+
+    fn peek_next_plan_time(&self) -> Option<f64> {
+        self.get_data_container::<PlanData>()?.next_plan_time()
+    }
+
+    fn plans_executed(&self) -> usize {
+        self.get_data_container::<PlanData>()
+            .map_or(0, |plan_data| plan_data.plans_executed)
+    }
+}
+
+/// Runs plans in time order until there are no plans left, `shutdown()` is called, or the next
+/// plan is scheduled at or after `max_time`, then flushes all reports.
+fn run_plans(context: &mut Context, max_time: Option<f64>) {
+    trace!("entering event loop");
+    loop {
+        let plan_data = context.get_data_container_mut::<PlanData>();
+        if plan_data.shutdown_requested {
+            break;
+        }
+        let next_time = plan_data.next_plan_time();
+        if max_time.is_some_and(|max_time| next_time.is_some_and(|time| time >= max_time)) {
+            break;
+        }
+        let Some((time, callback)) = plan_data.pop_plan() else {
+            break;
+        };
+        plan_data.current_time = time;
+        plan_data.plans_executed += 1;
+        callback(context);
+    }
+    // A shutdown only ends the current run, so that a later run executes the remaining plans.
+    context.get_data_container_mut::<PlanData>().shutdown_requested = false;
+    context.flush_reports();
+}
+
+/// Schedules one run of a periodic plan at `time`. After the callback runs, the next run is
+/// scheduled `period` later unless the plan was canceled in the meantime.
+fn schedule_recurrence(
+    context: &mut Context,
+    plan_id: PlanId,
+    time: f64,
+    period: f64,
+    callback: PeriodicCallback,
+) {
+    let recurrence = Box::new(move |context: &mut Context| {
+        callback(context);
+        let plan_data = context.get_data_container_mut::<PlanData>();
+        if plan_data.periodic_plans.contains(&plan_id) {
+            let next_time = plan_data.current_time + period;
+            schedule_recurrence(context, plan_id, next_time, period, callback);
+        }
+    });
+    context
+        .get_data_container_mut::<PlanData>()
+        .schedule(plan_id, time, 0, recurrence);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn plans_executed() {
+        let mut context = Context::new();
+        assert_eq!(context.plans_executed(), 0);
+        context.add_plan(1.0, |_| {});
+        let canceled = context.add_plan(2.0, |_| {});
+        context.add_plan(3.0, |context| {
+            context.add_plan(4.0, |_| {});
+        });
+        context.cancel_plan(canceled);
+        context.execute();
+        assert_eq!(context.plans_executed(), 3);
+    }
+
+    #[test]
+    fn plans_run_in_time_order() {
+        let mut context = Context::new();
+        let times = Rc::new(RefCell::new(Vec::new()));
+
+        for time in [3.0, 1.0, 2.0] {
+            let times = times.clone();
+            context.add_plan(time, move |context| {
+                times.borrow_mut().push(context.get_current_time());
+            });
+        }
+        context.execute();
+
+        assert_eq!(*times.borrow(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(context.get_current_time(), 3.0);
+    }
+
+    #[test]
+    fn equal_times_run_in_insertion_order() {
+        let mut context = Context::new();
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        for index in 0..100 {
+            let order = order.clone();
+            context.add_plan(1.0, move |_| order.borrow_mut().push(index));
+        }
+        // A plan added at the same time from inside a plan runs after the ones already queued.
+        let order_clone = order.clone();
+        context.add_plan(0.5, move |context| {
+            context.add_plan(1.0, move |_| order_clone.borrow_mut().push(100));
+        });
+        context.execute();
+
+        assert_eq!(*order.borrow(), (0..=100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn current_time_starts_at_zero() {
+        let context = Context::new();
+        assert_eq!(context.get_current_time(), 0.0);
+    }
+
+    #[test]
+    fn plan_can_schedule_plan() {
+        let mut context = Context::new();
+        let times = Rc::new(RefCell::new(Vec::new()));
+
+        let times_clone = times.clone();
+        context.add_plan(1.0, move |context| {
+            times_clone.borrow_mut().push(context.get_current_time());
+            let times_clone = times_clone.clone();
+            context.add_plan(2.5, move |context| {
+                times_clone.borrow_mut().push(context.get_current_time());
+            });
+        });
+        context.execute();
+
+        assert_eq!(*times.borrow(), vec![1.0, 2.5]);
+    }
+
+    #[test]
+    fn plan_priority() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let record = |name: &'static str| {
+            let ran = ran.clone();
+            move |_: &mut Context| ran.borrow_mut().push(name)
+        };
+        context.add_plan(1.0, record("default"));
+        context.add_plan_with_priority(1.0, 10, record("report"));
+        context.add_plan_with_priority(1.0, -5, record("recover"));
+        context.add_plan_with_priority(0.5, 10, record("earlier"));
+        context.add_plan_with_priority(1.0, -5, record("recover again"));
+        context.add_plan_with_priority(1.0, 0, record("default again"));
+        context.execute();
+
+        assert_eq!(
+            *ran.borrow(),
+            vec!["earlier", "recover", "recover again", "default", "default again", "report"]
+        );
+    }
+
+    #[test]
+    fn run_until() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        for time in [1.0, 5.0, 7.0, 10.0] {
+            let ran = ran.clone();
+            context.add_plan(time, move |_| ran.borrow_mut().push(time));
+        }
+
+        context.run_until(7.0);
+        assert_eq!(*ran.borrow(), vec![1.0, 5.0]);
+        assert_eq!(context.get_current_time(), 5.0);
+        assert_eq!(context.time_to_next_event(), Some(2.0));
+
+        // The remaining plans run on the next call.
+        context.execute();
+        assert_eq!(*ran.borrow(), vec![1.0, 5.0, 7.0, 10.0]);
+    }
+
+    #[test]
+    fn shutdown_stops_execution() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+
+        let ran_clone = ran.clone();
+        context.add_plan(1.0, move |context| {
+            ran_clone.borrow_mut().push(1.0);
+            context.shutdown();
+        });
+        let ran_clone = ran.clone();
+        context.add_plan(2.0, move |_| ran_clone.borrow_mut().push(2.0));
+        context.execute();
+
+        assert_eq!(*ran.borrow(), vec![1.0]);
+        assert_eq!(context.get_current_time(), 1.0);
+    }
+
+    #[test]
+    fn execute_after_shutdown() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+
+        context.add_plan(1.0, |context| context.shutdown());
+        context.execute();
+        assert_eq!(context.get_current_time(), 1.0);
+
+        let ran_clone = ran.clone();
+        context.add_plan(2.0, move |_| ran_clone.borrow_mut().push(2.0));
+        context.execute();
+
+        assert_eq!(*ran.borrow(), vec![2.0]);
+        assert_eq!(context.get_current_time(), 2.0);
+    }
+
+    #[test]
+    fn cancel_plan() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+
+        let ran_clone = ran.clone();
+        let canceled = context.add_plan(1.0, move |_| ran_clone.borrow_mut().push("canceled"));
+        let ran_clone = ran.clone();
+        context.add_plan(2.0, move |_| ran_clone.borrow_mut().push("survivor"));
+        context.cancel_plan(canceled);
+        context.execute();
+
+        assert_eq!(*ran.borrow(), vec!["survivor"]);
+        assert_eq!(context.get_current_time(), 2.0);
+    }
+
+    #[test]
+    fn cancel_plan_from_plan() {
+        let mut context = Context::new();
+        let ran = Rc::new(RefCell::new(false));
+
+        let ran_clone = ran.clone();
+        let recovery = context.add_plan(5.0, move |_| *ran_clone.borrow_mut() = true);
+        context.add_plan(1.0, move |context| context.cancel_plan(recovery));
+        context.execute();
+
+        assert!(!*ran.borrow());
+        // Canceling again is harmless.
+        context.cancel_plan(recovery);
+    }
+
+    #[test]
+    fn periodic_plan_runs_until_shutdown() {
+        const MAX_TIME: f64 = 10.0;
+        let mut context = Context::new();
+        let times = Rc::new(RefCell::new(Vec::new()));
+
+        let times_clone = times.clone();
+        context.add_periodic_plan(2.0, move |context| {
+            times_clone.borrow_mut().push(context.get_current_time());
+        });
+        context.add_plan(MAX_TIME, |context| context.shutdown());
+        context.execute();
+
+        // The shutdown plan was added before the recurrence at `MAX_TIME`, so it runs first.
+        assert_eq!(*times.borrow(), vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(context.get_current_time(), MAX_TIME);
+    }
+
+    #[test]
+    fn cancel_periodic_plan() {
+        let mut context = Context::new();
+        let count = Rc::new(RefCell::new(0));
+
+        let count_clone = count.clone();
+        let plan_id = context.add_periodic_plan(1.0, move |_| *count_clone.borrow_mut() += 1);
+        context.add_plan(2.5, move |context| context.cancel_plan(plan_id));
+        context.execute();
+
+        // Runs at 0, 1, and 2; the recurrence queued for 3 is skipped.
+        assert_eq!(*count.borrow(), 3);
+        assert_eq!(context.get_current_time(), 2.5);
+    }
+
+    #[test]
+    fn periodic_plan_cancels_itself() {
+        let mut context = Context::new();
+        let count = Rc::new(RefCell::new(0));
+        let own_id: Rc<RefCell<Option<PlanId>>> = Rc::new(RefCell::new(None));
+
+        let count_clone = count.clone();
+        let own_id_clone = own_id.clone();
+        let plan_id = context.add_periodic_plan(1.0, move |context| {
+            *count_clone.borrow_mut() += 1;
+            if *count_clone.borrow() == 2 {
+                context.cancel_plan(own_id_clone.borrow().unwrap());
+            }
+        });
+        *own_id.borrow_mut() = Some(plan_id);
+        context.execute();
+
+        assert_eq!(*count.borrow(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Period is invalid")]
+    fn add_periodic_plan_zero_period() {
+        let mut context = Context::new();
+        context.add_periodic_plan(0.0, |_| {});
+    }
+
+    #[test]
+    fn time_to_next_event() {
+        let mut context = Context::new();
+        assert_eq!(context.time_to_next_event(), None);
+
+        let canceled = context.add_plan(1.0, |_| {});
+        context.add_plan(2.5, |context| {
+            assert_eq!(context.time_to_next_event(), Some(1.5));
+        });
+        context.add_plan(4.0, |context| {
+            assert_eq!(context.time_to_next_event(), None);
+        });
+        assert_eq!(context.time_to_next_event(), Some(1.0));
+
+        // Canceled plans don't count as the next event.
+        context.cancel_plan(canceled);
+        assert_eq!(context.time_to_next_event(), Some(2.5));
+
+        context.execute();
+        assert_eq!(context.time_to_next_event(), None);
+    }
+
+    #[test]
+    fn peek_next_plan_time() {
+        let mut context = Context::new();
+        assert_eq!(context.peek_next_plan_time(), None);
+
+        context.add_plan(3.0, |_| {});
+        let canceled = context.add_plan(1.0, |_| {});
+        context.add_plan(1.0, |_| {});
+        assert_eq!(context.peek_next_plan_time(), Some(1.0));
+
+        // Peeking doesn't remove the plan, and canceled plans are skipped.
+        context.cancel_plan(canceled);
+        assert_eq!(context.peek_next_plan_time(), Some(1.0));
+        context.run_until(2.0);
+        assert_eq!(context.plans_executed(), 1);
+        assert_eq!(context.peek_next_plan_time(), Some(3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Time is invalid")]
+    fn add_plan_in_past() {
+        let mut context = Context::new();
+        context.add_plan(2.0, |context| {
+            context.add_plan(1.0, |_| {});
+        });
+        context.execute();
+    }
+
+    #[test]
+    fn add_plan_in_is_relative_to_current_time() {
+        let mut context = Context::new();
+        let times = Rc::new(RefCell::new(Vec::new()));
+        let times_clone = times.clone();
+        context.add_plan(2.0, move |context| {
+            context.add_plan_in(1.5, move |context| {
+                times_clone.borrow_mut().push(context.get_current_time());
+            });
+        });
+        context.execute();
+        assert_eq!(*times.borrow(), vec![3.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Delay is invalid")]
+    fn add_plan_in_negative_delay() {
+        let mut context = Context::new();
+        context.add_plan_in(-1.0, |_| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Time is invalid")]
+    fn add_plan_nan() {
+        let mut context = Context::new();
+        context.add_plan(f64::NAN, |_| {});
+    }
+}
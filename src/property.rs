@@ -1,7 +1,8 @@
 use crate::{
     context::Context,
     EntityId,
-    entity::ContextEntityExtInternal,
+    IxaError,
+    entity::{ContextEntityExtInternal, DefaultEntity, EntityKind},
     TypeId,
     type_of,
 };
@@ -53,20 +54,60 @@ pub trait Property: Clone + Debug + PartialEq + Hash + 'static {
         type_name::<Self>()
     }
 
+    /// If `true`, every entity must be given a value for the property when it is created, and
+    /// `add_entity()` returns `IxaError::MissingRequiredProperty` otherwise. The requirement is
+    /// enforced once the property is registered with the context, so register a required
+    /// property during setup with `Property::register`, before any entity is added. Use
+    /// `define_required_property!` to implement `Property` with this set.
     #[must_use]
     #[inline]
     fn is_required() -> bool {
         false
     }
 
-    /// Overridden by `DerivedProperty`s, because they also need to register dependencies.
+    /// If `true`, the property is indexed as soon as it is registered, and new entities are added
+    /// to the index as they are created instead of on the next query.
+    #[must_use]
+    #[inline]
+    fn is_indexed() -> bool {
+        false
+    }
+
+    /// If `true`, the value computed for each entity is cached until one of the property's
+    /// dependencies is set. Only meaningful for derived properties; see
+    /// `define_memoized_derived_property!`.
+    #[must_use]
+    #[inline]
+    fn is_memoized() -> bool {
+        false
+    }
+
+    /// Registers the property for `DefaultEntity`.
     #[inline]
     fn register(context: &mut Context) {
-        if !context.is_registered::<Self>(){
-            context.register_nonderived_property::<Self>();
+        Self::register_for::<DefaultEntity>(context);
+    }
+
+    /// Registers the property for the entities of kind `K`. Derived properties also register
+    /// their dependencies.
+    #[inline]
+    fn register_for<K: EntityKind>(context: &mut Context) {
+        if context.is_registered::<K, Self>() {
+            return;
+        }
+        if Self::is_derived() {
+            context.register_derived_property::<K, Self>();
+        } else {
+            context.register_nonderived_property::<K, Self>();
         }
     }
 
+    /// Subscribes to changes of the global properties a derived property depends on, so that its
+    /// cached values and index for the entities of kind `K` are cleared when one changes.
+    /// Implemented by `define_derived_property!`.
+    #[doc(hidden)]
+    fn register_global_dependencies<K: EntityKind>(_context: &mut Context) {}
+
     /// Adds all nonderived dependencies of `Self` to `dependencies`, ***including `Self`***
     /// if `Self` is nonderived.
     #[inline]
@@ -80,11 +121,56 @@ pub trait Property: Clone + Debug + PartialEq + Hash + 'static {
         PropertyInfo(Self::name().to_string(), type_of::<Self>(), Self::is_required(), false)
     }
 
+    /// Formats the value for a report column. Defaults to the `Debug` representation.
+    #[must_use]
+    fn to_report_value(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// Checks a value before it is stored with `set_property()`, `add_entity()`, or
+    /// `update_entity()`, which return `IxaError::IxaError` with the message instead of storing
+    /// an invalid value. Accepts every value by default.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the value is invalid.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Validates the value with `validate()`, wrapping a failure in an `IxaError` that names the
+    /// property and the value.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the value is invalid.
+    fn check_valid(&self) -> Result<(), IxaError> {
+        self.validate().map_err(|message| {
+            IxaError::IxaError(format!("Invalid value {self:?} for {}: {message}", Self::name()))
+        })
+    }
+
+    /// The value of the property for entities that were never given one, or `None` if the
+    /// property has no default. With a default, reads, queries, and indexes treat an entity with
+    /// no stored value as having the default.
+    #[must_use]
+    #[inline]
+    fn default_value() -> Option<Self> {
+        None
+    }
+
+    /// The value of the property for the given `DefaultEntity`.
     #[must_use]
     fn compute(context: &Context, entity_id: EntityId) -> Option<Self> {
-        context.get_data_container::<EntityData>()
+        Self::compute_for::<DefaultEntity>(context, entity_id)
+    }
+
+    /// The value of the property for the given entity of kind `K`. Overridden by derived
+    /// properties, which compute it from their dependencies.
+    #[must_use]
+    fn compute_for<K: EntityKind>(context: &Context, entity_id: EntityId) -> Option<Self> {
+        context.get_data_container::<EntityData<K>>()
                .unwrap()
                .get_property_ref(entity_id).cloned()
+               .or_else(Self::default_value)
     }
 }
 
@@ -112,6 +198,7 @@ define_derived_property!(
 #[macro_export]
 macro_rules! define_derived_property {
     (
+        @impl $is_memoized:literal,
         $derived_property:ident,
         [$($dependency:ident),*],
         [$($global_dependency:ident),*],
@@ -126,11 +213,8 @@ macro_rules! define_derived_property {
                 stringify!($derived_property)
             }
 
-            fn register(context: &mut $crate::Context) {
-                use $crate::entity::ContextEntityExtInternal;
-                if !context.is_registered::<Self>(){
-                    context.register_derived_property::<$derived_property>();
-                }
+            fn is_memoized() -> bool {
+                $is_memoized
             }
 
             fn collect_dependencies(dependencies: &mut Vec<std::any::TypeId>) {
@@ -139,6 +223,18 @@ macro_rules! define_derived_property {
                 )*
             }
 
+            #[allow(unused_variables)]
+            fn register_global_dependencies<K: $crate::EntityKind>(context: &mut $crate::Context) {
+                $(
+                    <$crate::Context as $crate::ContextEventExt>::subscribe_to_event(
+                        context,
+                        |context, _: $crate::GlobalPropertyChangeEvent<$global_dependency>| {
+                            $crate::entity::global_dependency_changed::<K, Self>(context);
+                        },
+                    );
+                )*
+            }
+
             fn property_info() -> $crate::property::PropertyInfo {
                 $crate::property::PropertyInfo(
                     Self::name().to_string(),
@@ -148,28 +244,58 @@ macro_rules! define_derived_property {
                 )
             }
 
-            fn compute(context: &$crate::context::Context, entity_id: $crate::EntityId) -> Option<Self> {
-                // #[allow(unused_imports)]
-                // use $crate::global_properties::ContextGlobalPropertiesExt;
-                #[allow(unused_parens)]
-                let ($($param,)*) = (
-                    $(context.get_property_internal::<$dependency>(entity_id).unwrap()),*,
-                    
-                    $(
-                        *context.get_global_property_value::<$global_dependency>()
-                            .unwrap_or_else(|| panic!(
-                                "Global property {} not initialized",
-                                stringify!($global_dependency)
-                            )),
-                    )*
-                    
-                );
-
-                (|$($param),+| $derive_fn)($($param),+)
+            fn compute_for<K: $crate::EntityKind>(
+                context: &$crate::Context,
+                entity_id: $crate::EntityId,
+            ) -> Option<Self> {
+                #[allow(unused_imports)]
+                use $crate::{
+                    entity::ContextEntityExtInternal,
+                    global_properties::ContextGlobalPropertiesExt,
+                };
+                let compute = || -> Option<Self> {
+                    #[allow(unused_parens)]
+                    let ($($param,)*) = (
+                        // A derived property has no value if any of its dependencies has none.
+                        $(context.get_property_internal::<K, $dependency>(entity_id)?),*,
+
+                        $(
+                            *context.get_global_property_value::<$global_dependency>()
+                                .unwrap_or_else(|| panic!(
+                                    "Global property {} not initialized",
+                                    stringify!($global_dependency)
+                                )),
+                        )*
+
+                    );
+
+                    (|$($param),+| $derive_fn)($($param),+)
+                };
+
+                if Self::is_memoized() {
+                    $crate::entity::memoize::<K, Self>(context, entity_id, compute)
+                } else {
+                    compute()
+                }
             }
         }
     };
 
+    (
+        $derived_property:ident,
+        [$($dependency:ident),*],
+        [$($global_dependency:ident),*],
+        |$($param:ident),+| $derive_fn:expr
+    ) => {
+        define_derived_property!(
+            @impl false,
+            $derived_property,
+            [$($dependency),*],
+            [$($global_dependency),*],
+            |$($param),+| $derive_fn
+        );
+    };
+
     (
         $derived_property:ident,
         [$($dependency:ident),*],
@@ -183,3 +309,66 @@ macro_rules! define_derived_property {
         );
     };
 }
+
+/// Like `define_derived_property!`, but caches the value computed for each entity until one of
+/// the property's dependencies is set for that entity. Use it for derived properties that are
+/// expensive to compute. Changing one of the global properties it depends on clears the cached
+/// values of every entity.
+#[macro_export]
+macro_rules! define_memoized_derived_property {
+    (
+        $derived_property:ident,
+        [$($dependency:ident),*],
+        [$($global_dependency:ident),*],
+        |$($param:ident),+| $derive_fn:expr
+    ) => {
+        $crate::define_derived_property!(
+            @impl true,
+            $derived_property,
+            [$($dependency),*],
+            [$($global_dependency),*],
+            |$($param),+| $derive_fn
+        );
+    };
+
+    (
+        $derived_property:ident,
+        [$($dependency:ident),*],
+        |$($param:ident),+| $derive_fn:expr
+    ) => {
+        $crate::define_memoized_derived_property!(
+            $derived_property,
+            [$($dependency),*],
+            [],
+            |$($param),+| $derive_fn
+        );
+    };
+}
+
+/// Implements `Property` for `$property` with `Property::is_required` returning `true`, so that
+/// every entity must be created with a value for it. The type must already derive the traits
+/// `Property` requires. As with any required property, register it with `Property::register`
+/// before adding entities:
+///
+/// ```ignore
+/// #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+/// struct Age(u8);
+/// define_required_property!(Age);
+///
+/// Age::register(&mut context);
+/// assert!(context.add_entity(()).is_err());
+/// ```
+#[macro_export]
+macro_rules! define_required_property {
+    ($property:ident) => {
+        impl $crate::Property for $property {
+            fn name() -> &'static str {
+                stringify!($property)
+            }
+
+            fn is_required() -> bool {
+                true
+            }
+        }
+    };
+}
@@ -12,6 +12,8 @@ use crate::{
 pub(crate) struct PropertyStore<T: Property> {
     pub is_required: bool,
     pub values: Vec<Option<T>>,
+    /// `T::default_value()`, stored once so that it can be borrowed.
+    pub default: Option<T>,
 }
 
 impl<T: Property> PropertyStore<T> {
@@ -20,6 +22,7 @@ impl<T: Property> PropertyStore<T> {
         Self {
             is_required: false,
             values: Vec::new(),
+            default: T::default_value(),
         }
     }
     #[inline(always)]
@@ -30,6 +33,11 @@ impl<T: Property> PropertyStore<T> {
     pub fn len(&self) -> usize {
         self.values.len()
     }
+    /// Reserves capacity for at least `additional` more values.
+    #[inline(always)]
+    pub fn reserve(&mut self, additional: usize) {
+        self.values.reserve(additional);
+    }
 }
 
 define_any_map_container!(
@@ -1,9 +1,13 @@
 use crate::{
     context::Context,
+    entity::{DefaultEntity, EntityKind, Query},
     hashing::hash_str,
     trace,
     trait_map::TraitMap,
-    context::DataPlugin
+    context::DataPlugin,
+    EntityId,
+    HashMap,
+    IxaError,
 };
 use rand::{
     distr::{
@@ -14,10 +18,12 @@ use rand::{
         }
     },
     prelude::Distribution,
+    seq::{IndexedRandom, SliceRandom},
     Rng,
     SeedableRng,
 };
-use std::any::Any;
+use rand_distr::{Binomial, Gamma, Poisson};
+use std::any::{Any, TypeId};
 
 // pub struct RngId {
 //     idx: usize,
@@ -31,32 +37,149 @@ pub trait RngId: Any  {
     const name: &'static str;
     type RngType: SeedableRng;
     fn rng(&mut self) -> &mut Self::RngType;
+
+    /// If `true`, the generator ignores the seed passed to `new`, so it can be used before
+    /// `init_random` is called. Set by `define_rng!` when given a fixed seed.
+    #[must_use]
+    fn has_fixed_seed() -> bool {
+        false
+    }
+
+    /// Serializes the state of the generator, or returns `None` if its type can't be serialized.
+    fn state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores the generator to a state returned by `state()`.
+    ///
+    /// # Errors
+    /// Returns an error if the state is malformed or the generator's type can't be serialized.
+    fn set_state(&mut self, _state: &[u8]) -> Result<(), IxaError> {
+        Err(IxaError::IxaError(format!("Cannot restore the state of RNG {}", Self::name)))
+    }
+}
+
+/// Type-erased access to the state of one stream in `RngPlugin::rng_map`.
+struct RngStateFns {
+    name: &'static str,
+    export: fn(&TraitMap) -> Option<Vec<u8>>,
+    restore: fn(&mut TraitMap, &[u8]) -> Result<(), IxaError>,
+    reseed: fn(&mut TraitMap, Option<u64>, &HashMap<TypeId, u64>),
+}
+
+fn export_state<R: RngId>(rng_map: &TraitMap) -> Option<Vec<u8>> {
+    rng_map.get::<R>().and_then(R::state)
+}
+
+fn reseed<R: RngId>(
+    rng_map: &mut TraitMap,
+    base_seed: Option<u64>,
+    seed_overrides: &HashMap<TypeId, u64>,
+) {
+    rng_map.insert(R::new(RngPlugin::stream_seed::<R>(base_seed, seed_overrides)));
+}
+
+fn restore_state<R: RngId>(rng_map: &mut TraitMap, state: &[u8]) -> Result<(), IxaError> {
+    // Will never panic, as functions are only recorded for streams in the map.
+    rng_map.get_mut::<R>().unwrap().set_state(state)
 }
 
-struct RngPlugin {
-    base_seed: u64,
-    rng_map  : TraitMap
+pub(crate) struct RngPlugin {
+    /// The seed passed to `init_random`, or `None` if it hasn't been called yet.
+    base_seed: Option<u64>,
+    rng_map  : TraitMap,
+    /// One entry for each stream in `rng_map`, so that their states can be exported.
+    state_fns: Vec<RngStateFns>,
+    /// Restored states of streams that haven't been created yet, keyed by stream name. A
+    /// stream's state is applied when it is created.
+    pending_states: HashMap<String, Vec<u8>>,
+    /// The generators used by `sample_keyed`, keyed by the stream's `TypeId` and the key. Each
+    /// value is a `R::RngType`.
+    keyed_rngs: HashMap<(TypeId, u64), Box<dyn Any>>,
+    /// Seeds set with `set_rng_seed`, keyed by the stream's `TypeId`. They take the place of the
+    /// seed derived from the base seed, and are kept by `init_random` and `reseed_all`.
+    seed_overrides: HashMap<TypeId, u64>,
 }
 
 impl RngPlugin {
     fn with_seed(seed : u64) -> Self {
         RngPlugin{
-            base_seed: seed,
-            rng_map  : TraitMap::new()
+            base_seed: Some(seed),
+            ..RngPlugin::new()
         }
     }
     fn clear(&mut self) {
         self.rng_map.clear();
+        self.state_fns.clear();
+        self.pending_states.clear();
+        self.keyed_rngs.clear();
     }
 
-    pub fn get_rng<R: RngId>(&mut self) -> &mut R::RngType {
-        if !self.rng_map.contains_key::<R>() {
-            let base_seed = self.base_seed;
-            let seed_offset = base_seed.wrapping_add(hash_str(R::name));
-            self.rng_map.insert(R::new(seed_offset));
-        }
+    /// The seed the stream `R` is created with: the base seed offset by the hash of the
+    /// stream's name.
+    fn seed_for<R: RngId>(base_seed: Option<u64>) -> u64 {
+        base_seed.unwrap_or(0).wrapping_add(hash_str(R::name))
+    }
+
+    /// The seed the stream `R` is created with: the seed set with `set_rng_seed`, if any, or
+    /// else the seed derived from the base seed.
+    fn stream_seed<R: RngId>(base_seed: Option<u64>, seed_overrides: &HashMap<TypeId, u64>) -> u64 {
+        seed_overrides
+            .get(&TypeId::of::<R>())
+            .copied()
+            .unwrap_or_else(|| RngPlugin::seed_for::<R>(base_seed))
+    }
+
+    /// The seed of the stream `key` of `R` used by `sample_keyed`.
+    fn keyed_seed_for<R: RngId>(&self, key: u64) -> u64 {
+        RngPlugin::stream_seed::<R>(self.base_seed, &self.seed_overrides)
+            .wrapping_add(hash_str(&key.to_string()))
+    }
+
+    fn get_keyed_rng<R: RngId>(&mut self, key: u64) -> &mut R::RngType
+    where
+        R::RngType: 'static,
+    {
+        let seeded =
+            self.base_seed.is_some() || self.seed_overrides.contains_key(&TypeId::of::<R>());
+        let seed = self.keyed_seed_for::<R>(key);
+        self.keyed_rngs
+            .entry((TypeId::of::<R>(), key))
+            .or_insert_with(|| {
+                if !seeded {
+                    panic!("RNG {} was used before init_random was called", R::name);
+                }
+                Box::new(R::RngType::seed_from_u64(seed))
+            })
+            .downcast_mut()
+            // Will never panic, as the value for `R` is always a `R::RngType`.
+            .unwrap()
+    }
 
-        self.rng_map.get_mut::<R>().unwrap().rng()
+    pub fn get_rng<R: RngId>(&mut self) -> &mut R::RngType {
+        let RngPlugin { base_seed, rng_map, state_fns, pending_states, seed_overrides, .. } = self;
+        rng_map
+            .get_or_insert_with(|| {
+                let restored = pending_states.contains_key(R::name);
+                let overridden = seed_overrides.contains_key(&TypeId::of::<R>());
+                if base_seed.is_none() && !R::has_fixed_seed() && !restored && !overridden {
+                    panic!("RNG {} was used before init_random was called", R::name);
+                }
+                let mut rng = R::new(RngPlugin::stream_seed::<R>(*base_seed, seed_overrides));
+                state_fns.push(RngStateFns {
+                    name: R::name,
+                    export: export_state::<R>,
+                    restore: restore_state::<R>,
+                    reseed: reseed::<R>,
+                });
+                if let Some(state) = pending_states.remove(R::name) {
+                    rng.set_state(&state).unwrap_or_else(|error| {
+                        panic!("Invalid state for RNG {}: {error}", R::name)
+                    });
+                }
+                rng
+            })
+            .rng()
     }
 }
 
@@ -64,12 +187,27 @@ impl DataPlugin for RngPlugin {
     #[allow(non_upper_case_globals)]
     const new: &'static dyn Fn() -> Self = &|| {
         RngPlugin{
-            base_seed: 0,
-            rng_map: TraitMap::new()
+            base_seed: None,
+            rng_map: TraitMap::new(),
+            state_fns: Vec::new(),
+            pending_states: HashMap::default(),
+            keyed_rngs: HashMap::default(),
+            seed_overrides: HashMap::default(),
         }
     };
 }
 
+impl Context {
+    /// Creates a new `Context` with its random number generators seeded from `base_seed`, as if
+    /// by `Context::new()` followed by `init_random(base_seed)`.
+    #[must_use]
+    pub fn with_seed(base_seed: u64) -> Context {
+        let mut context = Context::new();
+        context.init_random(base_seed);
+        context
+    }
+}
+
 /// Gets a mutable reference to the random number generator associated with the given
 /// `RngId`.
 // This is a private free function so that it's not leaked to the public API.
@@ -83,19 +221,74 @@ fn get_rng<R: RngId>(context: &mut Context) -> &mut R::RngType {
 pub trait ContextRandomExt {
     fn init_random(&mut self, base_seed: u64);
 
+    /// Sets a new base seed and immediately re-creates every stream that has been used with the
+    /// seed derived from it, `new_base_seed.wrapping_add(hash_str(name))`, so that replicates can
+    /// vary the seed without rebuilding the context. Unlike `init_random`, the streams are kept
+    /// and `rng_state_blobs()` still lists them. Keyed streams and restored states that haven't
+    /// been applied yet are discarded, and streams defined with a fixed seed restart from it.
+    fn reseed_all(&mut self, new_base_seed: u64);
+
+    /// Returns the base seed passed to `init_random`, or `None` if it hasn't been called.
+    fn base_seed(&self) -> Option<u64>;
+
+    /// Returns the seed the stream `R` is created with, which is the seed set with
+    /// `set_rng_seed`, if any, or else `base_seed.wrapping_add(hash_str(R::name))`. Logging it
+    /// lets a run be reproduced one stream at a time. Streams defined with a fixed seed in
+    /// `define_rng!` ignore this value.
+    fn rng_seed<R: RngId>(&self) -> u64;
+
+    /// Seeds the stream `R` with `seed` instead of a seed derived from the base seed, so that
+    /// one stream can be held fixed while the base seed varies. If the stream has been used, it
+    /// is re-created with the new seed right away. The override is kept by `init_random` and
+    /// `reseed_all`, and it also replaces the seed that `R`'s keyed streams are derived from.
+    /// Streams defined with a fixed seed in `define_rng!` ignore it.
+    fn set_rng_seed<R: RngId>(&mut self, seed: u64);
+
+    /// Exports the state of every stream that has been used, keyed by stream name and sorted by
+    /// name, so that a resumed simulation can continue the exact random sequence. Streams whose
+    /// generator type can't be serialized are left out; streams defined with `define_rng!` and
+    /// the default generator type can always be exported.
+    fn rng_state_blobs(&self) -> Vec<(String, Vec<u8>)>;
+
+    /// Restores stream states exported with `rng_state_blobs()`. Streams that haven't been
+    /// created yet are restored when they are first used. Calling `init_random` afterward
+    /// discards the restored states.
+    ///
+    /// # Errors
+    /// Returns an error if a state for an existing stream can't be restored.
+    fn restore_rng_state_blobs(
+        &mut self,
+        blobs: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), IxaError>;
+
     /// Gets a random sample from the random number generator associated with the given
     /// `RngId` by applying the specified sampler function. If the Rng has not been used
-    /// before, one will be created with the base seed you defined in `set_base_random_seed`.
-    /// Note that this will panic if `set_base_random_seed` was not called yet.
+    /// before, one will be created with the base seed passed to `init_random`.
+    /// Note that this will panic if `init_random` was not called yet.
     fn sample<R: RngId + 'static, T>(
         &mut self,
         sampler: impl FnOnce(&mut R::RngType) -> T,
     ) -> T;
 
+    /// Like `sample`, but draws from one of a family of independent streams of `R`, selected by
+    /// `key`, such as one stream per region. The stream for `key` is created on first use with
+    /// the seed `rng_seed::<R>().wrapping_add(hash_str(&key.to_string()))`, so each key
+    /// reproduces the same sequence for a given base seed. Keyed streams are discarded by
+    /// `init_random` and aren't included in `rng_state_blobs()`.
+    /// Note that this will panic if `init_random` was not called yet, even if `R` has a fixed
+    /// seed.
+    fn sample_keyed<R: RngId + 'static, T>(
+        &mut self,
+        key: u64,
+        sampler: impl FnOnce(&mut R::RngType) -> T,
+    ) -> T
+    where
+        R::RngType: 'static;
+
     /// Gets a random sample from the specified distribution using a random number generator
     /// associated with the given `RngId`. If the Rng has not been used before, one will be
-    /// created with the base seed you defined in `set_base_random_seed`.
-    /// Note that this will panic if `set_base_random_seed` was not called yet.
+    /// created with the base seed passed to `init_random`.
+    /// Note that this will panic if `init_random` was not called yet.
     fn sample_distr<R: RngId + 'static, T>(
         &mut self,
         distribution: impl Distribution<T>,
@@ -103,9 +296,21 @@ pub trait ContextRandomExt {
     where
         R::RngType: Rng;
 
+    /// Fills `out` with random samples from the specified distribution using the random number
+    /// generator associated with the given `RngId`. The generator is looked up once for the whole
+    /// slice, which makes this cheaper than calling `sample_distr` in a loop.
+    /// Note that this will panic if `init_random` was not called yet.
+    fn sample_distr_into<R: RngId + 'static, T>(
+        &mut self,
+        distribution: &impl Distribution<T>,
+        out: &mut [T],
+    )
+    where
+        R::RngType: Rng;
+
     /// Gets a random sample within the range provided by `range`
     /// using the generator associated with the given `RngId`.
-    /// Note that this will panic if `set_base_random_seed` was not called yet.
+    /// Note that this will panic if `init_random` was not called yet.
     fn sample_range<R: RngId + 'static, S, T>(&mut self, range: S) -> T
     where
         R::RngType: Rng,
@@ -114,7 +319,7 @@ pub trait ContextRandomExt {
 
     /// Gets a random boolean value which is true with probability `p`
     /// using the generator associated with the given `RngId`.
-    /// Note that this will panic if `set_base_random_seed` was not called yet.
+    /// Note that this will panic if `init_random` was not called yet.
     fn sample_bool<R: RngId + 'static>(&mut self, p: f64) -> bool
     where
         R::RngType: Rng;
@@ -122,11 +327,89 @@ pub trait ContextRandomExt {
     /// Draws a random entry out of the list provided in `weights`
     /// with the given weights using the generator associated with the
     /// given `RngId`.  Note that this will panic if
-    /// `set_base_random_seed` was not called yet.
+    /// `init_random` was not called yet.
     fn sample_weighted<R: RngId + 'static, T>(&mut self, weights: &[T]) -> usize
     where
         R::RngType: Rng,
         T: Clone + Default + SampleUniform + for<'a> std::ops::AddAssign<&'a T> + PartialOrd + Weight;
+
+    /// Draws one of `items` with probability proportional to its weight using the generator
+    /// associated with the given `RngId`, and returns a copy of the item. Returns `None` if
+    /// `items` is empty. Weights have the same bounds as in `sample_weighted`.
+    /// Note that this will panic if `init_random` was not called yet.
+    ///
+    /// # Panics
+    /// Panics if a weight is negative or all the weights are zero.
+    fn sample_weighted_item<R: RngId + 'static, I: Clone, W>(
+        &mut self,
+        items: &[(I, W)],
+    ) -> Option<I>
+    where
+        R::RngType: Rng,
+        W: Clone + Default + SampleUniform + PartialOrd + Weight,
+        W: for<'a> std::ops::AddAssign<&'a W>;
+
+    /// Draws a count from the Poisson distribution with mean `lambda` using the generator
+    /// associated with the given `RngId`.
+    /// Note that this will panic if `init_random` was not called yet.
+    ///
+    /// # Panics
+    /// Panics if `lambda` is not a positive, finite number.
+    fn sample_poisson<R: RngId + 'static>(&mut self, lambda: f64) -> u64
+    where
+        R::RngType: Rng;
+
+    /// Draws how many of `n` independent trials fall in each category, where each trial picks a
+    /// category with probability proportional to its entry in `weights`. The counts have one
+    /// entry per weight and sum to `n`. The draw takes one binomial sample per category, so it
+    /// is fast for large `n`.
+    /// Note that this will panic if `init_random` was not called yet.
+    ///
+    /// # Panics
+    /// Panics if `weights` is empty, a weight is negative or not finite, or all the weights are
+    /// zero.
+    fn sample_multinomial<R: RngId + 'static>(&mut self, n: u64, weights: &[f64]) -> Vec<u64>
+    where
+        R::RngType: Rng;
+
+    /// Draws a probability vector from the Dirichlet distribution with concentration parameters
+    /// `alphas` using the generator associated with the given `RngId`. The result has one entry
+    /// per alpha and sums to 1, so it can be passed directly to `sample_weighted`.
+    /// Note that this will panic if `init_random` was not called yet.
+    ///
+    /// # Panics
+    /// Panics if `alphas` is empty or any alpha is not a positive, finite number.
+    fn sample_dirichlet<R: RngId + 'static>(&mut self, alphas: &[f64]) -> Vec<f64>
+    where
+        R::RngType: Rng;
+
+    /// Shuffles `slice` in place using the generator associated with the given `RngId`.
+    /// Note that this will panic if `init_random` was not called yet.
+    fn shuffle<R: RngId + 'static, T>(&mut self, slice: &mut [T])
+    where
+        R::RngType: Rng;
+
+    /// Chooses an element of `slice` uniformly at random using the generator associated with the
+    /// given `RngId`. Returns `None` if `slice` is empty.
+    /// Note that this will panic if `init_random` was not called yet.
+    fn choose<'a, R: RngId + 'static, T>(&mut self, slice: &'a [T]) -> Option<&'a T>
+    where
+        R::RngType: Rng;
+
+    /// Draws an entity uniformly at random from the entities matching the query `q` using the
+    /// generator associated with the given `RngId`. Returns `None` if no entity matches.
+    /// Note that this will panic if `init_random` was not called yet.
+    fn sample_entity<R: RngId + 'static, Q: Query>(&mut self, q: Q) -> Option<EntityId>
+    where
+        R::RngType: Rng;
+
+    /// Like `sample_entity()`, but draws from the entities of kind `K` matching the query `q`.
+    fn sample_entity_of<R: RngId + 'static, K: EntityKind, Q: Query>(
+        &mut self,
+        q: Q,
+    ) -> Option<EntityId>
+    where
+        R::RngType: Rng;
 }
 
 impl ContextRandomExt for Context {
@@ -135,12 +418,91 @@ impl ContextRandomExt for Context {
     fn init_random(&mut self, base_seed: u64) {
         trace!("initializing random module");
         let rng_container = self.get_data_container_mut::<RngPlugin>();
-        rng_container.base_seed = base_seed;
+        rng_container.base_seed = Some(base_seed);
 
         // Clear any existing Rngs to ensure they get re-seeded when `get_rng` is called
         rng_container.clear();
     }
 
+    fn reseed_all(&mut self, new_base_seed: u64) {
+        trace!("reseeding random module");
+        let RngPlugin {
+            base_seed,
+            rng_map,
+            state_fns,
+            pending_states,
+            keyed_rngs,
+            seed_overrides,
+        } = self.get_data_container_mut::<RngPlugin>();
+        *base_seed = Some(new_base_seed);
+        for state_fns in state_fns.iter() {
+            (state_fns.reseed)(rng_map, *base_seed, seed_overrides);
+        }
+        pending_states.clear();
+        keyed_rngs.clear();
+    }
+
+    fn base_seed(&self) -> Option<u64> {
+        self.get_data_container::<RngPlugin>()
+            .and_then(|rng_container| rng_container.base_seed)
+    }
+
+    fn rng_seed<R: RngId>(&self) -> u64 {
+        match self.get_data_container::<RngPlugin>() {
+            Some(rng_container) => {
+                RngPlugin::stream_seed::<R>(rng_container.base_seed, &rng_container.seed_overrides)
+            }
+            None => RngPlugin::seed_for::<R>(None),
+        }
+    }
+
+    fn set_rng_seed<R: RngId>(&mut self, seed: u64) {
+        let rng_container = self.get_data_container_mut::<RngPlugin>();
+        rng_container.seed_overrides.insert(TypeId::of::<R>(), seed);
+        rng_container.pending_states.remove(R::name);
+        rng_container.keyed_rngs.retain(|(type_id, _), _| *type_id != TypeId::of::<R>());
+        if rng_container.rng_map.contains_key::<R>() {
+            rng_container.rng_map.insert(R::new(seed));
+        }
+    }
+
+    fn rng_state_blobs(&self) -> Vec<(String, Vec<u8>)> {
+        let Some(rng_container) = self.get_data_container::<RngPlugin>() else {
+            return Vec::new();
+        };
+        let mut blobs: Vec<(String, Vec<u8>)> = rng_container
+            .state_fns
+            .iter()
+            .filter_map(|state_fns| {
+                let state = (state_fns.export)(&rng_container.rng_map)?;
+                Some((state_fns.name.to_string(), state))
+            })
+            .collect();
+        blobs.sort_by(|a, b| a.0.cmp(&b.0));
+        blobs
+    }
+
+    fn restore_rng_state_blobs(
+        &mut self,
+        blobs: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), IxaError> {
+        let rng_container = self.get_data_container_mut::<RngPlugin>();
+        for (name, state) in blobs {
+            let restore = rng_container
+                .state_fns
+                .iter()
+                .find(|state_fns| state_fns.name == name)
+                .map(|state_fns| state_fns.restore);
+            match restore {
+                Some(restore) => restore(&mut rng_container.rng_map, &state)?,
+                None => {
+                    rng_container.pending_states.insert(name, state);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn sample<R: RngId + 'static, T>(
         &mut self,
         sampler: impl FnOnce(&mut R::RngType) -> T,
@@ -149,6 +511,18 @@ impl ContextRandomExt for Context {
         sampler(rng)
     }
 
+    fn sample_keyed<R: RngId + 'static, T>(
+        &mut self,
+        key: u64,
+        sampler: impl FnOnce(&mut R::RngType) -> T,
+    ) -> T
+    where
+        R::RngType: 'static,
+    {
+        let rng = self.get_data_container_mut::<RngPlugin>().get_keyed_rng::<R>(key);
+        sampler(rng)
+    }
+
     fn sample_distr<R: RngId + 'static, T>(
         &mut self,
         distribution: impl Distribution<T>,
@@ -160,6 +534,20 @@ impl ContextRandomExt for Context {
         distribution.sample::<R::RngType>(rng)
     }
 
+    fn sample_distr_into<R: RngId + 'static, T>(
+        &mut self,
+        distribution: &impl Distribution<T>,
+        out: &mut [T],
+    )
+    where
+        R::RngType: Rng,
+    {
+        let rng = get_rng::<R>(self);
+        for (slot, value) in out.iter_mut().zip(distribution.sample_iter(rng)) {
+            *slot = value;
+        }
+    }
+
     fn sample_range<R: RngId + 'static, S, T>(&mut self, range: S) -> T
     where
         R::RngType: Rng,
@@ -185,31 +573,183 @@ impl ContextRandomExt for Context {
         let rng = get_rng::<R>(self);
         index.sample(rng)
     }
-}
 
+    fn sample_weighted_item<R: RngId + 'static, I: Clone, W>(
+        &mut self,
+        items: &[(I, W)],
+    ) -> Option<I>
+    where
+        R::RngType: Rng,
+        W: Clone + Default + SampleUniform + PartialOrd + Weight,
+        W: for<'a> std::ops::AddAssign<&'a W>,
+    {
+        if items.is_empty() {
+            return None;
+        }
+        let index = WeightedIndex::new(items.iter().map(|(_, weight)| weight)).unwrap();
+        let rng = get_rng::<R>(self);
+        Some(items[index.sample(rng)].0.clone())
+    }
+
+    fn sample_poisson<R: RngId + 'static>(&mut self, lambda: f64) -> u64
+    where
+        R::RngType: Rng,
+    {
+        assert!(lambda.is_finite() && lambda > 0.0, "Poisson lambda is invalid: {lambda}");
+        let poisson: Poisson<f64> = Poisson::new(lambda).unwrap();
+        // Poisson draws are whole numbers, so the conversion is exact.
+        self.sample_distr::<R, f64>(poisson) as u64
+    }
+
+    fn sample_multinomial<R: RngId + 'static>(&mut self, n: u64, weights: &[f64]) -> Vec<u64>
+    where
+        R::RngType: Rng,
+    {
+        assert!(!weights.is_empty(), "Multinomial distribution requires at least one weight");
+        for &weight in weights {
+            assert!(
+                weight.is_finite() && weight >= 0.0,
+                "Multinomial weight is invalid: {weight}"
+            );
+        }
+        assert!(weights.iter().sum::<f64>() > 0.0, "Multinomial weights are all zero");
+
+        // Each count is binomial given the trials left over from the categories before it.
+        let rng = get_rng::<R>(self);
+        let mut remaining = n;
+        let mut counts = Vec::with_capacity(weights.len());
+        for (idx, &weight) in weights[..weights.len() - 1].iter().enumerate() {
+            // Summing the remaining weights, rather than subtracting from the total, makes `p`
+            // exactly 1 when the later weights are all zero.
+            let remaining_weight: f64 = weights[idx..].iter().sum();
+            let p = if remaining_weight > 0.0 { (weight / remaining_weight).min(1.0) } else { 0.0 };
+            let count = Binomial::new(remaining, p).unwrap().sample(rng);
+            counts.push(count);
+            remaining -= count;
+        }
+        counts.push(remaining);
+        counts
+    }
+
+    fn sample_dirichlet<R: RngId + 'static>(&mut self, alphas: &[f64]) -> Vec<f64>
+    where
+        R::RngType: Rng,
+    {
+        assert!(!alphas.is_empty(), "Dirichlet distribution requires at least one alpha");
+        // A Dirichlet draw is a vector of independent Gamma(alpha_i, 1) draws normalized to
+        // sum to 1.
+        let gammas: Vec<Gamma<f64>> = alphas
+            .iter()
+            .map(|&alpha| {
+                assert!(
+                    alpha.is_finite() && alpha > 0.0,
+                    "Dirichlet alpha is invalid: {alpha}"
+                );
+                Gamma::new(alpha, 1.0).unwrap()
+            })
+            .collect();
+
+        let rng = get_rng::<R>(self);
+        let mut sample: Vec<f64> = gammas.iter().map(|gamma| gamma.sample(rng)).collect();
+        let total: f64 = sample.iter().sum();
+        for value in &mut sample {
+            *value /= total;
+        }
+        sample
+    }
 
+    fn shuffle<R: RngId + 'static, T>(&mut self, slice: &mut [T])
+    where
+        R::RngType: Rng,
+    {
+        self.sample::<R, ()>(|rng| slice.shuffle(rng));
+    }
+
+    fn choose<'a, R: RngId + 'static, T>(&mut self, slice: &'a [T]) -> Option<&'a T>
+    where
+        R::RngType: Rng,
+    {
+        self.sample::<R, Option<&'a T>>(|rng| slice.choose(rng))
+    }
+
+    fn sample_entity<R: RngId + 'static, Q: Query>(&mut self, q: Q) -> Option<EntityId>
+    where
+        R::RngType: Rng,
+    {
+        self.sample_entity_of::<R, DefaultEntity, Q>(q)
+    }
+
+    fn sample_entity_of<R: RngId + 'static, K: EntityKind, Q: Query>(
+        &mut self,
+        q: Q,
+    ) -> Option<EntityId>
+    where
+        R::RngType: Rng,
+    {
+        // The query holds an immutable borrow of the context while it runs, so we can't draw from
+        // the RNG inside the accumulator. Instead of materializing the matches, we count them,
+        // draw an index, and then walk the matches a second time to find the selected entity.
+        q.setup::<K>(self);
+        let mut count: usize = 0;
+        q.execute_query::<K>(self, |_| count += 1);
+        if count == 0 {
+            return None;
+        }
+
+        let selected = self.sample_range::<R, _, usize>(0..count);
+        let mut position: usize = 0;
+        let mut result = None;
+        q.execute_query::<K>(self, |entity_id| {
+            if position == selected {
+                result = Some(entity_id);
+            }
+            position += 1;
+        });
+
+        result
+    }
+}
+
+/// Defines a named random number generator stream with the following parameters:
+/// * `$random_id`: The name of the type identifying the stream
+/// * `$rng_type`: The type of the generator, which must implement `SeedableRng` (optional,
+///   defaults to `rand_chacha::ChaCha12Rng`, the generator behind `rand::rngs::StdRng`)
+/// * `$seed`: A fixed seed for the generator (optional). Without it, the generator is seeded
+///   from the base seed passed to `init_random` and the name of the stream.
+///
+/// In every form the generator is created with `SeedableRng::seed_from_u64`, so a given seed
+/// always reproduces the same stream. Only the default generator type can be exported with
+/// `ContextRandomExt::rng_state_blobs`.
 #[macro_export]
 macro_rules! define_rng {
     ($random_id:ident) => {
         struct $random_id{
-            rng: $crate::rand::rngs::StdRng,
+            rng: $crate::rand_chacha::ChaCha12Rng,
         }
 
-        impl $crate::random::RngId for $random_id {
+        impl $crate::RngId for $random_id {
             #![allow(non_upper_case_globals)]
-            // TODO(ryl8@cdc.gov): This is hardcoded to StdRng; we should replace this
-            type RngType = $crate::rand::rngs::StdRng;
+            type RngType = $crate::rand_chacha::ChaCha12Rng;
             const name: &'static str = &stringify!($random_id);
             const new: &'static dyn Fn(u64) -> Self = &|seed| {
                 use $crate::rand::SeedableRng;
                 Self {
-                    rng: $crate::rand::rngs::StdRng::seed_from_u64(seed),
+                    rng: $crate::rand_chacha::ChaCha12Rng::seed_from_u64(seed),
                 }
             };
 
             fn rng(&mut self) -> &mut Self::RngType {
                 &mut self.rng
             }
+
+            fn state(&self) -> Option<Vec<u8>> {
+                $crate::serde_json::to_vec(&self.rng).ok()
+            }
+
+            fn set_state(&mut self, state: &[u8]) -> Result<(), $crate::IxaError> {
+                self.rng = $crate::serde_json::from_slice(state)?;
+                Ok(())
+            }
         }
     };
     ($random_id:ident, $rng_type:ty) => {
@@ -217,9 +757,8 @@ macro_rules! define_rng {
             rng: $rng_type,
         }
 
-        impl $crate::random::RngId for $random_id {
+        impl $crate::RngId for $random_id {
             #![allow(non_upper_case_globals)]
-            // TODO(ryl8@cdc.gov): This is hardcoded to StdRng; we should replace this
             type RngType = $rng_type;
             const name: &'static str = &stringify!($random_id);
             const new: &'static dyn Fn(u64) -> Self = &|seed| {
@@ -239,9 +778,8 @@ macro_rules! define_rng {
             rng: $rng_type,
         }
 
-        impl $crate::random::RngId for $random_id {
+        impl $crate::RngId for $random_id {
             #![allow(non_upper_case_globals)]
-            // TODO(ryl8@cdc.gov): This is hardcoded to StdRng; we should replace this
             type RngType = $rng_type;
             const name: &'static str = &stringify!($random_id);
             const new: &'static dyn Fn(u64) -> Self = &|_| {
@@ -251,6 +789,10 @@ macro_rules! define_rng {
                 }
             };
 
+            fn has_fixed_seed() -> bool {
+                true
+            }
+
             fn rng(&mut self) -> &mut Self::RngType {
                 &mut self.rng
             }
@@ -263,12 +805,118 @@ pub use define_rng;
 #[cfg(test)]
 mod test {
     use crate::context::{Context, DataPlugin};
+    use crate::entity::{ContextEntityExt, EntityKind};
+    use crate::property::Property;
     use crate::random::ContextRandomExt;
-    use rand::RngCore;
-    use rand::{distr::weighted::WeightedIndex, prelude::Distribution};
+    use crate::hashing::hash_str;
+    use crate::IxaError;
+    use rand::{rngs::{SmallRng, StdRng}, RngCore, SeedableRng};
+    use rand::{distr::{weighted::WeightedIndex, Uniform}, prelude::Distribution};
 
     define_rng!(FooRng);
     define_rng!(BarRng);
+    define_rng!(SmallFooRng, rand::rngs::SmallRng);
+    define_rng!(FixedSmallRng, rand::rngs::SmallRng, 1234);
+
+    #[test]
+    #[should_panic(expected = "RNG FooRng was used before init_random was called")]
+    fn sample_before_init_random() {
+        let mut context = Context::new();
+        context.sample::<FooRng, _>(RngCore::next_u64);
+    }
+
+    #[test]
+    fn fixed_seed_before_init_random() {
+        let mut context = Context::new();
+        let value = context.sample::<FixedSmallRng, _>(RngCore::next_u64);
+        assert_eq!(value, SmallRng::seed_from_u64(1234).next_u64());
+    }
+
+    #[test]
+    fn context_with_seed() {
+        let mut context = Context::with_seed(42);
+        assert_eq!(context.base_seed(), Some(42));
+        let value = context.sample::<FooRng, _>(RngCore::next_u64);
+
+        let mut expected = Context::new();
+        expected.init_random(42);
+        assert_eq!(value, expected.sample::<FooRng, _>(RngCore::next_u64));
+    }
+
+    #[test]
+    fn set_rng_seed() {
+        let draw = |base_seed: u64| -> (Vec<u64>, u64) {
+            let mut context = Context::new();
+            context.set_rng_seed::<FooRng>(7);
+            context.init_random(base_seed);
+            assert_eq!(context.rng_seed::<FooRng>(), 7);
+            let foo = (0..3).map(|_| context.sample::<FooRng, _>(RngCore::next_u64)).collect();
+            (foo, context.sample::<BarRng, _>(RngCore::next_u64))
+        };
+        let (foo_42, bar_42) = draw(42);
+        let (foo_43, bar_43) = draw(43);
+        assert_eq!(foo_42, foo_43);
+        assert_ne!(bar_42, bar_43);
+        assert_eq!(foo_42[0], StdRng::seed_from_u64(7).next_u64());
+
+        // Overriding the seed of a stream in use restarts it from the new seed.
+        let mut context = Context::new();
+        context.init_random(42);
+        context.sample::<FooRng, _>(RngCore::next_u64);
+        context.set_rng_seed::<FooRng>(7);
+        assert_eq!(context.sample::<FooRng, _>(RngCore::next_u64), foo_42[0]);
+        context.reseed_all(43);
+        assert_eq!(context.sample::<FooRng, _>(RngCore::next_u64), foo_42[0]);
+    }
+
+    #[test]
+    fn reseed_all() {
+        let draw = |context: &mut Context| -> Vec<u64> {
+            (0..3).map(|_| context.sample::<FooRng, _>(RngCore::next_u64)).collect()
+        };
+        let mut context = Context::new();
+        context.init_random(42);
+        let first = draw(&mut context);
+        context.sample::<BarRng, _>(RngCore::next_u64);
+
+        context.reseed_all(43);
+        assert_eq!(context.base_seed(), Some(43));
+        let reseeded = draw(&mut context);
+        assert_ne!(reseeded, first);
+        let mut expected = StdRng::seed_from_u64(43_u64.wrapping_add(hash_str("FooRng")));
+        assert_eq!(reseeded[0], expected.next_u64());
+        // The streams are kept, so both can still be exported.
+        assert_eq!(context.rng_state_blobs().len(), 2);
+
+        // Reseeding with the original seed restarts the original sequence.
+        context.reseed_all(42);
+        assert_eq!(draw(&mut context), first);
+    }
+
+    #[test]
+    fn sample_keyed() {
+        let draw = |context: &mut Context, key: u64| -> Vec<u64> {
+            (0..5).map(|_| context.sample_keyed::<FooRng, _>(key, RngCore::next_u64)).collect()
+        };
+        let mut context = Context::new();
+        context.init_random(42);
+        let region_1 = draw(&mut context, 1);
+        let region_2 = draw(&mut context, 2);
+        assert_ne!(region_1, region_2);
+
+        // Keyed streams don't advance the unkeyed stream.
+        let unkeyed = context.sample::<FooRng, _>(RngCore::next_u64);
+        let mut expected = StdRng::seed_from_u64(context.rng_seed::<FooRng>());
+        assert_eq!(unkeyed, expected.next_u64());
+
+        let seed = context.rng_seed::<FooRng>().wrapping_add(hash_str("1"));
+        assert_eq!(region_1[0], rand_chacha::ChaCha12Rng::seed_from_u64(seed).next_u64());
+
+        // Each key reproduces its sequence after `init_random`.
+        context.init_random(42);
+        assert_eq!(draw(&mut context, 2), region_2);
+        assert_eq!(draw(&mut context, 1), region_1);
+    }
 
     #[test]
     fn get_rng_basic() {
@@ -311,6 +959,140 @@ mod test {
         assert_ne!(run_1, context.sample::<FooRng, _>(RngCore::next_u64));
     }
 
+    #[test]
+    fn custom_rng_type_is_reproducible() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let run_0: Vec<u64> = (0..5)
+            .map(|_| context.sample::<SmallFooRng, _>(RngCore::next_u64))
+            .collect();
+
+        context.init_random(42);
+        let run_1: Vec<u64> = (0..5)
+            .map(|_| context.sample::<SmallFooRng, _>(RngCore::next_u64))
+            .collect();
+        assert_eq!(run_0, run_1);
+
+        // The stream is seeded with `SmallRng` itself from the derived seed.
+        let mut expected = SmallRng::seed_from_u64(42_u64.wrapping_add(hash_str("SmallFooRng")));
+        assert_eq!(run_0[0], expected.next_u64());
+
+        context.init_random(88);
+        assert_ne!(run_0[0], context.sample::<SmallFooRng, _>(RngCore::next_u64));
+    }
+
+    #[test]
+    fn default_rng_matches_std_rng() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let mut expected = StdRng::seed_from_u64(context.rng_seed::<FooRng>());
+        assert_eq!(context.sample::<FooRng, _>(RngCore::next_u64), expected.next_u64());
+    }
+
+    #[test]
+    fn rng_state_blobs_round_trip() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.sample::<FooRng, _>(RngCore::next_u64);
+        context.sample::<BarRng, _>(RngCore::next_u64);
+        // A stream whose type can't be serialized is left out.
+        context.sample::<SmallFooRng, _>(RngCore::next_u64);
+
+        let blobs = context.rng_state_blobs();
+        let names: Vec<&str> = blobs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["BarRng", "FooRng"]);
+        let expected: Vec<u64> = (0..5)
+            .map(|_| context.sample::<FooRng, _>(RngCore::next_u64))
+            .collect();
+
+        // Restoring an existing stream.
+        context.init_random(88);
+        context.sample::<FooRng, _>(RngCore::next_u64);
+        context.restore_rng_state_blobs(blobs.clone()).unwrap();
+        let restored: Vec<u64> = (0..5)
+            .map(|_| context.sample::<FooRng, _>(RngCore::next_u64))
+            .collect();
+        assert_eq!(restored, expected);
+
+        // Restoring into a fresh context, before the stream is created.
+        let mut resumed = Context::new();
+        resumed.init_random(88);
+        resumed.restore_rng_state_blobs(blobs).unwrap();
+        let restored: Vec<u64> = (0..5)
+            .map(|_| resumed.sample::<FooRng, _>(RngCore::next_u64))
+            .collect();
+        assert_eq!(restored, expected);
+    }
+
+    #[test]
+    fn restore_malformed_rng_state() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.sample::<FooRng, _>(RngCore::next_u64);
+        let result = context.restore_rng_state_blobs(vec![("FooRng".to_string(), vec![0, 1])]);
+        assert!(matches!(result, Err(IxaError::JsonError(_))));
+    }
+
+    #[test]
+    fn sample_dirichlet() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let alphas = [0.5, 1.0, 2.0, 4.0];
+
+        let sample = context.sample_dirichlet::<FooRng>(&alphas);
+        assert_eq!(sample.len(), alphas.len());
+        assert!(sample.iter().all(|&p| p >= 0.0));
+        assert!((sample.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        // Usable as weights.
+        let index = context.sample_weighted::<FooRng, f64>(&sample);
+        assert!(index < alphas.len());
+
+        context.init_random(42);
+        assert_eq!(context.sample_dirichlet::<FooRng>(&alphas), sample);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dirichlet alpha is invalid")]
+    fn sample_dirichlet_nonpositive_alpha() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.sample_dirichlet::<FooRng>(&[1.0, 0.0]);
+    }
+
+    #[test]
+    fn rng_seed_matches_formula() {
+        let mut context = Context::new();
+        assert_eq!(context.base_seed(), None);
+
+        context.init_random(42);
+        assert_eq!(context.base_seed(), Some(42));
+        assert_eq!(
+            context.rng_seed::<FooRng>(),
+            42_u64.wrapping_add(hash_str("FooRng"))
+        );
+        assert_ne!(context.rng_seed::<FooRng>(), context.rng_seed::<BarRng>());
+
+        // The reported seed is the one the stream was actually created with.
+        let mut expected = SmallRng::seed_from_u64(context.rng_seed::<SmallFooRng>());
+        assert_eq!(
+            context.sample::<SmallFooRng, _>(RngCore::next_u64),
+            expected.next_u64()
+        );
+    }
+
+    #[test]
+    fn fixed_seed_ignores_base_seed() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let run_0 = context.sample::<FixedSmallRng, _>(RngCore::next_u64);
+
+        context.init_random(88);
+        let run_1 = context.sample::<FixedSmallRng, _>(RngCore::next_u64);
+        assert_eq!(run_0, run_1);
+        assert_eq!(run_0, SmallRng::seed_from_u64(1234).next_u64());
+    }
+
     struct SamplerData(WeightedIndex<f64>);
     impl DataPlugin for SamplerData{
         const new: &'static dyn Fn() -> Self = &||{
@@ -358,6 +1140,20 @@ mod test {
         assert!((zero_counter - 1000_i32).abs() < 50);
     }
 
+    #[test]
+    fn sample_distribution_into_slice() {
+        let mut context = Context::new();
+        context.init_random(42);
+
+        let mut draws = [0.0_f64; 100];
+        let uniform = Uniform::new(0.0, 10.0).unwrap();
+        context.sample_distr_into::<FooRng, f64>(&uniform, &mut draws);
+
+        assert!(draws.iter().all(|draw| (0.0..10.0).contains(draw)));
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        assert!((mean - 5.0).abs() < 1.0);
+    }
+
     #[test]
     fn sample_range() {
         let mut context = Context::new();
@@ -380,4 +1176,146 @@ mod test {
         let r: usize = context.sample_weighted::<FooRng, _>(&[0.1, 0.3, 0.4]);
         assert!(r < 3);
     }
+
+    #[test]
+    fn sample_weighted_item() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let items = [("rare", 1u32), ("common", 98), ("uncommon", 1)];
+        let mut common = 0;
+        for _ in 0..1000 {
+            match context.sample_weighted_item::<FooRng, _, _>(&items) {
+                Some("common") => common += 1,
+                Some(_) => {}
+                None => panic!("No item drawn"),
+            }
+        }
+        assert!(common > 950, "common drawn {common} times");
+
+        let empty: [(&str, f64); 0] = [];
+        assert_eq!(context.sample_weighted_item::<FooRng, _, _>(&empty), None);
+    }
+
+    #[test]
+    fn sample_poisson_mean() {
+        let mut context = Context::new();
+        context.init_random(42);
+        let draws = 10_000;
+        let total: u64 = (0..draws).map(|_| context.sample_poisson::<FooRng>(3.5)).sum();
+        let mean = total as f64 / f64::from(draws);
+        assert!((mean - 3.5).abs() < 0.1, "mean {mean}");
+    }
+
+    #[test]
+    #[should_panic(expected = "Poisson lambda is invalid: 0")]
+    fn sample_poisson_invalid_lambda() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.sample_poisson::<FooRng>(0.0);
+    }
+
+    #[test]
+    fn sample_multinomial() {
+        let draw = |seed: u64| {
+            let mut context = Context::new();
+            context.init_random(seed);
+            context.sample_multinomial::<FooRng>(100_000, &[0.9, 0.09, 0.0, 0.01])
+        };
+        let counts = draw(42);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.iter().sum::<u64>(), 100_000);
+        assert_eq!(counts[2], 0);
+        assert!((89_000..91_000).contains(&counts[0]), "{counts:?}");
+        assert!((700..1_300).contains(&counts[3]), "{counts:?}");
+        assert_eq!(draw(42), counts);
+
+        let mut context = Context::new();
+        context.init_random(42);
+        assert_eq!(context.sample_multinomial::<FooRng>(10, &[1.0]), vec![10]);
+        assert_eq!(context.sample_multinomial::<FooRng>(0, &[1.0, 2.0]), vec![0, 0]);
+    }
+
+    #[test]
+    fn shuffle_is_permutation() {
+        let mut context = Context::new();
+        context.init_random(42);
+
+        let original: Vec<u32> = (0..50).collect();
+        let mut shuffled = original.clone();
+        context.shuffle::<FooRng, _>(&mut shuffled);
+        assert_ne!(shuffled, original);
+
+        shuffled.sort_unstable();
+        assert_eq!(shuffled, original);
+    }
+
+    #[test]
+    fn choose_from_slice() {
+        let mut context = Context::new();
+        context.init_random(42);
+
+        let items = ["a", "b", "c"];
+        for _ in 0..20 {
+            let item = context.choose::<FooRng, _>(&items).unwrap();
+            assert!(items.contains(item));
+        }
+
+        let empty: [u8; 0] = [];
+        assert!(context.choose::<FooRng, _>(&empty).is_none());
+    }
+
+    #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+    enum InfectionStatus {
+        S,
+        I,
+    }
+    impl Property for InfectionStatus {}
+
+    #[test]
+    fn sample_entity() {
+        let mut context = Context::new();
+        context.init_random(42);
+
+        let mut infected = Vec::new();
+        for idx in 0..10 {
+            if idx % 3 == 0 && infected.len() < 3 {
+                infected.push(context.add_entity(InfectionStatus::I).unwrap());
+            } else {
+                context.add_entity(InfectionStatus::S).unwrap();
+            }
+        }
+        assert_eq!(infected.len(), 3);
+
+        for _ in 0..100 {
+            let entity_id = context.sample_entity::<FooRng, _>(InfectionStatus::I).unwrap();
+            assert!(infected.contains(&entity_id));
+        }
+    }
+
+    #[test]
+    fn sample_entity_no_match() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.add_entity(InfectionStatus::S).unwrap();
+
+        assert!(context.sample_entity::<FooRng, _>(InfectionStatus::I).is_none());
+    }
+
+    struct Household;
+    impl EntityKind for Household {}
+
+    #[test]
+    fn sample_entity_of_kind() {
+        let mut context = Context::new();
+        context.init_random(42);
+        context.add_entity(InfectionStatus::I).unwrap();
+        context.add_entity_of::<Household, _>(InfectionStatus::S).unwrap();
+        let household = context.add_entity_of::<Household, _>(InfectionStatus::I).unwrap();
+
+        for _ in 0..10 {
+            let entity_id =
+                context.sample_entity_of::<FooRng, Household, _>(InfectionStatus::I).unwrap();
+            assert_eq!(entity_id, household);
+        }
+    }
 }
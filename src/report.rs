@@ -0,0 +1,519 @@
+//! Reports are how a model records its output. A report is a CSV file with one row per item sent
+//! to it. Each report has its own item type, which must implement `serde::Serialize` and
+//! [`Report`]; the easiest way to implement `Report` is with [`create_report_trait!`]:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize)]
+//! struct IncidenceReportItem {
+//!     time: f64,
+//!     entity_id: EntityId,
+//! }
+//!
+//! create_report_trait!(IncidenceReportItem);
+//!
+//! context.report_options().directory(PathBuf::from("output")).overwrite(true);
+//! context.add_report::<IncidenceReportItem>("incidence")?;
+//! context.send_report(IncidenceReportItem { time: 0.0, entity_id });
+//! ```
+//!
+//! Report files are named `<directory>/<file_prefix><name>.csv` using the options returned by
+//! [`ContextReportExt::report_options()`], so the options must be set before reports are added.
+//!
+//! The same item type can be written to several reports by adding it under different names and
+//! sending items with [`ContextReportExt::send_report_to()`]. [`ContextReportExt::send_report()`]
+//! writes to the report most recently added for the item's type.
+//!
+//! Rows are buffered in memory and written to the file when the buffer fills, when
+//! [`ContextReportExt::flush_reports()`] is called, when `execute()` returns, and when the
+//! `Context` is dropped. Rows sent inside [`ContextReportExt::report_transaction()`] are held
+//! back until the transaction succeeds and discarded if it fails.
+
+use crate::{
+    context::{Context, DataPlugin},
+    error,
+    error::IxaError,
+    trace,
+    type_of,
+    HashMap,
+    TypeId,
+};
+use csv::{Writer, WriterBuilder};
+use std::{fs::File, path::PathBuf};
+
+/// A type that can be written as a row of a report. Implement it with [`create_report_trait!`].
+pub trait Report: 'static {
+    /// Writes `self` as one row of the report.
+    fn serialize(&self, writer: &mut Writer<File>);
+}
+
+/// Implements [`Report`] for a type that implements `serde::Serialize`.
+#[macro_export]
+macro_rules! create_report_trait {
+    ($name:ident) => {
+        impl $crate::Report for $name {
+            fn serialize(&self, writer: &mut $crate::csv::Writer<std::fs::File>) {
+                writer.serialize(self).unwrap();
+            }
+        }
+    };
+}
+#[allow(unused_imports)]
+pub use create_report_trait;
+
+/// The default size in bytes of each report's write buffer.
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Options controlling where report files are written.
+pub struct ConfigReportOptions {
+    file_prefix: String,
+    output_dir: PathBuf,
+    overwrite: bool,
+    buffer_size: usize,
+}
+
+impl ConfigReportOptions {
+    /// The default options write to the current directory with no file prefix and refuse to
+    /// overwrite existing files.
+    #[must_use]
+    pub fn new() -> Self {
+        ConfigReportOptions {
+            file_prefix: String::new(),
+            output_dir: PathBuf::from("."),
+            overwrite: false,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+
+    /// Sets a prefix prepended to the name of every report file.
+    pub fn file_prefix(&mut self, file_prefix: String) -> &mut ConfigReportOptions {
+        self.file_prefix = file_prefix;
+        self
+    }
+
+    /// Sets the directory report files are written to.
+    pub fn directory(&mut self, directory: PathBuf) -> &mut ConfigReportOptions {
+        self.output_dir = directory;
+        self
+    }
+
+    /// Sets whether existing report files are overwritten. If `false`, adding a report whose
+    /// file already exists is an error.
+    pub fn overwrite(&mut self, overwrite: bool) -> &mut ConfigReportOptions {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Sets the size in bytes of each report's write buffer. Rows are written to the file once
+    /// the buffer is full. Defaults to 8 KiB.
+    pub fn buffer_size(&mut self, buffer_size: usize) -> &mut ConfigReportOptions {
+        self.buffer_size = buffer_size;
+        self
+    }
+}
+
+impl Default for ConfigReportOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A row sent during a transaction, kept until the transaction completes.
+type PendingRow = (String, Box<dyn FnOnce(&mut Writer<File>)>);
+
+pub(crate) struct ReportData {
+    /// Maps each report's name to its writer.
+    file_writers: HashMap<String, Writer<File>>,
+    /// Maps each report item type to the name of the report `send_report` writes it to.
+    default_reports: HashMap<TypeId, String>,
+    /// The rows sent during the current transaction, or `None` outside of a transaction.
+    pending_rows: Option<Vec<PendingRow>>,
+    config: ConfigReportOptions,
+}
+
+impl DataPlugin for ReportData {
+    #[allow(non_upper_case_globals)]
+    const new: &'static dyn Fn() -> Self = &|| {
+        ReportData {
+            file_writers: HashMap::default(),
+            default_reports: HashMap::default(),
+            pending_rows: None,
+            config: ConfigReportOptions::new(),
+        }
+    };
+
+    fn on_context_drop(&mut self) {
+        for writer in self.file_writers.values_mut() {
+            if let Err(e) = writer.flush() {
+                error!("Failed to flush report: {e}");
+            }
+        }
+    }
+}
+
+impl ReportData {
+    /// Creates the CSV writer for the report named `short_name`, respecting the configured
+    /// directory, file prefix, overwrite setting, and buffer size.
+    pub(crate) fn create_writer(&self, short_name: &str) -> Result<Writer<File>, IxaError> {
+        let path = self
+            .config
+            .output_dir
+            .join(format!("{}{short_name}.csv", self.config.file_prefix));
+        if !self.config.overwrite && path.exists() {
+            return Err(IxaError::ReportFileExists(path));
+        }
+        trace!("creating report file {}", path.display());
+        Ok(WriterBuilder::new()
+            .buffer_capacity(self.config.buffer_size)
+            .from_path(path)?)
+    }
+
+    /// Writes `report` to the report named `short_name`, or holds it back if a transaction is
+    /// in progress.
+    fn write_row<T: Report>(&mut self, short_name: &str, report: T) {
+        assert!(
+            self.file_writers.contains_key(short_name),
+            "No report named {short_name}"
+        );
+        match &mut self.pending_rows {
+            Some(pending_rows) => pending_rows.push((
+                short_name.to_string(),
+                Box::new(move |writer| report.serialize(writer)),
+            )),
+            None => report.serialize(self.file_writers.get_mut(short_name).unwrap()),
+        }
+    }
+}
+
+pub trait ContextReportExt {
+    /// Creates the file for the report with item type `T`, named `short_name`. This report
+    /// becomes the one `send_report()` writes items of type `T` to.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the file already exists and `overwrite` is not set, or if the
+    /// file can't be created.
+    fn add_report<T: Report>(&mut self, short_name: &str) -> Result<(), IxaError>;
+
+    /// Writes `report` as a row of the report most recently added for its type.
+    ///
+    /// # Panics
+    /// Panics if no report was added for `T` or if the row can't be written.
+    fn send_report<T: Report>(&mut self, report: T);
+
+    /// Writes `report` as a row of the report named `short_name`.
+    ///
+    /// # Panics
+    /// Panics if no report named `short_name` was added or if the row can't be written.
+    fn send_report_to<T: Report>(&mut self, short_name: &str, report: T);
+
+    /// Returns the options used to name report files. Set them before adding any reports.
+    fn report_options(&mut self) -> &mut ConfigReportOptions;
+
+    /// Writes the buffered rows of every report to their files.
+    ///
+    /// # Panics
+    /// Panics if a report file can't be written.
+    fn flush_reports(&mut self);
+
+    /// Runs `f` as a transaction: the report rows it sends are written and flushed together if
+    /// it returns `Ok` and discarded if it returns `Err`, so a group of rows is recorded in full
+    /// or not at all. Transactions can be nested, in which case the rows of an inner transaction
+    /// are written when the outermost one succeeds.
+    ///
+    /// # Errors
+    /// Returns the error returned by `f`.
+    fn report_transaction(
+        &mut self,
+        f: impl FnOnce(&mut Context) -> Result<(), IxaError>,
+    ) -> Result<(), IxaError>;
+}
+
+impl ContextReportExt for Context {
+    fn add_report<T: Report>(&mut self, short_name: &str) -> Result<(), IxaError> {
+        let report_data = self.get_data_container_mut::<ReportData>();
+        let writer = report_data.create_writer(short_name)?;
+        report_data
+            .file_writers
+            .insert(short_name.to_string(), writer);
+        report_data
+            .default_reports
+            .insert(type_of::<T>(), short_name.to_string());
+        Ok(())
+    }
+
+    fn send_report<T: Report>(&mut self, report: T) {
+        let report_data = self.get_data_container_mut::<ReportData>();
+        let short_name = report_data
+            .default_reports
+            .get(&type_of::<T>())
+            .expect("No report added for this type")
+            .clone();
+        report_data.write_row(&short_name, report);
+    }
+
+    fn send_report_to<T: Report>(&mut self, short_name: &str, report: T) {
+        self.get_data_container_mut::<ReportData>()
+            .write_row(short_name, report);
+    }
+
+    fn report_options(&mut self) -> &mut ConfigReportOptions {
+        &mut self.get_data_container_mut::<ReportData>().config
+    }
+
+    fn flush_reports(&mut self) {
+        // Don't create the report plugin just to flush nothing.
+        if self.get_data_container::<ReportData>().is_none() {
+            return;
+        }
+        for writer in self
+            .get_data_container_mut::<ReportData>()
+            .file_writers
+            .values_mut()
+        {
+            writer.flush().expect("Failed to flush report");
+        }
+    }
+
+    fn report_transaction(
+        &mut self,
+        f: impl FnOnce(&mut Context) -> Result<(), IxaError>,
+    ) -> Result<(), IxaError> {
+        let report_data = self.get_data_container_mut::<ReportData>();
+        let is_outermost = report_data.pending_rows.is_none();
+        let start = report_data.pending_rows.get_or_insert_with(Vec::new).len();
+
+        let result = f(self);
+
+        let report_data = self.get_data_container_mut::<ReportData>();
+        if result.is_err() {
+            // Will not panic, as `pending_rows` is only reset by the outermost transaction.
+            report_data.pending_rows.as_mut().unwrap().truncate(start);
+        }
+        if is_outermost {
+            let pending_rows = report_data.pending_rows.take().unwrap();
+            if !pending_rows.is_empty() {
+                for (short_name, write) in pending_rows {
+                    write(report_data.file_writers.get_mut(&short_name).unwrap());
+                }
+                self.flush_reports();
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContextPlanExt;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct SampleReport {
+        id: u32,
+        value: String,
+    }
+
+    create_report_trait!(SampleReport);
+
+    #[test]
+    fn send_report_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("prefix_sample.csv");
+        {
+            let mut context = Context::new();
+            context
+                .report_options()
+                .directory(temp_dir.path().to_path_buf())
+                .file_prefix("prefix_".to_string());
+            context.add_report::<SampleReport>("sample").unwrap();
+            for id in 0..3 {
+                context.send_report(SampleReport {
+                    id,
+                    value: format!("row {id}"),
+                });
+            }
+        }
+
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["id", "value"]);
+        let rows: Vec<SampleReport> = reader.deserialize().map(Result::unwrap).collect();
+        assert_eq!(
+            rows,
+            (0..3)
+                .map(|id| SampleReport {
+                    id,
+                    value: format!("row {id}"),
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    fn count_rows(path: &std::path::Path) -> usize {
+        csv::Reader::from_path(path).unwrap().records().count()
+    }
+
+    #[test]
+    fn flush_reports_writes_buffered_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sample.csv");
+        let mut context = Context::new();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf())
+            .buffer_size(1 << 20);
+        context.add_report::<SampleReport>("sample").unwrap();
+
+        for id in 0..3 {
+            context.send_report(SampleReport {
+                id,
+                value: format!("row {id}"),
+            });
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        context.flush_reports();
+        assert_eq!(count_rows(&path), 3);
+
+        for id in 3..5 {
+            context.add_plan(f64::from(id), move |context| {
+                context.send_report(SampleReport {
+                    id,
+                    value: format!("row {id}"),
+                });
+            });
+        }
+        context.add_plan(10.0, |context| context.shutdown());
+        context.execute();
+        assert_eq!(count_rows(&path), 5);
+    }
+
+    #[test]
+    fn send_report_to_named_reports() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut context = Context::new();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf());
+        context.add_report::<SampleReport>("week1").unwrap();
+        context.add_report::<SampleReport>("week2").unwrap();
+
+        context.send_report_to("week1", SampleReport { id: 1, value: "first".to_string() });
+        context.send_report_to("week2", SampleReport { id: 2, value: "second".to_string() });
+        // `send_report` writes to the report added last.
+        context.send_report(SampleReport { id: 3, value: "third".to_string() });
+        context.flush_reports();
+
+        let read = |name: &str| -> Vec<SampleReport> {
+            csv::Reader::from_path(temp_dir.path().join(name))
+                .unwrap()
+                .deserialize()
+                .map(Result::unwrap)
+                .collect()
+        };
+        assert_eq!(read("week1.csv"), vec![SampleReport { id: 1, value: "first".to_string() }]);
+        assert_eq!(
+            read("week2.csv"),
+            vec![
+                SampleReport { id: 2, value: "second".to_string() },
+                SampleReport { id: 3, value: "third".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "No report named week3")]
+    fn send_report_to_unknown_name() {
+        let mut context = Context::new();
+        context.send_report_to("week3", SampleReport { id: 0, value: String::new() });
+    }
+
+    #[test]
+    fn report_transaction() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sample.csv");
+        let mut context = Context::new();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf())
+            .buffer_size(1 << 20);
+        context.add_report::<SampleReport>("sample").unwrap();
+
+        let result = context.report_transaction(|context| {
+            context.send_report(SampleReport { id: 0, value: "kept".to_string() });
+            context.send_report(SampleReport { id: 1, value: "kept".to_string() });
+            Ok(())
+        });
+        assert!(result.is_ok());
+        // The rows are flushed when the transaction succeeds.
+        assert_eq!(count_rows(&path), 2);
+
+        let result = context.report_transaction(|context| {
+            context.send_report(SampleReport { id: 2, value: "discarded".to_string() });
+            Err(IxaError::from("transition failed"))
+        });
+        assert!(matches!(result, Err(IxaError::IxaError(_))));
+        context.flush_reports();
+        assert_eq!(count_rows(&path), 2);
+    }
+
+    #[test]
+    fn nested_report_transaction() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sample.csv");
+        let mut context = Context::new();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf())
+            .buffer_size(1 << 20);
+        context.add_report::<SampleReport>("sample").unwrap();
+
+        context
+            .report_transaction(|context| {
+                context.send_report(SampleReport { id: 0, value: "outer".to_string() });
+                let inner = context.report_transaction(|context| {
+                    context.send_report(SampleReport { id: 1, value: "inner".to_string() });
+                    Err(IxaError::from("inner failed"))
+                });
+                assert!(inner.is_err());
+                context.send_report(SampleReport { id: 2, value: "outer".to_string() });
+                Ok(())
+            })
+            .unwrap();
+
+        let ids: Vec<u32> = csv::Reader::from_path(&path)
+            .unwrap()
+            .deserialize::<SampleReport>()
+            .map(|row| row.unwrap().id)
+            .collect();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn existing_file_without_overwrite() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("sample.csv"), "id,value\n").unwrap();
+
+        let mut context = Context::new();
+        context
+            .report_options()
+            .directory(temp_dir.path().to_path_buf());
+        let result = context.add_report::<SampleReport>("sample");
+        assert!(matches!(
+            result,
+            Err(IxaError::ReportFileExists(path)) if path == temp_dir.path().join("sample.csv")
+        ));
+
+        context.report_options().overwrite(true);
+        context.add_report::<SampleReport>("sample").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "No report added")]
+    fn send_report_without_add() {
+        let mut context = Context::new();
+        context.send_report(SampleReport {
+            id: 0,
+            value: String::new(),
+        });
+    }
+}
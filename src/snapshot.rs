@@ -0,0 +1,295 @@
+//! Snapshots of a `Context` that can be restored into a new `Context`, so that a long run can be
+//! checkpointed and resumed.
+//!
+//! A snapshot holds the entities of `DefaultEntity`, the value of each property that has opted
+//! in with [`define_snapshot_property!()`], the base seed, and the state of each random number
+//! generator stream that can be exported with `ContextRandomExt::rng_state_blobs()`. Properties
+//! that haven't opted in, derived properties, global properties, plans, and other data plugins
+//! are not included.
+//!
+//! ```ignore
+//! #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+//! struct Age(u8);
+//! impl Property for Age {}
+//! define_snapshot_property!(Age);
+//!
+//! let bytes = context.snapshot()?;
+//! let mut resumed = Context::restore(&bytes)?;
+//! ```
+use crate::{
+    context::Context,
+    entity::EntityData,
+    property::Property,
+    random::ContextRandomExt,
+    trace,
+    EntityId,
+    HashMap,
+    IxaError,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::{LazyLock, Mutex};
+
+/// Serializes the values of one property type for every entity, including `None` for entities
+/// with no value.
+type SaveFn = fn(&Context) -> Result<serde_json::Value, IxaError>;
+
+/// Registers one property type and sets the values saved by its `SaveFn`.
+type LoadFn = fn(&mut Context, serde_json::Value) -> Result<(), IxaError>;
+
+#[derive(Copy, Clone)]
+struct SnapshotPropertyFns {
+    save: SaveFn,
+    load: LoadFn,
+}
+
+// A global list of the properties that have opted into snapshots, keyed by property name, so
+// that `Context::restore` can find them for a context that has never registered them.
+static SNAPSHOT_PROPERTIES: LazyLock<Mutex<HashMap<&'static str, SnapshotPropertyFns>>> =
+    LazyLock::new(|| Mutex::new(HashMap::default()));
+
+fn save_values<T: Property + Serialize>(context: &Context) -> Result<serde_json::Value, IxaError> {
+    // Will not panic, as only properties registered with the context are saved.
+    let entity_data = context.get_data_container::<EntityData>().unwrap();
+    let values: Vec<Option<&T>> = (0..entity_data.entity_count)
+        .map(|idx| entity_data.get_property_ref::<T>(EntityId(idx)))
+        .collect();
+    Ok(serde_json::to_value(values)?)
+}
+
+fn load_values<T: Property + DeserializeOwned>(
+    context: &mut Context,
+    values: serde_json::Value,
+) -> Result<(), IxaError> {
+    T::register(context);
+    let values: Vec<Option<T>> = serde_json::from_value(values)?;
+    let entity_data = context.get_data_container_mut::<EntityData>();
+    for (idx, value) in values.into_iter().enumerate() {
+        if let Some(value) = value {
+            entity_data.set_property(EntityId(idx), value);
+        }
+    }
+    Ok(())
+}
+
+/// Opts the property `T` into snapshots. Use [`define_snapshot_property!()`] rather than calling
+/// this directly, so that the property is registered before `main` runs.
+#[allow(clippy::missing_panics_doc)]
+pub fn add_snapshot_property<T: Property + Serialize + DeserializeOwned>() {
+    trace!("Adding snapshot property {}", T::name());
+    SNAPSHOT_PROPERTIES.lock().unwrap().insert(
+        T::name(),
+        SnapshotPropertyFns {
+            save: save_values::<T>,
+            load: load_values::<T>,
+        },
+    );
+}
+
+fn get_snapshot_property_fns(name: &str) -> Option<SnapshotPropertyFns> {
+    SNAPSHOT_PROPERTIES.lock().unwrap().get(name).copied()
+}
+
+/// Opts a nonderived property into `Context::snapshot()`. The property type must implement
+/// `Serialize` and `Deserialize`.
+#[macro_export]
+macro_rules! define_snapshot_property {
+    ($property:ident) => {
+        $crate::paste::paste! {
+            #[$crate::ctor::ctor]
+            fn [<$property:snake _register_snapshot>]() {
+                $crate::snapshot::add_snapshot_property::<$property>();
+            }
+        }
+    };
+}
+#[allow(unused_imports)]
+pub use define_snapshot_property;
+
+/// The entities of `DefaultEntity` and the values of the properties that have opted into
+/// snapshots. Shared by `Context::snapshot()` and checkpoints.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct EntitySnapshot {
+    entity_count: usize,
+    removed_entities: Vec<usize>,
+    /// The name of each saved property with the values saved by its `SaveFn`.
+    properties: Vec<(String, serde_json::Value)>,
+}
+
+impl EntitySnapshot {
+    /// Saves the entities of `context` and the values of every nonderived property that has
+    /// opted in with `define_snapshot_property!`. Other properties are skipped, and their names
+    /// are passed to `skipped`.
+    pub(crate) fn save(
+        context: &Context,
+        mut skipped: impl FnMut(&str),
+    ) -> Result<EntitySnapshot, IxaError> {
+        let mut entity_snapshot = EntitySnapshot::default();
+        let Some(entity_data) = context.get_data_container::<EntityData>() else {
+            return Ok(entity_snapshot);
+        };
+        entity_snapshot.entity_count = entity_data.entity_count;
+        entity_snapshot.removed_entities =
+            entity_data.removed_entities.iter().map(|entity_id| entity_id.0).collect();
+        for property_info in &entity_data.property_metadata {
+            if property_info.is_derived() {
+                continue;
+            }
+            let Some(fns) = get_snapshot_property_fns(property_info.name()) else {
+                skipped(property_info.name());
+                continue;
+            };
+            entity_snapshot
+                .properties
+                .push((property_info.name().to_string(), (fns.save)(context)?));
+        }
+        Ok(entity_snapshot)
+    }
+
+    /// Replaces the entities of `context` and their property values with the saved ones. Indexes
+    /// are rebuilt on the next query, and properties that weren't saved are left without values.
+    ///
+    /// # Errors
+    /// Returns `IxaError::PropertyNotRegistered`, leaving `context` unchanged, if a saved property
+    /// hasn't opted in with `define_snapshot_property!`, or an `IxaError` if saved values can't
+    /// be deserialized.
+    pub(crate) fn load(&self, context: &mut Context) -> Result<(), IxaError> {
+        let mut load_fns = Vec::with_capacity(self.properties.len());
+        for (name, values) in &self.properties {
+            let fns = get_snapshot_property_fns(name)
+                .ok_or_else(|| IxaError::PropertyNotRegistered(name.clone()))?;
+            load_fns.push((fns.load, values));
+        }
+
+        let entity_data = context.get_data_container_mut::<EntityData>();
+        entity_data.clear_entities();
+        entity_data.create_entities(self.entity_count);
+        entity_data.removed_entities =
+            self.removed_entities.iter().copied().map(EntityId).collect();
+        for (load, values) in load_fns {
+            load(context, values.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    #[serde(flatten)]
+    entities: EntitySnapshot,
+    base_seed: Option<u64>,
+    rng_states: Vec<(String, Vec<u8>)>,
+}
+
+impl Context {
+    /// Serializes the entities, the values of properties that opted in with
+    /// `define_snapshot_property!`, and the random number generator state, so that they can be
+    /// restored with `Context::restore()`. Only the entities of `DefaultEntity` are saved;
+    /// entities of other kinds are left out.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if a value can't be serialized.
+    pub fn snapshot(&self) -> Result<Vec<u8>, IxaError> {
+        let snapshot = Snapshot {
+            entities: EntitySnapshot::save(self, |_| {})?,
+            base_seed: self.base_seed(),
+            rng_states: self.rng_state_blobs(),
+        };
+        Ok(serde_json::to_vec(&snapshot)?)
+    }
+
+    /// Creates a new `Context` from a snapshot made with `Context::snapshot()`. The properties
+    /// in the snapshot are registered with the new context, but derived properties, indexes,
+    /// and event handlers must be set up again.
+    ///
+    /// # Errors
+    /// Returns an `IxaError` if the snapshot is malformed, or
+    /// `IxaError::PropertyNotRegistered` if it holds a property that hasn't opted in with
+    /// `define_snapshot_property!`.
+    pub fn restore(bytes: &[u8]) -> Result<Context, IxaError> {
+        let snapshot: Snapshot = serde_json::from_slice(bytes)?;
+        let mut context = Context::new();
+
+        if let Some(base_seed) = snapshot.base_seed {
+            context.init_random(base_seed);
+        }
+        context.restore_rng_state_blobs(snapshot.rng_states)?;
+        snapshot.entities.load(&mut context)?;
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{define_derived_property, define_rng, entity::ContextEntityExt};
+    use rand::RngCore;
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    struct Age(u8);
+    impl Property for Age {}
+    define_snapshot_property!(Age);
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    enum RiskCategory {
+        High,
+        Low,
+    }
+    impl Property for RiskCategory {}
+    define_snapshot_property!(RiskCategory);
+
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    struct Senior(bool);
+    define_derived_property!(Senior, [Age], |age| {
+        let Age(years) = age;
+        Some(Senior(years >= 65))
+    });
+
+    define_rng!(SnapshotRng);
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut context = Context::new();
+        context.init_random(42);
+        for years in [10, 70, 30, 80, 65] {
+            context.add_entity((Age(years), RiskCategory::Low)).unwrap();
+        }
+        let high_risk = context.add_entity((Age(50), RiskCategory::High)).unwrap();
+        context.add_entity(Age(90)).unwrap();
+        context.remove_entity(EntityId(2)).unwrap();
+        context.sample::<SnapshotRng, _>(RngCore::next_u64);
+
+        let bytes = context.snapshot().unwrap();
+        let mut resumed = Context::restore(&bytes).unwrap();
+
+        assert_eq!(resumed.get_entity_count(), context.get_entity_count());
+        assert!(!resumed.entity_exists(EntityId(2)));
+        assert_eq!(resumed.get_property::<RiskCategory>(high_risk), Some(RiskCategory::High));
+        for query_result in [
+            |context: &mut Context| context.query_entities(Senior(true)),
+            |context: &mut Context| context.query_entities((Age(30), RiskCategory::Low)),
+            |context: &mut Context| context.query_entities(RiskCategory::Low),
+        ] {
+            assert_eq!(query_result(&mut resumed), query_result(&mut context));
+        }
+        assert_eq!(
+            resumed.sample::<SnapshotRng, _>(RngCore::next_u64),
+            context.sample::<SnapshotRng, _>(RngCore::next_u64)
+        );
+    }
+
+    #[test]
+    fn restore_unregistered_property() {
+        let snapshot = Snapshot {
+            entities: EntitySnapshot {
+                entity_count: 1,
+                removed_entities: Vec::new(),
+                properties: vec![("Unknown".to_string(), serde_json::Value::Array(Vec::new()))],
+            },
+            base_seed: None,
+            rng_states: Vec::new(),
+        };
+        let bytes = serde_json::to_vec(&snapshot).unwrap();
+        let error = Context::restore(&bytes).err().unwrap();
+        assert!(matches!(error, IxaError::PropertyNotRegistered(name) if name == "Unknown"));
+    }
+}
@@ -0,0 +1,180 @@
+//! A one-shot readout of the state of a simulation, meant to be printed once a run has finished.
+//! [`ContextSummaryExt::summary()`] collects the population, the number of entities with each
+//! value of every indexed property, how many plans ran, how many events were emitted, and how
+//! much simulation time elapsed. [`ContextSummaryExt::print_summary()`] prints the same
+//! information to standard output.
+
+use crate::{
+    context::Context,
+    entity::{ContextEntityExt, DefaultEntity, EntityData, EntityKind},
+    event::ContextEventExt,
+    plans::ContextPlanExt,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+/// The state of a simulation at the time `ContextSummaryExt::summary()` was called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    /// The number of entities, not counting removed entities.
+    pub population: usize,
+    /// For each indexed property, keyed by property name, the number of entities with each
+    /// value, keyed by the value formatted with `Property::to_report_value`.
+    pub index_counts: BTreeMap<String, BTreeMap<String, usize>>,
+    /// The number of plans that have run.
+    pub plans_executed: usize,
+    /// The number of events that have been emitted.
+    pub events_emitted: usize,
+    /// The current simulation time. Simulations start at time 0.
+    pub elapsed_time: f64,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Simulation summary")?;
+        writeln!(f, "  elapsed time: {}", self.elapsed_time)?;
+        writeln!(f, "  plans executed: {}", self.plans_executed)?;
+        writeln!(f, "  events emitted: {}", self.events_emitted)?;
+        writeln!(f, "  population: {}", self.population)?;
+        for (property_name, counts) in &self.index_counts {
+            writeln!(f, "  {property_name}:")?;
+            for (value, count) in counts {
+                writeln!(f, "    {value}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub trait ContextSummaryExt {
+    /// Collects a `Summary` of the current state of the simulation. Indexes are brought up to
+    /// date first, so the counts include every entity. The population and index counts are of
+    /// the entities of `DefaultEntity`.
+    fn summary(&self) -> Summary;
+
+    /// Like `summary()`, but the population and index counts are of the entities of kind `K`.
+    fn summary_of<K: EntityKind>(&self) -> Summary;
+
+    /// Prints the `Summary` of the current state of the simulation to standard output.
+    fn print_summary(&self);
+}
+
+impl ContextSummaryExt for Context {
+    fn summary(&self) -> Summary {
+        self.summary_of::<DefaultEntity>()
+    }
+
+    fn summary_of<K: EntityKind>(&self) -> Summary {
+        let mut index_counts = BTreeMap::new();
+        if let Some(entity_data) = self.get_data_container::<EntityData<K>>() {
+            let bucket_sizes = entity_data
+                .property_indexes
+                .borrow_mut()
+                .all_bucket_sizes(self);
+            for (property_name, sizes) in bucket_sizes {
+                // Every indexed property is registered, so it has a report value function.
+                let report_value = entity_data.report_value_fns[property_name];
+                let counts: BTreeMap<String, usize> = sizes
+                    .into_iter()
+                    .filter_map(|(entity_id, count)| Some((report_value(self, entity_id)?, count)))
+                    .collect();
+                index_counts.insert(property_name.to_string(), counts);
+            }
+        }
+
+        Summary {
+            population: self.get_entity_count_of::<K>(),
+            index_counts,
+            plans_executed: self.plans_executed(),
+            events_emitted: self.events_emitted(),
+            elapsed_time: self.get_current_time(),
+        }
+    }
+
+    fn print_summary(&self) {
+        print!("{}", self.summary());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event::ContextEventExt, property::Property};
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+    enum InfectionStatus {
+        S,
+        I,
+    }
+    impl Property for InfectionStatus {
+        fn name() -> &'static str {
+            "InfectionStatus"
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct Infection;
+
+    #[test]
+    fn summary_of_completed_run() {
+        let mut context = Context::new();
+        context.index_property::<InfectionStatus>();
+        for _ in 0..3 {
+            context.add_entity(InfectionStatus::S).unwrap();
+        }
+        let patient_zero = context.add_entity(InfectionStatus::S).unwrap();
+        context.add_plan(2.5, move |context| {
+            context.set_property(patient_zero, InfectionStatus::I).unwrap();
+            context.emit_event(Infection);
+        });
+        context.add_plan(7.0, |_| {});
+        context.execute();
+
+        let summary = context.summary();
+        assert_eq!(summary.population, 4);
+        assert_eq!(summary.plans_executed, 2);
+        // Four `EntityCreatedEvent`s, the property change and the `Infection` event.
+        assert_eq!(summary.events_emitted, 6);
+        assert_eq!(summary.elapsed_time, 7.0);
+        let status_counts = &summary.index_counts["InfectionStatus"];
+        assert_eq!(status_counts.len(), 2);
+        assert_eq!(status_counts["S"], 3);
+        assert_eq!(status_counts["I"], 1);
+
+        assert_eq!(
+            summary.to_string(),
+            "Simulation summary\n  elapsed time: 7\n  plans executed: 2\n  events emitted: 6\n  \
+             population: 4\n  InfectionStatus:\n    I: 1\n    S: 3\n"
+        );
+    }
+
+    #[test]
+    fn summary_of_empty_context() {
+        let summary = Context::new().summary();
+        assert_eq!(summary.population, 0);
+        assert!(summary.index_counts.is_empty());
+        assert_eq!(summary.plans_executed, 0);
+        assert_eq!(summary.events_emitted, 0);
+        assert_eq!(summary.elapsed_time, 0.0);
+    }
+
+    struct Household;
+    impl EntityKind for Household {}
+
+    #[test]
+    fn summary_of_entity_kind() {
+        let mut context = Context::new();
+        context.add_entity(InfectionStatus::S).unwrap();
+        context.index_property_of::<Household, InfectionStatus>();
+        for _ in 0..2 {
+            context.add_entity_of::<Household, _>(InfectionStatus::I).unwrap();
+        }
+
+        let summary = context.summary_of::<Household>();
+        assert_eq!(summary.population, 2);
+        assert_eq!(summary.index_counts["InfectionStatus"]["I"], 2);
+        assert!(context.summary().index_counts.is_empty());
+    }
+}
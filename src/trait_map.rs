@@ -62,6 +62,17 @@ impl TraitMap {
         )
   }
   
+  /// Returns the value of type `T`, first inserting the value returned by `f` if there is none.
+  /// `f` is only called when the value is inserted.
+  pub fn get_or_insert_with<T: Any>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+    let boxed = self.map
+        .entry(type_of::<T>())
+        .or_insert_with(|| Box::new(f()));
+    // ToDo: Use `Any::downcast_mut_unchecked` (nightly feature).
+    // Guaranteed safe, as only a Box<T> can be a value for `type_of::<T>()`.
+    unsafe { boxed.downcast_mut().unwrap_unchecked() }
+  }
+
   pub fn contains_key<T: Any>(&self) -> bool {
     self.map.contains_key(&type_of::<T>())
   }
@@ -79,4 +90,59 @@ impl TraitMap {
   pub fn clear(&mut self) {
     self.map.clear();
   }
+
+  /// Iterates over the `TypeId` of each stored value, in no particular order.
+  pub fn keys(&self) -> impl Iterator<Item = &TypeId> {
+    self.map.keys()
+  }
+
+  /// Calls `f` with the `TypeId` of each stored value and the value itself, in no particular
+  /// order. `f` can recover the value with `downcast_mut()`.
+  pub fn for_each_mut(&mut self, mut f: impl FnMut(TypeId, &mut dyn Any)) {
+    for (type_id, boxed) in &mut self.map {
+      f(*type_id, boxed.as_mut());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn get_or_insert_with_calls_closure_once() {
+    let mut map = TraitMap::new();
+    let mut calls = 0;
+    *map.get_or_insert_with(|| { calls += 1; 1u32 }) += 1;
+    *map.get_or_insert_with(|| { calls += 1; 1u32 }) += 1;
+
+    assert_eq!(calls, 1);
+    assert_eq!(map.get::<u32>(), Some(&3));
+  }
+
+  #[test]
+  fn iterate_values() {
+    let mut map = TraitMap::new();
+    map.insert(1u32);
+    map.insert(String::from("a"));
+
+    let mut keys: Vec<TypeId> = map.keys().copied().collect();
+    keys.sort();
+    let mut expected = vec![type_of::<u32>(), type_of::<String>()];
+    expected.sort();
+    assert_eq!(keys, expected);
+
+    let mut visited = Vec::new();
+    map.for_each_mut(|type_id, value| {
+      visited.push(type_id);
+      if let Some(value) = value.downcast_mut::<u32>() {
+        *value += 1;
+      } else if let Some(value) = value.downcast_mut::<String>() {
+        value.push('b');
+      }
+    });
+    assert_eq!(visited.len(), 2);
+    assert_eq!(map.get::<u32>(), Some(&2));
+    assert_eq!(map.get::<String>().map(String::as_str), Some("ab"));
+  }
 }